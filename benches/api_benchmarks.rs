@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId};
-use codestral_test_rs::{Config, models::{CompletionRequest, ChatMessage, ChatCompletionRequest}};
+use codestral_test_rs::{models::{CompletionRequest, ChatMessage, ChatCompletionRequest}};
 use serde_json::json;
 use codestral_test_rs::api::extract_response_fields;
 
@@ -13,6 +13,7 @@ fn bench_serialization(c: &mut Criterion) {
         suffix: "}".to_string(),
         max_tokens: 100,
         temperature: 0.0,
+        ..Default::default()
     };
     
     group.bench_function("serialize_completion_request", |b| {
@@ -25,6 +26,7 @@ fn bench_serialization(c: &mut Criterion) {
             ChatMessage {
                 role: "user".to_string(),
                 content: "Hello, how are you?".to_string(),
+                ..Default::default()
             };
             msg_count
         ];
@@ -34,6 +36,7 @@ fn bench_serialization(c: &mut Criterion) {
             messages,
             max_tokens: Some(100),
             temperature: 0.7,
+            ..Default::default()
         };
         
         group.bench_with_input(
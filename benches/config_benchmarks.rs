@@ -8,7 +8,7 @@ fn bench_config_load_save(c: &mut Criterion) {
     
     // Benchmark default config creation
     group.bench_function("default_config", |b| {
-        b.iter(|| Config::default())
+        b.iter(Config::default)
     });
     
     // Prepare a test config file
@@ -39,9 +39,11 @@ fn bench_config_serialization(c: &mut Criterion) {
     let mut group = c.benchmark_group("Config Serialization");
     
     for model_size in ["small", "medium", "large", "extra-large"] {
-        let mut config = Config::default();
-        config.code_model = format!("codestral-{}", model_size);
-        config.chat_model = format!("mistral-{}", model_size);
+        let config = Config {
+            code_model: format!("codestral-{}", model_size),
+            chat_model: format!("mistral-{}", model_size),
+            ..Config::default()
+        };
         
         group.bench_with_input(
             BenchmarkId::new("serialize", model_size), 
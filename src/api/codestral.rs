@@ -1,19 +1,26 @@
 use crate::Config;
 use crate::api::common::{ApiClient, get_api_key, make_api_request};
 use crate::api::progress::with_progress;
-use crate::models::CompletionRequest;
+use crate::daemon::try_post;
+use crate::models::{CompletionRequest, SamplingParams};
 use crate::error::Result;
 use async_trait::async_trait;
+use futures::future::try_join_all;
 use reqwest::Client;
+use std::path::PathBuf;
+use std::time::Duration;
 use serde_json::{json, Value};
 use log::debug;
 
 pub struct CodestralClient {
     model: String,
     temperature: f64,
+    sampling: SamplingParams,
     api_url: String,
     api_key: String,
     client: Client,
+    daemon_socket: Option<PathBuf>,
+    daemon_timeout: Duration,
 }
 
 impl CodestralClient {
@@ -23,24 +30,25 @@ impl CodestralClient {
         Ok(Self {
             model: config.code_model.clone(),
             temperature: config.code_temperature,
+            sampling: config.sampling_params(),
             api_url: config.code_api_url.clone(),
             api_key,
             client: Client::new(),
+            daemon_socket: config.use_daemon.then(|| config.get_daemon_socket_path()),
+            daemon_timeout: Duration::from_secs(config.daemon_timeout),
         })
     }
     
+    /// Override the sampling parameters taken from the configuration
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+    
     pub async fn code_completion(&self, prompt: &str, suffix: &str, max_tokens: u32) -> Result<Value> {
         debug!("Sending request to Codestral API for code completion");
         
-        let request = CompletionRequest {
-            model: self.model.clone(),
-            prompt: prompt.to_string(),
-            suffix: suffix.to_string(),
-            max_tokens,
-            temperature: self.temperature,
-        };
-        
-        let request_json = json!(request);
+        let request_json = json!(self.build_request(prompt, suffix, max_tokens));
         
         // Use progress tracking for the API request
         with_progress(
@@ -49,11 +57,43 @@ impl CodestralClient {
             self.send_request(request_json)
         ).await
     }
+    
+    /// Send one completion request per seed in parallel, returning the raw responses in seed order
+    pub async fn code_completion_with_seeds(&self, prompt: &str, suffix: &str, max_tokens: u32, seeds: &[u64]) -> Result<Vec<Value>> {
+        debug!("Sending {} parallel code completion requests", seeds.len());
+        
+        let requests = seeds.iter().map(|seed| {
+            let mut request = self.build_request(prompt, suffix, max_tokens);
+            request.sampling.random_seed = Some(*seed);
+            request.sampling.n = None;
+            self.send_request(json!(request))
+        });
+        
+        with_progress(
+            &format!("Generating {} completions with model '{}'...", seeds.len(), self.model),
+            "Code completions received!",
+            try_join_all(requests)
+        ).await
+    }
+    
+    fn build_request(&self, prompt: &str, suffix: &str, max_tokens: u32) -> CompletionRequest {
+        CompletionRequest {
+            model: self.model.clone(),
+            prompt: prompt.to_string(),
+            suffix: suffix.to_string(),
+            max_tokens,
+            temperature: self.temperature,
+            sampling: self.sampling.clone(),
+        }
+    }
 }
 
 #[async_trait]
 impl ApiClient for CodestralClient {
     async fn send_request(&self, request_body: Value) -> Result<Value> {
+        if let Some(response) = try_post(self.daemon_socket.as_deref(), self.daemon_timeout, &self.api_url, &self.api_key, &request_body).await {
+            return response;
+        }
         make_api_request(&self.client, &self.api_url, &self.api_key, request_body).await
     }
 }
@@ -1,4 +1,5 @@
 use crate::error::{AppError, Result, ErrorExt};
+use crate::models::{ChatMessage, ToolCall};
 use serde_json::Value;
 use reqwest::{Client, header};
 use std::env;
@@ -27,6 +28,23 @@ pub async fn make_api_request(
         .await
         .with_context(|| format!("Failed to send request to {}", url))?;
     
+    parse_api_response(response).await
+}
+
+/// Make a GET request to the specified URL
+pub async fn make_get_request(client: &Client, url: &str, api_key: &str) -> Result<Value> {
+    let response = client
+        .get(url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {}", url))?;
+    
+    parse_api_response(response).await
+}
+
+/// Check the response status and parse the JSON body
+async fn parse_api_response(response: reqwest::Response) -> Result<Value> {
     // Check if response is successful
     if !response.status().is_success() {
         let status = response.status();
@@ -56,22 +74,13 @@ pub fn extract_response_fields(response: &Value) -> crate::models::ApiResponse {
         .map(|v| v as i64) // Safe conversion as long as value is within i64 range
         .unwrap_or(0);
     
-    // Content extraction differs between API responses
-    let content = if let Some(choices) = response.get("choices") {
-        if let Some(first_choice) = choices.as_array().and_then(|a| a.first()) {
-            if let Some(text) = first_choice.get("text") {
-                text.as_str().map(|s| s.to_string())
-            } else if let Some(message) = first_choice.get("message") {
-                message.get("content").and_then(|c| c.as_str()).map(|s| s.to_string())
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+    // Every choice is kept so that requests with n > 1 return all of them
+    let choices = extract_choices(response);
+    let content = response
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|a| a.first())
+        .and_then(choice_text);
     
     // Finish reason extraction 
     let finish_reason = if let Some(choices) = response.get("choices") {
@@ -105,12 +114,58 @@ pub fn extract_response_fields(response: &Value) -> crate::models::ApiResponse {
         model,
         created,
         content,
+        choices,
         finish_reason,
         completion_tokens,
         total_tokens,
     }
 }
 
+/// Extract the generated text of every choice, from either a completion
+/// (`text`) or a chat (`message.content`) response
+pub fn extract_choices(response: &Value) -> Vec<String> {
+    response
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .map(|choices| {
+            choices
+                .iter()
+                .filter_map(choice_text)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Content extraction differs between API responses
+fn choice_text(choice: &Value) -> Option<String> {
+    if let Some(text) = choice.get("text") {
+        text.as_str().map(|s| s.to_string())
+    } else {
+        choice
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_str())
+            .map(|s| s.to_string())
+    }
+}
+
+/// Extract the assistant message of the first choice in a chat response
+pub fn extract_message(response: &Value) -> Option<ChatMessage> {
+    response
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|a| a.first())
+        .and_then(|choice| choice.get("message"))
+        .and_then(|m| serde_json::from_value(m.clone()).ok())
+}
+
+/// Extract the tool calls requested in the first choice of a chat response
+pub fn extract_tool_calls(response: &Value) -> Vec<ToolCall> {
+    extract_message(response)
+        .and_then(|m| m.tool_calls)
+        .unwrap_or_default()
+}
+
 // API client trait
 #[async_trait]
 pub trait ApiClient {
@@ -0,0 +1,129 @@
+use crate::Config;
+use crate::api::common::{ApiClient, get_api_key, make_api_request, extract_response_fields};
+use crate::api::progress::ProgressTracker;
+use crate::daemon::try_post;
+use crate::models::{ApiResponse, EmbeddingRequest};
+use crate::error::{AppError, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use std::path::PathBuf;
+use std::time::Duration;
+use serde_json::{json, Value};
+use log::debug;
+
+/// Embeddings for a set of inputs along with the aggregated response metadata
+pub struct EmbeddingResult {
+    pub embeddings: Vec<Vec<f32>>,
+    pub response: ApiResponse,
+}
+
+/// Parse the `data` array of an embeddings response, ordered by input index
+pub fn parse_embeddings_response(response: &Value) -> Result<Vec<Vec<f32>>> {
+    let data = response
+        .get("data")
+        .and_then(|d| d.as_array())
+        .ok_or_else(|| AppError::api("Embeddings response has no 'data' array"))?;
+
+    let mut indexed = Vec::with_capacity(data.len());
+    for (position, item) in data.iter().enumerate() {
+        let index = item.get("index").and_then(|i| i.as_u64()).map(|i| i as usize).unwrap_or(position);
+        let embedding = item
+            .get("embedding")
+            .and_then(|e| e.as_array())
+            .ok_or_else(|| AppError::api(format!("Embedding {} has no 'embedding' array", index)))?
+            .iter()
+            .map(|v| v.as_f64().map(|f| f as f32))
+            .collect::<Option<Vec<f32>>>()
+            .ok_or_else(|| AppError::api(format!("Embedding {} contains non-numeric values", index)))?;
+        indexed.push((index, embedding));
+    }
+
+    indexed.sort_by_key(|(index, _)| *index);
+    Ok(indexed.into_iter().map(|(_, embedding)| embedding).collect())
+}
+
+pub struct EmbeddingsClient {
+    model: String,
+    api_url: String,
+    api_key: String,
+    batch_size: usize,
+    client: Client,
+    daemon_socket: Option<PathBuf>,
+    daemon_timeout: Duration,
+}
+
+impl EmbeddingsClient {
+    pub fn new(config: Config) -> Result<Self> {
+        let api_key = get_api_key(&config.chat_api_key_env)?;
+
+        Ok(Self {
+            model: config.embed_model.clone(),
+            api_url: config.embed_api_url.clone(),
+            api_key,
+            batch_size: config.embed_batch_size.max(1),
+            client: Client::new(),
+            daemon_socket: config.use_daemon.then(|| config.get_daemon_socket_path()),
+            daemon_timeout: Duration::from_secs(config.daemon_timeout),
+        })
+    }
+
+    /// Embed the inputs, returning one vector per input in the same order
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(self.embed_with_usage(inputs).await?.embeddings)
+    }
+
+    /// Embed the inputs in batches, summing token usage across all batches
+    pub async fn embed_with_usage(&self, inputs: &[String]) -> Result<EmbeddingResult> {
+        let batch_count = inputs.len().div_ceil(self.batch_size);
+        let tracker = ProgressTracker::new(&format!("Embedding {} inputs with model '{}'...", inputs.len(), self.model));
+
+        let work = async {
+            let mut embeddings = Vec::with_capacity(inputs.len());
+            let mut aggregate: Option<ApiResponse> = None;
+
+            for (i, batch) in inputs.chunks(self.batch_size).enumerate() {
+                tracker.update_message(&format!("Embedding batch {}/{} with model '{}'...", i + 1, batch_count, self.model));
+                debug!("Sending embeddings batch {} of {} ({} inputs)", i + 1, batch_count, batch.len());
+
+                let request = EmbeddingRequest {
+                    model: self.model.clone(),
+                    input: batch.to_vec(),
+                };
+                let response_json = self.send_request(json!(request)).await?;
+
+                let batch_embeddings = parse_embeddings_response(&response_json)?;
+                if batch_embeddings.len() != batch.len() {
+                    return Err(AppError::api(format!(
+                        "Expected {} embeddings but received {}", batch.len(), batch_embeddings.len()
+                    )));
+                }
+                embeddings.extend(batch_embeddings);
+
+                let batch_response = extract_response_fields(&response_json);
+                aggregate = Some(match aggregate {
+                    Some(mut total) => {
+                        total.completion_tokens += batch_response.completion_tokens;
+                        total.total_tokens += batch_response.total_tokens;
+                        total
+                    },
+                    None => batch_response,
+                });
+            }
+
+            let response = aggregate.unwrap_or_else(|| extract_response_fields(&Value::Null));
+            Ok(EmbeddingResult { embeddings, response })
+        };
+
+        tracker.track_api_call(work, "Embeddings received!").await
+    }
+}
+
+#[async_trait]
+impl ApiClient for EmbeddingsClient {
+    async fn send_request(&self, request_body: Value) -> Result<Value> {
+        if let Some(response) = try_post(self.daemon_socket.as_deref(), self.daemon_timeout, &self.api_url, &self.api_key, &request_body).await {
+            return response;
+        }
+        make_api_request(&self.client, &self.api_url, &self.api_key, request_body).await
+    }
+}
@@ -1,19 +1,56 @@
 use crate::Config;
-use crate::api::common::{ApiClient, get_api_key, make_api_request};
+use crate::api::common::{ApiClient, extract_message, get_api_key, make_api_request};
 use crate::api::progress::with_progress;
-use crate::models::{ChatMessage, ChatCompletionRequest};
-use crate::error::Result;
+use crate::daemon::try_post;
+use crate::models::{ChatMessage, ChatCompletionRequest, ResponseFormat, SamplingParams};
+use crate::structured::{check_schema, correction_prompt, json_instruction, parse_json_content, schema_errors};
+use crate::tools::{ToolInvocation, ToolRegistry};
+use crate::error::{AppError, Result};
 use async_trait::async_trait;
 use reqwest::Client;
+use std::path::PathBuf;
+use std::time::Duration;
+use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
-use log::debug;
+use log::{debug, info, warn};
+
+/// Deserialized structured output along with the final raw response
+pub struct StructuredOutcome<T> {
+    pub value: T,
+    pub response: Value,
+    pub attempts: usize,
+}
+
+/// Final response of a tool-calling chat along with the full conversation
+pub struct ToolChatOutcome {
+    pub response: Value,
+    pub messages: Vec<ChatMessage>,
+    pub invocations: Vec<ToolInvocation>,
+}
+
+/// A failed tool-calling chat along with the tool calls made before it failed
+#[derive(Debug)]
+pub struct ToolChatError {
+    pub error: AppError,
+    pub invocations: Vec<ToolInvocation>,
+}
+
+impl From<ToolChatError> for AppError {
+    fn from(failure: ToolChatError) -> Self {
+        failure.error
+    }
+}
 
 pub struct MistralClient {
     model: String,
     temperature: f64,
+    system_prompt: Option<String>,
+    sampling: SamplingParams,
     api_url: String,
     api_key: String,
     client: Client,
+    daemon_socket: Option<PathBuf>,
+    daemon_timeout: Duration,
 }
 
 impl MistralClient {
@@ -23,28 +60,192 @@ impl MistralClient {
         Ok(Self {
             model: config.chat_model.clone(),
             temperature: config.chat_temperature,
+            system_prompt: config.system_prompt.clone(),
+            sampling: config.sampling_params(),
             api_url: config.chat_api_url.clone(),
             api_key,
             client: Client::new(),
+            daemon_socket: config.use_daemon.then(|| config.get_daemon_socket_path()),
+            daemon_timeout: Duration::from_secs(config.daemon_timeout),
         })
     }
     
+    /// Override the sampling parameters taken from the configuration
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+    
+    /// Override the system prompt taken from the configuration
+    pub fn with_system_prompt(mut self, system_prompt: Option<String>) -> Self {
+        self.system_prompt = system_prompt;
+        self
+    }
+    
     pub async fn chat(&self, message: &str, max_tokens: Option<u32>) -> Result<Value> {
-        debug!("Sending request to Mistral API for chat completion");
+        self.chat_with_messages(vec![ChatMessage::user(message)], max_tokens).await
+    }
+    
+    /// Send a full conversation, e.g. with a leading system message
+    pub async fn chat_with_messages(&self, messages: Vec<ChatMessage>, max_tokens: Option<u32>) -> Result<Value> {
+        let request = self.build_request(messages, max_tokens);
+        self.send_chat(request).await
+    }
+    
+    /// Chat with tools available, executing the model's tool calls and feeding
+    /// the results back until it gives a final answer or `max_iterations` requests
+    /// have been made. On failure, the tool calls already made are returned
+    /// with the error.
+    pub async fn chat_with_tools(
+        &self,
+        messages: Vec<ChatMessage>,
+        registry: &ToolRegistry,
+        max_tokens: Option<u32>,
+        max_iterations: usize,
+    ) -> std::result::Result<ToolChatOutcome, ToolChatError> {
+        let mut invocations = Vec::new();
+        match self.run_tool_loop(messages, registry, max_tokens, max_iterations, &mut invocations).await {
+            Ok((response, messages)) => Ok(ToolChatOutcome { response, messages, invocations }),
+            Err(error) => Err(ToolChatError { error, invocations }),
+        }
+    }
+    
+    async fn run_tool_loop(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        registry: &ToolRegistry,
+        max_tokens: Option<u32>,
+        max_iterations: usize,
+        invocations: &mut Vec<ToolInvocation>,
+    ) -> Result<(Value, Vec<ChatMessage>)> {
+        for iteration in 1..=max_iterations {
+            let mut request = self.build_request(messages.clone(), max_tokens);
+            if !registry.is_empty() {
+                request.tools = Some(registry.definitions());
+                request.tool_choice = Some("auto".to_string());
+            }
+            
+            let response = self.send_chat(request).await?;
+            let message = extract_message(&response)
+                .ok_or_else(|| AppError::api("Chat response has no message"))?;
+            
+            let tool_calls = message.tool_calls.clone().unwrap_or_default();
+            if tool_calls.is_empty() {
+                messages.push(message);
+                return Ok((response, messages));
+            }
+            
+            info!("Model requested {} tool calls (iteration {})", tool_calls.len(), iteration);
+            messages.push(message);
+            
+            for call in &tool_calls {
+                debug!("Calling tool '{}' with arguments {}", call.function.name, call.function.arguments);
+                let invocation = registry.invoke(call);
+                messages.push(ChatMessage::tool(&call.id, &call.function.name, invocation.result.clone()));
+                invocations.push(invocation);
+            }
+        }
+        
+        Err(AppError::api(format!(
+            "Model did not produce a final answer within {} tool-calling iterations", max_iterations
+        )))
+    }
+    
+    /// Chat in JSON mode, returning the raw response
+    pub async fn chat_json(&self, messages: Vec<ChatMessage>, max_tokens: Option<u32>) -> Result<Value> {
+        let mut request = self.build_request(messages, max_tokens);
+        request.response_format = Some(ResponseFormat::json_object());
+        self.send_chat(request).await
+    }
+    
+    /// Chat in JSON mode and deserialize the reply, validating it against
+    /// `schema` if given and re-prompting with the errors up to `max_retries` times
+    pub async fn chat_structured<T: DeserializeOwned>(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: Option<&Value>,
+        max_tokens: Option<u32>,
+        max_retries: usize,
+    ) -> Result<T> {
+        Ok(self.chat_structured_with_response(messages, schema, max_tokens, max_retries).await?.value)
+    }
+    
+    /// Like [`MistralClient::chat_structured`], also returning the final raw response
+    pub async fn chat_structured_with_response<T: DeserializeOwned>(
+        &self,
+        mut messages: Vec<ChatMessage>,
+        schema: Option<&Value>,
+        max_tokens: Option<u32>,
+        max_retries: usize,
+    ) -> Result<StructuredOutcome<T>> {
+        if let Some(schema) = schema {
+            check_schema(schema)?;
+        }
+        match messages.first_mut() {
+            Some(first) if first.role == "system" => {
+                first.content = format!("{}\n\n{}", first.content, json_instruction(schema));
+            },
+            _ => messages.insert(0, ChatMessage::system(json_instruction(schema))),
+        }
         
-        let messages = vec![
-            ChatMessage {
-                role: "user".to_string(),
-                content: message.to_string(),
+        let mut last_errors = Vec::new();
+        for attempt in 1..=max_retries + 1 {
+            let response = self.chat_json(messages.clone(), max_tokens).await?;
+            let content = extract_message(&response)
+                .map(|m| m.content)
+                .ok_or_else(|| AppError::api("Chat response has no message"))?;
+            
+            let errors = match parse_json_content(&content) {
+                Ok(value) => {
+                    let mut errors = match schema {
+                        Some(schema) => schema_errors(schema, &value)?,
+                        None => Vec::new(),
+                    };
+                    if errors.is_empty() {
+                        match serde_json::from_value::<T>(value) {
+                            Ok(value) => return Ok(StructuredOutcome { value, response, attempts: attempt }),
+                            Err(e) => errors.push(format!("Response has the wrong shape: {}", e)),
+                        }
+                    }
+                    errors
+                },
+                Err(e) => vec![e.to_string()],
+            };
+            
+            warn!("Structured response attempt {} failed validation: {}", attempt, errors.join("; "));
+            messages.push(ChatMessage::assistant(content));
+            messages.push(ChatMessage::user(correction_prompt(&errors)));
+            last_errors = errors;
+        }
+        
+        Err(AppError::api(format!(
+            "Response failed validation after {} attempts: {}", max_retries + 1, last_errors.join("; ")
+        )))
+    }
+    
+    fn build_request(&self, mut messages: Vec<ChatMessage>, max_tokens: Option<u32>) -> ChatCompletionRequest {
+        // The system prompt comes first, ahead of any system message from the caller
+        if let Some(system_prompt) = self.system_prompt.as_deref().filter(|p| !p.trim().is_empty()) {
+            match messages.first_mut() {
+                Some(first) if first.role == "system" => {
+                    first.content = format!("{}\n\n{}", system_prompt, first.content);
+                },
+                _ => messages.insert(0, ChatMessage::system(system_prompt)),
             }
-        ];
+        }
         
-        let request = ChatCompletionRequest {
+        ChatCompletionRequest {
             model: self.model.clone(),
             messages,
             max_tokens,
             temperature: self.temperature,
-        };
+            sampling: self.sampling.clone(),
+            ..Default::default()
+        }
+    }
+    
+    async fn send_chat(&self, request: ChatCompletionRequest) -> Result<Value> {
+        debug!("Sending request to Mistral API for chat completion");
         
         let request_json = json!(request);
         
@@ -60,6 +261,9 @@ impl MistralClient {
 #[async_trait]
 impl ApiClient for MistralClient {
     async fn send_request(&self, request_body: Value) -> Result<Value> {
+        if let Some(response) = try_post(self.daemon_socket.as_deref(), self.daemon_timeout, &self.api_url, &self.api_key, &request_body).await {
+            return response;
+        }
         make_api_request(&self.client, &self.api_url, &self.api_key, request_body).await
     }
 }
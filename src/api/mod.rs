@@ -1,9 +1,13 @@
 mod common;
 mod mistral;
 mod codestral;
+mod embeddings;
+mod models;
 mod progress;
 
-pub use mistral::MistralClient;
+pub use mistral::{MistralClient, StructuredOutcome, ToolChatError, ToolChatOutcome};
 pub use codestral::CodestralClient;
-pub use common::extract_response_fields;
+pub use embeddings::{EmbeddingsClient, EmbeddingResult, parse_embeddings_response};
+pub use models::{ModelsClient, ModelCache, parse_models_response};
+pub use common::{extract_choices, extract_message, extract_response_fields, extract_tool_calls, make_api_request};
 pub use progress::{ProgressTracker, with_progress};
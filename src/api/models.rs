@@ -0,0 +1,124 @@
+use crate::Config;
+use crate::api::common::{get_api_key, make_get_request};
+use crate::api::progress::with_progress;
+use crate::models::ModelInfo;
+use crate::error::{AppError, Result, ErrorExt};
+use chrono::Utc;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use log::{debug, info};
+
+/// Locally cached copy of the provider's model list
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelCache {
+    pub fetched_at: i64,
+    pub models: Vec<ModelInfo>,
+}
+
+impl ModelCache {
+    pub fn new(models: Vec<ModelInfo>) -> Self {
+        Self {
+            fetched_at: Utc::now().timestamp(),
+            models,
+        }
+    }
+
+    /// Load the cache file, returning `None` if it does not exist yet
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read models cache {}", path.display()))?;
+        let cache = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse models cache {}", path.display()))?;
+        Ok(Some(cache))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write models cache {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Whether the cache is younger than `ttl` seconds
+    pub fn is_fresh(&self, ttl: u64) -> bool {
+        Utc::now().timestamp() - self.fetched_at < ttl as i64
+    }
+}
+
+/// Parse the `data` array of a `/v1/models` response
+pub fn parse_models_response(response: &Value) -> Result<Vec<ModelInfo>> {
+    let data = response
+        .get("data")
+        .cloned()
+        .ok_or_else(|| AppError::api("Models response has no 'data' field"))?;
+
+    let mut models: Vec<ModelInfo> = serde_json::from_value(data)?;
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+pub struct ModelsClient {
+    api_url: String,
+    api_key: String,
+    cache_path: std::path::PathBuf,
+    cache_ttl: u64,
+    client: Client,
+}
+
+impl ModelsClient {
+    pub fn new(config: Config) -> Result<Self> {
+        let api_key = get_api_key(&config.chat_api_key_env)?;
+
+        Ok(Self {
+            api_url: config.models_api_url.clone(),
+            api_key,
+            cache_path: config.get_models_cache_path(),
+            cache_ttl: config.models_cache_ttl,
+            client: Client::new(),
+        })
+    }
+
+    /// Fetch the model list from the provider, bypassing the cache
+    pub async fn fetch_models(&self) -> Result<Vec<ModelInfo>> {
+        debug!("Requesting model list from {}", self.api_url);
+
+        let response = with_progress(
+            "Fetching available models...",
+            "Model list received!",
+            make_get_request(&self.client, &self.api_url, &self.api_key)
+        ).await?;
+
+        parse_models_response(&response)
+    }
+
+    /// Return the cached model list if it is fresh, otherwise fetch and cache it
+    pub async fn list_models(&self, refresh: bool) -> Result<Vec<ModelInfo>> {
+        if !refresh {
+            if let Some(cache) = ModelCache::load(&self.cache_path)? {
+                if cache.is_fresh(self.cache_ttl) {
+                    info!("Using cached model list from {}", self.cache_path.display());
+                    return Ok(cache.models);
+                }
+            }
+        }
+
+        let models = self.fetch_models().await?;
+        ModelCache::new(models.clone()).save(&self.cache_path)?;
+        info!("Cached {} models to {}", models.len(), self.cache_path.display());
+        Ok(models)
+    }
+}
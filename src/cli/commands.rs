@@ -1,4 +1,7 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::models::SamplingParams;
+use crate::explain::Depth;
+use crate::review::DiffSource;
 
 /// Codestral Test CLI - A command-line interface for interacting with Codestral and Mistral APIs
 #[derive(Parser, Debug)]
@@ -23,7 +26,7 @@ pub enum Commands {
     /// Generate code completions
     #[command(visible_alias = "code")]
     CodeCompletion {
-        /// Prompt text before the cursor
+        /// Prompt text before the cursor, or `-` to read it from stdin
         #[arg(required = true)]
         prompt: String,
 
@@ -34,17 +37,304 @@ pub enum Commands {
         /// Maximum number of tokens to generate
         #[arg(short, long)]
         max_tokens: Option<u32>,
+
+        #[command(flatten)]
+        options: CompletionArgs,
     },
 
     /// Chat with the model
     Chat {
-        /// Message to send to the chat model
+        /// Message to send to the chat model, or `-` to read it from stdin
+        /// (available to templates as `{{input}}`)
+        #[arg(required_unless_present_any = ["template", "message_file"], conflicts_with = "message_file")]
+        message: Option<String>,
+
+        /// Maximum number of tokens to generate
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+
+        #[command(flatten)]
+        options: ChatArgs,
+    },
+
+    /// Generate embeddings for text inputs
+    Embed {
+        /// Texts to embed (use `-` to read from stdin)
+        inputs: Vec<String>,
+
+        /// Read an input from a file (can be repeated)
+        #[arg(short, long = "file", value_name = "FILE")]
+        files: Vec<String>,
+
+        /// Treat each non-empty line of files and stdin as a separate input
+        #[arg(long)]
+        lines: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = EmbedFormat::Jsonl)]
+        format: EmbedFormat,
+
+        /// Write embeddings to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+
+    /// Manage the local semantic code index
+    Index {
+        /// Index operation to perform
+        #[command(subcommand)]
+        action: IndexCommands,
+    },
+
+    /// Search the local code index
+    Search {
+        /// Natural language or code query
+        #[arg(required = true)]
+        query: String,
+
+        /// Root directory of the index
+        #[arg(long, default_value = ".")]
+        dir: String,
+
+        /// Number of results to show
+        #[arg(short = 'k', long, default_value_t = 10)]
+        top_k: usize,
+    },
+
+    /// Send the same request to several models and show the outputs side by side
+    Compare {
+        /// Chat message, or the text before the cursor when --suffix is given (`-` for stdin)
         #[arg(required = true)]
-        message: String,
+        prompt: String,
+
+        /// Models to compare, comma-separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        models: Vec<String>,
+
+        /// Send a FIM request with this suffix instead of a chat message
+        #[arg(long)]
+        suffix: Option<String>,
 
         /// Maximum number of tokens to generate
         #[arg(short, long)]
         max_tokens: Option<u32>,
+
+        /// Report format
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+
+    /// Score code models on a dataset of FIM tasks with pass@k
+    Eval {
+        /// JSONL file of tasks with prefix, suffix and a test command or expected output
+        #[arg(required = true)]
+        dataset: String,
+
+        /// Samples to generate per task
+        #[arg(short = 'k', long, default_value_t = 1)]
+        samples: usize,
+
+        /// Model to evaluate (can be repeated; defaults to the configured code model)
+        #[arg(long = "model", value_name = "MODEL")]
+        models: Vec<String>,
+
+        /// Answer tasks with their canonical solutions instead of calling the API
+        #[arg(long)]
+        mock: bool,
+
+        /// Maximum number of tokens to generate per sample
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+
+        /// Write the full report as JSON to this file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+
+    /// Review a git diff and report findings per file and line
+    Review {
+        #[command(flatten)]
+        diff: DiffArgs,
+
+        /// Output format for the findings
+        #[arg(long, value_enum, default_value_t = FindingsFormat::Text)]
+        format: FindingsFormat,
+
+        /// Write the findings to a file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Maximum number of tokens to generate per chunk
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+
+        #[command(flatten)]
+        persona: PersonaArgs,
+    },
+
+    /// Generate a commit message for the staged changes
+    CommitMsg {
+        /// Install a prepare-commit-msg hook that runs this command
+        #[arg(long)]
+        install_hook: bool,
+
+        /// Replace an existing hook that was not installed by this tool
+        #[arg(long, requires = "install_hook")]
+        force: bool,
+
+        /// Write the message to this file, above its existing content
+        #[arg(short, long, value_name = "FILE", conflicts_with = "install_hook")]
+        output: Option<String>,
+
+        /// Repository to read the staged changes from
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        repo: String,
+
+        /// Maximum number of tokens to generate
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+    },
+
+    /// Generate unit tests for a Rust function
+    GenTests {
+        /// Rust source file containing the function
+        #[arg(required = true)]
+        file: String,
+
+        /// Function to test, as `name` or `Type::name` for methods
+        #[arg(short, long, required = true)]
+        function: String,
+
+        /// Write a file under `tests/` instead of a `#[cfg(test)]` module
+        #[arg(long)]
+        integration: bool,
+
+        /// Run the new tests with `cargo test` and report which pass
+        #[arg(long)]
+        run: bool,
+
+        /// Maximum number of tokens to generate
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+    },
+
+    /// Generate doc comments for undocumented public Rust items
+    GenDocs {
+        /// Rust file or directory to document
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Only list undocumented items, failing if there are any (no API calls)
+        #[arg(long)]
+        check: bool,
+
+        /// Write the doc comments into the files instead of printing a patch
+        #[arg(long, conflicts_with = "check")]
+        write: bool,
+
+        /// Maximum number of tokens to generate per item
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+    },
+
+    /// Explain a file or a range of lines in it
+    Explain {
+        /// File to explain, optionally with a line range as `file:start-end`
+        #[arg(required = true, value_name = "FILE[:START-END]")]
+        target: String,
+
+        /// How much detail to ask for
+        #[arg(long, value_enum, default_value_t = ExplainDepth::Brief)]
+        depth: ExplainDepth,
+
+        /// Print the Markdown answer without terminal formatting
+        #[arg(long)]
+        raw: bool,
+
+        /// Maximum number of tokens to generate
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+
+        #[command(flatten)]
+        persona: PersonaArgs,
+    },
+
+    /// Run a language server over stdio that offers code completions
+    ServeLsp {
+        /// Milliseconds to wait for more typing before requesting a completion
+        #[arg(long)]
+        debounce_ms: Option<u64>,
+
+        /// Maximum number of tokens to generate per completion
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+    },
+
+    /// Run a local HTTP proxy with OpenAI-compatible completion endpoints
+    Serve {
+        /// Address to listen on (defaults to serve_bind)
+        #[arg(long)]
+        bind: Option<String>,
+
+        /// Total tokens to spend before refusing requests
+        #[arg(long)]
+        budget: Option<u64>,
+
+        /// Allow a non-loopback --bind address; requests then need the bearer
+        /// token from the environment variable named by serve_token_env
+        #[arg(long)]
+        allow_remote: bool,
+    },
+
+    /// Manage the background daemon that keeps connections warm
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonCommands,
+    },
+
+    /// Watch a directory and complete marker comments when files are saved
+    Watch {
+        /// Directory to watch
+        #[arg(default_value = ".")]
+        dir: String,
+
+        /// Write suggestions to `<file>.suggestion.patch` instead of editing files
+        #[arg(long)]
+        sidecar: bool,
+
+        /// Print suggestions as patches without writing anything
+        #[arg(long, conflicts_with = "sidecar")]
+        dry_run: bool,
+
+        /// Gitignore-style pattern of files to skip (repeatable)
+        #[arg(long, value_name = "PATTERN")]
+        ignore: Vec<String>,
+
+        /// Marker that requests a completion, replacing watch_markers (repeatable)
+        #[arg(long)]
+        marker: Vec<String>,
+
+        /// Maximum number of tokens to generate per marker
+        #[arg(short, long)]
+        max_tokens: Option<u32>,
+    },
+
+    /// Manage prompt templates
+    Template {
+        /// Template operation to perform
+        #[command(subcommand)]
+        action: TemplateCommands,
+    },
+
+    /// List and inspect chat personas
+    Persona {
+        /// Persona operation to perform
+        #[command(subcommand)]
+        action: PersonaCommands,
     },
 
     /// Manage configuration
@@ -53,6 +343,329 @@ pub enum Commands {
         #[command(subcommand)]
         action: ConfigCommands,
     },
+
+    /// Inspect the models offered by the provider
+    Models {
+        /// Models operation to perform
+        #[command(subcommand)]
+        action: ModelsCommands,
+    },
+}
+
+/// Options for the code completion command
+#[derive(Args, Debug, Clone, Default)]
+pub struct CompletionArgs {
+    #[command(flatten)]
+    pub context: ContextArgs,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+
+    #[command(flatten)]
+    pub candidates: CandidateArgs,
+
+    #[command(flatten)]
+    pub verify: VerifyArgs,
+}
+
+/// Options for the chat command
+#[derive(Args, Debug, Clone, Default)]
+pub struct ChatArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub persona: PersonaArgs,
+
+    #[command(flatten)]
+    pub template: TemplateArgs,
+
+    #[command(flatten)]
+    pub context: ContextArgs,
+
+    #[command(flatten)]
+    pub tools: ToolArgs,
+
+    #[command(flatten)]
+    pub structured: StructuredArgs,
+
+    #[command(flatten)]
+    pub sampling: SamplingArgs,
+}
+
+/// Options for reading the chat message from files
+#[derive(Args, Debug, Clone, Default)]
+pub struct InputArgs {
+    /// Read the message from a file (`-` for stdin)
+    #[arg(long, value_name = "FILE")]
+    pub message_file: Option<String>,
+
+    /// Append a file to the message as a fenced code block (can be repeated)
+    #[arg(long, value_name = "PATH")]
+    pub attach: Vec<String>,
+}
+
+/// Options for choosing the system prompt, model and temperature of a chat
+#[derive(Args, Debug, Clone, Default)]
+pub struct PersonaArgs {
+    /// Use a persona from the configuration (system prompt, model and temperature)
+    #[arg(short, long, value_name = "NAME")]
+    pub persona: Option<String>,
+
+    /// System prompt to send, overriding the persona and configuration
+    #[arg(long, value_name = "PROMPT")]
+    pub system: Option<String>,
+}
+
+/// Options for choosing which git changes to read
+#[derive(Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// Use the changes staged for commit instead of the working tree
+    #[arg(long, conflicts_with = "range")]
+    pub staged: bool,
+
+    /// Use the changes between two revisions, e.g. `main..HEAD`
+    #[arg(long, value_name = "A..B")]
+    pub range: Option<String>,
+
+    /// Repository to read the diff from
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub repo: String,
+}
+
+impl DiffArgs {
+    pub fn source(&self) -> DiffSource {
+        match (&self.range, self.staged) {
+            (Some(range), _) => DiffSource::Range(range.clone()),
+            (None, true) => DiffSource::Staged,
+            (None, false) => DiffSource::WorkingTree,
+        }
+    }
+}
+
+/// Options for building a message from a prompt template
+#[derive(Args, Debug, Clone, Default)]
+pub struct TemplateArgs {
+    /// Render this template as the message
+    #[arg(short, long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// Value for a template variable (can be repeated)
+    #[arg(long = "var", value_name = "KEY=VALUE", requires = "template")]
+    pub vars: Vec<String>,
+}
+
+/// Options for pulling repository snippets into a request
+#[derive(Args, Debug, Clone, Default)]
+pub struct ContextArgs {
+    /// Include relevant snippets from this directory (uses its index if built)
+    #[arg(long, value_name = "DIR")]
+    pub context: Option<String>,
+
+    /// Include relevant snippets from files matching this glob (can be repeated)
+    #[arg(long, value_name = "GLOB")]
+    pub context_files: Vec<String>,
+
+    /// Maximum estimated tokens of context to include
+    #[arg(long, value_name = "TOKENS")]
+    pub context_budget: Option<usize>,
+}
+
+impl ContextArgs {
+    /// Whether any context source was requested
+    pub fn is_enabled(&self) -> bool {
+        self.context.is_some() || !self.context_files.is_empty()
+    }
+}
+
+/// Options for generating several code completions and ranking them locally
+#[derive(Args, Debug, Clone, Default)]
+pub struct CandidateArgs {
+    /// Generate this many completions and print the best-ranked one
+    #[arg(long, value_name = "N")]
+    pub candidates: Option<u32>,
+
+    /// Send one request per candidate with different seeds instead of using `n`
+    #[arg(long, requires = "candidates")]
+    pub parallel: bool,
+
+    /// Choose among the ranked candidates interactively
+    #[arg(long, requires = "candidates")]
+    pub pick: bool,
+
+    /// Language of the code, used for syntax checks when ranking (e.g. rust, json)
+    #[arg(long, value_name = "LANG")]
+    pub language: Option<String>,
+}
+
+impl CandidateArgs {
+    /// Number of candidates requested, if more than one
+    pub fn count(&self) -> Option<u32> {
+        self.candidates.filter(|&n| n > 1)
+    }
+}
+
+/// Options for checking Rust completions before printing them
+#[derive(Args, Debug, Clone, Default)]
+pub struct VerifyArgs {
+    /// Splice the completion into --file, parse it with syn and retry on errors
+    #[arg(long, requires = "file", conflicts_with = "candidates")]
+    pub verify: bool,
+
+    /// Source file the prompt and suffix were taken from; also sets the
+    /// comment syntax used for --context snippets
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<String>,
+
+    /// Also run `cargo check` in the crate containing --file
+    #[arg(long, requires = "verify")]
+    pub cargo_check: bool,
+
+    /// Follow-up requests to make when verification fails
+    #[arg(long, value_name = "N", requires = "verify")]
+    pub verify_retries: Option<usize>,
+}
+
+/// Options for letting the chat model use local workspace tools
+#[derive(Args, Debug, Clone, Default)]
+pub struct ToolArgs {
+    /// Let the model read, search and patch files and run allowlisted commands
+    #[arg(long)]
+    pub tools: bool,
+
+    /// Root directory the tools are confined to
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub workspace: String,
+
+    /// Run tool calls without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// Options for requesting JSON output from the chat model
+#[derive(Args, Debug, Clone, Default)]
+pub struct StructuredArgs {
+    /// Ask the model to reply with a JSON object
+    #[arg(long)]
+    pub json: bool,
+
+    /// Validate the JSON reply against this JSON Schema file (implies --json)
+    #[arg(long, value_name = "FILE")]
+    pub schema: Option<String>,
+
+    /// Times to re-prompt with validation errors before giving up
+    #[arg(long, value_name = "N")]
+    pub max_retries: Option<usize>,
+}
+
+impl StructuredArgs {
+    /// Whether JSON output was requested
+    pub fn is_enabled(&self) -> bool {
+        self.json || self.schema.is_some()
+    }
+}
+
+/// Sampling options that override the configured defaults
+#[derive(Args, Debug, Clone, Default)]
+pub struct SamplingArgs {
+    /// Nucleus sampling: only consider tokens within this probability mass
+    #[arg(long, value_name = "P")]
+    pub top_p: Option<f64>,
+
+    /// Stop generating when this sequence is produced (can be repeated)
+    #[arg(long, value_name = "SEQ")]
+    pub stop: Vec<String>,
+
+    /// Seed for reproducible sampling
+    #[arg(long, value_name = "SEED")]
+    pub random_seed: Option<u64>,
+
+    /// Minimum number of tokens to generate
+    #[arg(long, value_name = "TOKENS")]
+    pub min_tokens: Option<u32>,
+
+    /// Penalize tokens that already appear in the output
+    #[arg(long, value_name = "PENALTY", allow_negative_numbers = true)]
+    pub presence_penalty: Option<f64>,
+
+    /// Penalize tokens by how often they appear in the output
+    #[arg(long, value_name = "PENALTY", allow_negative_numbers = true)]
+    pub frequency_penalty: Option<f64>,
+
+    /// Number of completions to generate
+    #[arg(short = 'n', long = "choices", value_name = "N")]
+    pub n: Option<u32>,
+
+    /// Prepend the provider's safety prompt
+    #[arg(long)]
+    pub safe_prompt: bool,
+}
+
+impl SamplingArgs {
+    /// Parameters set on the command line; unset flags are left as `None`
+    pub fn to_params(&self) -> SamplingParams {
+        SamplingParams {
+            top_p: self.top_p,
+            stop: if self.stop.is_empty() { None } else { Some(self.stop.clone()) },
+            random_seed: self.random_seed,
+            min_tokens: self.min_tokens,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            n: self.n,
+            safe_prompt: self.safe_prompt.then_some(true),
+        }
+    }
+}
+
+/// Output formats for the embed command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbedFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// Compact little-endian f32 format
+    Binary,
+}
+
+/// Output formats for the compare command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Plain text columns
+    Text,
+    /// Markdown tables and code blocks
+    Markdown,
+    /// Standalone HTML page
+    Html,
+    /// JSON array of results
+    Json,
+}
+
+/// Output formats for review findings
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FindingsFormat {
+    /// One `file:line: severity: message` line per finding
+    Text,
+    /// JSON array of findings
+    Json,
+    /// SARIF 2.1.0 log for code scanning dashboards
+    Sarif,
+}
+
+/// Levels of detail for the explain command
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExplainDepth {
+    /// A few sentences on what the code does
+    Brief,
+    /// A step-by-step walkthrough with edge cases and potential problems
+    Detailed,
+}
+
+impl ExplainDepth {
+    pub fn to_depth(self) -> Depth {
+        match self {
+            ExplainDepth::Brief => Depth::Brief,
+            ExplainDepth::Detailed => Depth::Detailed,
+        }
+    }
 }
 
 /// Configuration subcommands
@@ -78,4 +691,96 @@ pub enum ConfigCommands {
         #[arg(required = true)]
         path: String,
     },
+    
+    /// Check configured model names against the provider's model list
+    Validate {
+        /// Path to the config file to validate
+        #[arg(default_value = "config.toml")]
+        path: String,
+    },
+}
+
+/// Index subcommands
+#[derive(Subcommand, Debug)]
+pub enum IndexCommands {
+    /// Chunk and embed every source file under a directory
+    Build {
+        /// Directory to index
+        #[arg(default_value = ".")]
+        dir: String,
+    },
+    
+    /// Re-embed only files that changed since the last build
+    Update {
+        /// Directory of an existing index
+        #[arg(default_value = ".")]
+        dir: String,
+    },
+}
+
+/// Template subcommands
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommands {
+    /// List templates from the configuration and the templates directory
+    List,
+    
+    /// Print a template with its variables
+    Show {
+        /// Template name
+        #[arg(required = true)]
+        name: String,
+    },
+    
+    /// Create a new template file in the templates directory
+    New {
+        /// Template name
+        #[arg(required = true)]
+        name: String,
+        
+        /// Short description shown by `template list`
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+}
+
+/// Persona subcommands
+#[derive(Subcommand, Debug)]
+pub enum PersonaCommands {
+    /// List the personas defined in the configuration
+    List,
+    
+    /// Print a persona's model, temperature and system prompt
+    Show {
+        /// Persona name
+        #[arg(required = true)]
+        name: String,
+    },
+}
+
+/// Daemon subcommands
+#[derive(Subcommand, Debug)]
+pub enum DaemonCommands {
+    /// Start the daemon in the background
+    Start {
+        /// Run in the foreground instead of detaching
+        #[arg(long)]
+        foreground: bool,
+    },
+    
+    /// Stop the running daemon
+    Stop,
+    
+    /// Show whether the daemon is running and what it has served
+    Status,
+}
+
+/// Models subcommands
+#[derive(Subcommand, Debug)]
+pub enum ModelsCommands {
+    /// List available models with their capabilities
+    List {
+        /// Ignore the local cache and fetch the list again
+        #[arg(short, long)]
+        refresh: bool,
+    },
 }
@@ -1,39 +1,132 @@
 mod commands;
 
-pub use commands::{Cli, Commands, ConfigCommands};
+pub use commands::{CandidateArgs, ChatArgs, Cli, Commands, CompletionArgs, ConfigCommands, ContextArgs, DaemonCommands, DiffArgs, EmbedFormat, ExplainDepth, FindingsFormat, IndexCommands, InputArgs, ModelsCommands, PersonaArgs, PersonaCommands, ReportFormat, SamplingArgs, StructuredArgs, TemplateArgs, TemplateCommands, ToolArgs, VerifyArgs};
 
 use crate::Config;
-use crate::api::{CodestralClient, MistralClient};
-use crate::logging::{log_generation, write_generation_content};
-use crate::error::Result;
-use log::info;
+use crate::api::{CodestralClient, EmbeddingsClient, MistralClient, ModelsClient, ModelCache, extract_response_fields};
+use crate::embeddings::{write_binary, write_jsonl};
+use crate::eval::{CodestralProvider, CompletionProvider, load_tasks, mock_provider_for, run_eval};
+use crate::input::{Attachment, LazyInput, read_message_file, with_attachments};
+use crate::explain::{EXPLAIN_TEMPLATE_NAME, Selection, default_template, parse_target};
+use crate::gen_docs::{collect_rust_files, doc_fim_parts, doc_lines, find_undocumented, insert_docs, unified_patch};
+use crate::gen_tests::{
+    TestTarget, append_module, crate_name, extract_code, find_function, module_path, run_cargo_test, test_prompt,
+    tests_name, wrap_test_module,
+};
+use crate::index::{CodeIndex, Embedder, index_path};
+use crate::commit_msg::{DEFAULT_STYLE_GUIDE, SUMMARY_CONCURRENCY, clean_message, deleted_files, format_summaries, install_hook, message_prompt, summary_prompt, write_message_file};
+use crate::compare::{compare_models, render_html, render_markdown, render_side_by_side};
+use crate::context::{ContextSnippet, ContextSource, comment_prefix_for, estimate_tokens, format_for_fim_prefix, format_for_system_message, gather_context, snippets_metadata};
+use crate::logging::{log_generation, log_generation_with_metadata, write_generation_content};
+use crate::lsp::serve_stdio;
+use crate::daemon::{self, Daemon, DaemonRequest, DaemonStatus};
+use crate::markdown::render_terminal;
+use crate::proxy::{ProxyState, check_bind, serve};
+use crate::models::{ApiResponse, ChatMessage};
+use crate::fim::{
+    Diagnostic, PostProcessOptions, RankedCandidate, VerifyReport, cargo_check_spliced, check_rust_syntax,
+    dedupe_candidates, find_crate_root, feedback_prefix, postprocess, rank_candidates, splice_at_cursor,
+};
+use crate::review::{DiffSource, ReviewResponse, findings_schema, git_diff, render_sarif, render_text, review_prompt, sort_findings, split_diff};
+use crate::templates::{Template, TemplateLibrary, TemplateSource, create_template, parse_vars};
+use crate::tools::{ToolRegistry, Workspace, auto_approve, prompt_confirmation};
+use crate::watch::{FileWatcher, complete_markers, find_markers, sidecar_path};
+use crate::error::{AppError, Result, ErrorExt};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use log::{info, warn};
+use serde_json::{json, Value};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use uuid::Uuid;
 
 /// Handle code completion command
-pub async fn handle_code_completion(prompt: &str, suffix: &str, max_tokens: Option<u32>, config: &Config) -> Result<()> {
+pub async fn handle_code_completion(
+    prompt: &str,
+    suffix: &str,
+    max_tokens: Option<u32>,
+    options: &CompletionArgs,
+    config: &Config,
+) -> Result<()> {
+    let prompt = &LazyInput::new(io::stdin()).resolve(prompt)?;
+    
     // Use provided max_tokens or default from config
     let max_tokens = max_tokens.unwrap_or(config.max_tokens_code);
+    let CompletionArgs { context, sampling, candidates, verify } = options;
+    
+    // Candidates come from a single request with `n` unless parallel requests were asked for
+    let mut sampling_params = config.sampling_params().merge(&sampling.to_params());
+    if let Some(count) = candidates.count().filter(|_| !candidates.parallel) {
+        sampling_params.n = Some(count);
+    }
+    
+    // Rank context against the code around the cursor
+    let query = fim_context_query(prompt, suffix);
+    let snippets = collect_context(&query, context, config).await?;
+    // Context is commented out in the syntax of the file being completed,
+    // falling back to the default when it is not known
+    let full_prompt = if snippets.is_empty() {
+        prompt.to_string()
+    } else {
+        let comment = comment_prefix_for(verify.file.as_deref().unwrap_or_default());
+        format!("{}{}", format_for_fim_prefix(&snippets, comment), prompt)
+    };
     
     info!("Initializing Codestral client");
-    let client = CodestralClient::new(config.clone())?;
+    let client = CodestralClient::new(config.clone())?
+        .with_sampling(sampling_params.clone());
     
     info!("Sending code completion request with max_tokens: {}", max_tokens);
     
-    // Make API request
-    let response_json = client.code_completion(prompt, suffix, max_tokens).await?;
+    if let Some(count) = candidates.count() {
+        let responses = if candidates.parallel {
+            let base_seed = sampling_params.random_seed.unwrap_or_else(|| Uuid::new_v4().as_u128() as u32 as u64);
+            let seeds: Vec<u64> = (0..count as u64).map(|i| base_seed.wrapping_add(i)).collect();
+            client.code_completion_with_seeds(&full_prompt, suffix, max_tokens, &seeds).await?
+        } else {
+            vec![client.code_completion(&full_prompt, suffix, max_tokens).await?]
+        };
+        return report_candidates(prompt, suffix, &responses, candidates, &context_metadata(context, &snippets), config);
+    }
     
-    // Extract and process the response
-    use crate::api::extract_response_fields;
-    let api_response = extract_response_fields(&response_json);
+    let mut metadata = context_metadata(context, &snippets);
+    
+    // Make API request, re-requesting with the errors while verification fails
+    let api_response = if verify.verify {
+        let (api_response, reports) = complete_verified(&client, prompt, &full_prompt, suffix, max_tokens, verify, config).await?;
+        let passed = reports.last().map(|r| r.passed).unwrap_or(false);
+        if !passed {
+            eprintln!("Completion failed verification after {} attempts:", reports.len());
+            for diagnostic in reports.last().iter().flat_map(|r| &r.diagnostics).filter(|d| d.is_error()) {
+                eprintln!("  line {}: {}", diagnostic.line.unwrap_or(0), diagnostic.message);
+            }
+        }
+        metadata["verify"] = json!({ "passed": passed, "attempts": reports });
+        api_response
+    } else {
+        let response_json = client.code_completion(&full_prompt, suffix, max_tokens).await?;
+        let mut api_response = extract_response_fields(&response_json);
+        postprocess_response(&mut api_response, prompt, suffix, config);
+        api_response
+    };
     
     // Log generation metrics
-    log_generation("Code", &api_response, config)?;
+    log_generation_with_metadata("Code", &api_response, &metadata, config)?;
     
     // Write generation content
     let user_input = format!("{}{}", prompt, suffix);
     write_generation_content("Code", &api_response, Some(&user_input), config)?;
     
     // Print generated code to stdout
-    if let Some(content) = &api_response.content {
+    if api_response.choices.len() > 1 {
+        for (i, choice) in api_response.choices.iter().enumerate() {
+            println!("--- Choice {} ---\n{}", i + 1, choice);
+        }
+    } else if let Some(content) = &api_response.content {
         println!("{}", content);
     } else {
         println!("No content was generated.");
@@ -42,31 +135,267 @@ pub async fn handle_code_completion(prompt: &str, suffix: &str, max_tokens: Opti
     Ok(())
 }
 
+/// Apply the configured FIM post-processing to every choice of a response
+fn postprocess_response(response: &mut ApiResponse, prompt: &str, suffix: &str, config: &Config) {
+    let options = PostProcessOptions::from_config(config);
+    response.choices = response.choices.iter().map(|c| postprocess(c, prompt, suffix, &options)).collect();
+    response.content = response.content.as_deref().map(|c| postprocess(c, prompt, suffix, &options));
+}
+
+/// Request completions until one spliced into the file passes the syntax check
+/// (and `cargo check` if asked), feeding errors back through the prefix
+async fn complete_verified(
+    client: &CodestralClient,
+    prompt: &str,
+    full_prompt: &str,
+    suffix: &str,
+    max_tokens: u32,
+    args: &VerifyArgs,
+    config: &Config,
+) -> Result<(ApiResponse, Vec<VerifyReport>)> {
+    let file = Path::new(args.file.as_deref().unwrap_or_default());
+    if file.extension().and_then(|e| e.to_str()) != Some("rs") {
+        return Err(AppError::cli_usage("--verify only supports Rust (.rs) files"));
+    }
+    let source = fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    if splice_at_cursor(&source, prompt, "", suffix).is_none() {
+        return Err(AppError::cli_usage(format!("The prompt and suffix were not found in {}", file.display())));
+    }
+    
+    let max_attempts = args.verify_retries.unwrap_or(config.verify_max_retries) + 1;
+    let timeout = Duration::from_secs(config.verify_check_timeout);
+    let mut request_prompt = full_prompt.to_string();
+    let mut reports = Vec::new();
+    
+    loop {
+        let response_json = client.code_completion(&request_prompt, suffix, max_tokens).await?;
+        let mut api_response = extract_response_fields(&response_json);
+        postprocess_response(&mut api_response, prompt, suffix, config);
+        
+        let completion = api_response.content.clone().unwrap_or_default();
+        let spliced = splice_at_cursor(&source, prompt, &completion, suffix).unwrap_or_default();
+        let mut diagnostics = check_rust_syntax(&spliced);
+        if diagnostics.is_empty() && args.cargo_check {
+            diagnostics = cargo_check_spliced(file, &spliced, timeout).await?;
+        }
+        
+        let passed = !diagnostics.iter().any(Diagnostic::is_error);
+        if !passed {
+            warn!("Completion attempt {} failed verification with {} diagnostics", reports.len() + 1, diagnostics.len());
+            request_prompt = feedback_prefix(full_prompt, &completion, &diagnostics, "//");
+        }
+        reports.push(VerifyReport { attempt: reports.len() + 1, completion, diagnostics, passed });
+        
+        if passed || reports.len() >= max_attempts {
+            return Ok((api_response, reports));
+        }
+    }
+}
+
+/// Rank the candidates from all responses, log each under one group id and
+/// print the best or the one picked by the user
+fn report_candidates(
+    prompt: &str,
+    suffix: &str,
+    responses: &[Value],
+    args: &CandidateArgs,
+    metadata: &Value,
+    config: &Config,
+) -> Result<()> {
+    let api_responses: Vec<ApiResponse> = responses
+        .iter()
+        .map(|response| {
+            let mut api_response = extract_response_fields(response);
+            postprocess_response(&mut api_response, prompt, suffix, config);
+            api_response
+        })
+        .collect();
+    let texts = dedupe_candidates(api_responses.iter().flat_map(|r| r.choices.clone()).collect());
+    if texts.is_empty() {
+        println!("No content was generated.");
+        return Ok(());
+    }
+    
+    let ranked = rank_candidates(&texts, prompt, suffix, args.language.as_deref());
+    info!("Ranked {} distinct candidates", ranked.len());
+    
+    let selected = if args.pick { pick_candidate(&ranked)? } else { 0 };
+    let group_id = Uuid::new_v4().to_string();
+    // Usage of all requests is logged once, on the first candidate, so
+    // summing the log does not count a response once per candidate
+    let completion_tokens: i64 = api_responses.iter().map(|r| r.completion_tokens).sum();
+    let total_tokens: i64 = api_responses.iter().map(|r| r.total_tokens).sum();
+    
+    for (rank, candidate) in ranked.iter().enumerate() {
+        // Log each candidate against the response it came from
+        let source = api_responses
+            .iter()
+            .find(|r| r.choices.contains(&candidate.text))
+            .unwrap_or(&api_responses[0]);
+        let api_response = ApiResponse {
+            content: Some(candidate.text.clone()),
+            choices: vec![candidate.text.clone()],
+            completion_tokens: if rank == 0 { completion_tokens } else { 0 },
+            total_tokens: if rank == 0 { total_tokens } else { 0 },
+            ..source.clone()
+        };
+        
+        let mut entry = metadata.clone();
+        entry["group_id"] = json!(group_id);
+        entry["candidate"] = json!({
+            "rank": rank + 1,
+            "count": ranked.len(),
+            "selected": rank == selected,
+            "score": candidate.score,
+            "bracket_errors": candidate.bracket_errors,
+            "syntax_valid": candidate.syntax_valid,
+            "suffix_overlap": candidate.suffix_overlap,
+        });
+        log_generation_with_metadata("Code", &api_response, &entry, config)?;
+        
+        if rank == selected {
+            let user_input = format!("{}{}", prompt, suffix);
+            write_generation_content("Code", &api_response, Some(&user_input), config)?;
+        }
+    }
+    
+    println!("{}", ranked[selected].text);
+    Ok(())
+}
+
+/// Show ranked candidates on stderr and read the chosen number from stdin
+fn pick_candidate(ranked: &[RankedCandidate]) -> Result<usize> {
+    for (i, candidate) in ranked.iter().enumerate() {
+        eprintln!("--- Candidate {} (score {:.2}) ---\n{}", i + 1, candidate.score, candidate.text);
+    }
+    eprint!("Choose a candidate [1-{}] (default 1): ", ranked.len());
+    io::stderr().flush()?;
+    
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(0);
+    }
+    match answer.parse::<usize>() {
+        Ok(n) if (1..=ranked.len()).contains(&n) => Ok(n - 1),
+        _ => Err(AppError::cli_usage(format!("Invalid candidate '{}'", answer))),
+    }
+}
+
 /// Handle chat command
-pub async fn handle_chat(message: &str, max_tokens: Option<u32>, config: &Config) -> Result<()> {
+pub async fn handle_chat(
+    message: Option<&str>,
+    max_tokens: Option<u32>,
+    options: &ChatArgs,
+    config: &Config,
+) -> Result<()> {
     // Use provided max_tokens or default from config
     let max_tokens = max_tokens.or(Some(config.max_tokens_chat));
+    let ChatArgs { input, persona, template, context, tools, structured, sampling } = options;
+    let config = &apply_persona(persona, config)?;
+    
+    if tools.tools && structured.is_enabled() {
+        return Err(AppError::cli_usage("--tools cannot be combined with --json or --schema"));
+    }
+    
+    let schema = match &structured.schema {
+        Some(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read schema file {}", path))?;
+            Some(serde_json::from_str::<Value>(&content)
+                .with_context(|| format!("Failed to parse schema file {}", path))?)
+        },
+        None => None,
+    };
+    
+    let message = read_chat_message(message, input, template, config)?;
+    let message = message.as_str();
+    
+    let snippets = collect_context(message, context, config).await?;
+    
+    let mut messages = Vec::new();
+    if !snippets.is_empty() {
+        messages.push(ChatMessage::system(format_for_system_message(&snippets)));
+    }
+    messages.push(ChatMessage::user(message));
     
     info!("Initializing Mistral client");
-    let client = MistralClient::new(config.clone())?;
+    let client = MistralClient::new(config.clone())?
+        .with_sampling(config.sampling_params().merge(&sampling.to_params()));
     
     info!("Sending chat request with message: {}", message);
     
-    // Make API request
-    let response_json = client.chat(message, max_tokens).await?;
+    let mut metadata = context_metadata(context, &snippets);
+    if let Some(name) = &template.template {
+        metadata["template"] = json!(name);
+    }
+    if let Some(name) = &persona.persona {
+        metadata["persona"] = json!(name);
+    }
+    if !input.attach.is_empty() {
+        metadata["attachments"] = json!(input.attach);
+    }
+    let mut structured_value = None;
+    
+    // Make API request, letting the model call workspace tools if enabled
+    let response_json = if tools.tools {
+        let workspace = Arc::new(Workspace::new(
+            &tools.workspace,
+            config.command_allowlist.clone(),
+            Duration::from_secs(config.tool_command_timeout),
+            if tools.yes { auto_approve() } else { prompt_confirmation()? },
+        )?);
+        let mut registry = ToolRegistry::new();
+        workspace.register_tools(&mut registry);
+        info!("Enabled {} tools in workspace {}", registry.len(), workspace.root().display());
+        
+        // Record every tool call with its arguments and result, even when the chat failed
+        match client.chat_with_tools(messages, &registry, max_tokens, config.tool_max_iterations).await {
+            Ok(outcome) => {
+                metadata["tool_calls"] = serde_json::to_value(&outcome.invocations)?;
+                outcome.response
+            },
+            Err(failure) => {
+                metadata["tool_calls"] = serde_json::to_value(&failure.invocations)?;
+                metadata["error"] = json!(failure.error.to_string());
+                log_generation_with_metadata("Chat", &extract_response_fields(&Value::Null), &metadata, config)?;
+                return Err(failure.into());
+            },
+        }
+    } else if structured.is_enabled() {
+        let max_retries = structured.max_retries.unwrap_or(config.structured_max_retries);
+        let outcome = client
+            .chat_structured_with_response::<Value>(messages, schema.as_ref(), max_tokens, max_retries)
+            .await?;
+        
+        metadata["structured"] = json!({
+            "schema": structured.schema,
+            "attempts": outcome.attempts,
+        });
+        structured_value = Some(outcome.value);
+        outcome.response
+    } else {
+        client.chat_with_messages(messages, max_tokens).await?
+    };
     
     // Extract and process the response
-    use crate::api::extract_response_fields;
     let api_response = extract_response_fields(&response_json);
     
     // Log generation metrics
-    log_generation("Chat", &api_response, config)?;
+    log_generation_with_metadata("Chat", &api_response, &metadata, config)?;
     
     // Write generation content
     write_generation_content("Chat", &api_response, Some(message), config)?;
     
-    // Print response to stdout
-    if let Some(content) = &api_response.content {
+    // Print response to stdout; JSON output is printed bare so it can be piped
+    if let Some(value) = &structured_value {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else if api_response.choices.len() > 1 {
+        for (i, choice) in api_response.choices.iter().enumerate() {
+            println!("Assistant [{}]: {}", i + 1, choice);
+        }
+    } else if let Some(content) = &api_response.content {
         println!("Assistant: {}", content);
     } else {
         println!("No response was generated.");
@@ -75,8 +404,1006 @@ pub async fn handle_chat(message: &str, max_tokens: Option<u32>, config: &Config
     Ok(())
 }
 
+/// Apply the requested persona and `--system` prompt to the configuration
+fn apply_persona(args: &PersonaArgs, config: &Config) -> Result<Config> {
+    let mut config = match &args.persona {
+        Some(name) => config.with_persona(name)?,
+        None => config.clone(),
+    };
+    if let Some(system) = &args.system {
+        config.system_prompt = Some(system.clone());
+    }
+    Ok(config)
+}
+
+/// Assemble the chat message from the argument or message file, the
+/// template and any attachments, reading stdin at most once
+fn read_chat_message(message: Option<&str>, input: &InputArgs, template: &TemplateArgs, config: &Config) -> Result<String> {
+    let mut stdin = LazyInput::new(io::stdin());
+    let message = match (&input.message_file, message) {
+        (Some(path), _) => Some(read_message_file(path, &mut stdin)?),
+        (None, Some(message)) => Some(stdin.resolve(message)?),
+        (None, None) => None,
+    };
+    
+    let message = build_message(message.as_deref(), template, &mut stdin, config)?;
+    let attachments = input.attach
+        .iter()
+        .map(|path| Attachment::load(path))
+        .collect::<Result<Vec<_>>>()?;
+    let message = with_attachments(&message, &attachments);
+    
+    if message.trim().is_empty() {
+        return Err(AppError::cli_usage("The chat message is empty"));
+    }
+    Ok(message)
+}
+
+/// Build the chat message, rendering a template if one was requested
+fn build_message<R: Read>(
+    message: Option<&str>,
+    args: &TemplateArgs,
+    stdin: &mut LazyInput<R>,
+    config: &Config,
+) -> Result<String> {
+    let Some(name) = &args.template else {
+        return Ok(message.unwrap_or_default().to_string());
+    };
+    
+    let library = TemplateLibrary::load(config)?;
+    let template = library.get(name)?;
+    
+    let mut vars = parse_vars(&args.vars)?;
+    if let Some(message) = message {
+        vars.entry("input".to_string()).or_insert_with(|| message.to_string());
+    }
+    
+    let stdin = if template.uses_stdin() {
+        Some(stdin.read()?)
+    } else {
+        None
+    };
+    
+    let mut rendered = template.render(&vars, stdin.as_deref())?;
+    
+    // A message is appended when the template has no place for it
+    if let Some(message) = message.filter(|_| !template.variables().iter().any(|v| v == "input")) {
+        rendered = format!("{}\n\n{}", rendered.trim_end(), message);
+    }
+    Ok(rendered)
+}
+
+/// Handle template commands
+pub fn handle_template(action: &TemplateCommands, config: &Config) -> Result<()> {
+    let library = TemplateLibrary::load(config)?;
+    match action {
+        TemplateCommands::List => {
+            let templates: Vec<&Template> = library.list().collect();
+            if templates.is_empty() {
+                println!("No templates found in config or {}", config.get_templates_dir_path().display());
+            }
+            for template in templates {
+                let source = match &template.source {
+                    TemplateSource::Config => "config".to_string(),
+                    TemplateSource::File(path) => path.display().to_string(),
+                };
+                println!(
+                    "{:<20} {:<40} {}",
+                    template.name,
+                    template.def.description.as_deref().unwrap_or(""),
+                    source
+                );
+            }
+        },
+        TemplateCommands::Show { name } => {
+            let template = library.get(name)?;
+            if let Some(description) = &template.def.description {
+                println!("# {}", description);
+            }
+            let variables = template.variables();
+            if !variables.is_empty() {
+                let listed: Vec<String> = variables
+                    .iter()
+                    .map(|v| match template.def.defaults.get(v) {
+                        Some(default) => format!("{} (default: {})", v, default),
+                        None => v.clone(),
+                    })
+                    .collect();
+                println!("# Variables: {}", listed.join(", "));
+            }
+            println!("{}", template.def.text);
+        },
+        TemplateCommands::New { name, description } => {
+            let path = create_template(&config.get_templates_dir_path(), name, description.as_deref())?;
+            println!("Template '{}' created at {}", name, path.display());
+        },
+    }
+    Ok(())
+}
+
+pub fn handle_persona(action: &PersonaCommands, config: &Config) -> Result<()> {
+    match action {
+        PersonaCommands::List => {
+            if config.personas.is_empty() {
+                println!("No personas defined in the configuration");
+            }
+            for (name, persona) in &config.personas {
+                println!(
+                    "{:<20} {:<40} {}",
+                    name,
+                    persona.description.as_deref().unwrap_or(""),
+                    persona.model.as_deref().unwrap_or(&config.chat_model)
+                );
+            }
+        },
+        PersonaCommands::Show { name } => {
+            let applied = config.with_persona(name)?;
+            if let Some(description) = &config.personas[name].description {
+                println!("# {}", description);
+            }
+            println!("# Model: {}", applied.chat_model);
+            println!("# Temperature: {}", applied.chat_temperature);
+            println!("{}", applied.system_prompt.as_deref().unwrap_or("(no system prompt)"));
+        },
+    }
+    Ok(())
+}
+
+/// Gather context snippets for a query if any context source was requested
+async fn collect_context(query: &str, args: &ContextArgs, config: &Config) -> Result<Vec<ContextSnippet>> {
+    if !args.is_enabled() {
+        return Ok(Vec::new());
+    }
+    
+    let dir = args.context.as_deref().map(Path::new);
+    let source = ContextSource {
+        dir,
+        file_globs: &args.context_files,
+        token_budget: args.context_budget.unwrap_or(config.context_token_budget),
+        chunk_lines: config.index_chunk_lines,
+        index_file_name: &config.index_file_name,
+    };
+    
+    // Only create an embeddings client when there is an index to search
+    let embedder = match dir {
+        Some(dir) if index_path(dir, &config.index_file_name).exists() => Some(EmbeddingsClient::new(config.clone())?),
+        _ => None,
+    };
+    
+    let snippets = gather_context(query, &source, embedder.as_ref().map(|e| e as &dyn Embedder)).await?;
+    info!("Including {} context snippets", snippets.len());
+    Ok(snippets)
+}
+
+/// Build a context query from the lines nearest the cursor
+fn fim_context_query(prompt: &str, suffix: &str) -> String {
+    let before: Vec<&str> = prompt.lines().rev().take(40).collect();
+    let after: Vec<&str> = suffix.lines().take(10).collect();
+    let mut query: Vec<&str> = before.into_iter().rev().collect();
+    query.extend(after);
+    query.join("\n")
+}
+
+/// Log metadata recording which context snippets were included
+fn context_metadata(args: &ContextArgs, snippets: &[ContextSnippet]) -> Value {
+    if args.is_enabled() {
+        json!({ "context": snippets_metadata(snippets) })
+    } else {
+        json!({})
+    }
+}
+
+/// Handle embed command
+pub async fn handle_embed(
+    inputs: &[String],
+    files: &[String],
+    lines: bool,
+    format: EmbedFormat,
+    output: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    let mut texts = Vec::new();
+    let mut read_stdin = inputs.is_empty() && files.is_empty();
+    
+    for input in inputs {
+        if input == "-" {
+            read_stdin = true;
+        } else {
+            texts.push(input.clone());
+        }
+    }
+    
+    for file in files {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read input file {}", file))?;
+        push_embed_input(&mut texts, content, lines);
+    }
+    
+    if read_stdin {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        push_embed_input(&mut texts, content, lines);
+    }
+    
+    if texts.is_empty() {
+        return Err(AppError::cli_usage("No input text to embed"));
+    }
+    
+    info!("Initializing embeddings client");
+    let client = EmbeddingsClient::new(config.clone())?;
+    
+    info!("Sending {} inputs for embedding", texts.len());
+    let result = client.embed_with_usage(&texts).await?;
+    
+    // Log generation metrics
+    log_generation("Embed", &result.response, config)?;
+    
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(fs::File::create(path)
+            .with_context(|| format!("Failed to create output file {}", path))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    
+    match format {
+        EmbedFormat::Jsonl => write_jsonl(&mut writer, &texts, &result.embeddings)?,
+        EmbedFormat::Binary => write_binary(&mut writer, &result.embeddings)?,
+    }
+    writer.flush()?;
+    
+    if let Some(path) = output {
+        println!("Wrote {} embeddings to {}", result.embeddings.len(), path);
+    }
+    
+    Ok(())
+}
+
+fn push_embed_input(texts: &mut Vec<String>, content: String, lines: bool) {
+    if lines {
+        texts.extend(content.lines().filter(|l| !l.trim().is_empty()).map(|l| l.to_string()));
+    } else if !content.trim().is_empty() {
+        texts.push(content);
+    }
+}
+
+/// Handle index commands
+pub async fn handle_index(action: &IndexCommands, config: &Config) -> Result<()> {
+    let client = EmbeddingsClient::new(config.clone())?;
+    
+    let (root, index, stats) = match action {
+        IndexCommands::Build { dir } => {
+            info!("Building index of {}", dir);
+            let root = Path::new(dir);
+            let (index, stats) = CodeIndex::build(root, &client, config.index_chunk_lines, &config.index_file_name).await?;
+            (root, index, stats)
+        },
+        IndexCommands::Update { dir } => {
+            let root = Path::new(dir);
+            let path = index_path(root, &config.index_file_name);
+            if !path.exists() {
+                return Err(AppError::cli_usage(format!("No index found at {}; run `index build` first", path.display())));
+            }
+            
+            info!("Updating index of {}", dir);
+            let mut index = CodeIndex::load(&path)?;
+            let stats = index.update(root, &client, &config.index_file_name).await?;
+            (root, index, stats)
+        },
+    };
+    
+    let path = index_path(root, &config.index_file_name);
+    index.save(&path)?;
+    
+    println!(
+        "Indexed {} files ({} chunks embedded), {} unchanged, {} removed. Index saved to {}",
+        stats.files_embedded,
+        stats.chunks_embedded,
+        stats.files_unchanged,
+        stats.files_removed,
+        path.display()
+    );
+    
+    Ok(())
+}
+
+/// Handle search command
+pub async fn handle_search(query: &str, dir: &str, top_k: usize, config: &Config) -> Result<()> {
+    let path = index_path(Path::new(dir), &config.index_file_name);
+    if !path.exists() {
+        return Err(AppError::cli_usage(format!("No index found at {}; run `index build` first", path.display())));
+    }
+    
+    let index = CodeIndex::load(&path)?;
+    let client = EmbeddingsClient::new(config.clone())?;
+    let hits = index.search(&client, query, top_k).await?;
+    
+    if hits.is_empty() {
+        println!("No results.");
+    }
+    for hit in &hits {
+        let first_line = hit.text.lines().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+        println!("{}:{}-{}  {:.4}  {}", hit.path, hit.start_line, hit.end_line, hit.score, first_line);
+    }
+    
+    Ok(())
+}
+
+/// Handle compare command
+pub async fn handle_compare(
+    prompt: &str,
+    models: &[String],
+    suffix: Option<&str>,
+    max_tokens: Option<u32>,
+    format: ReportFormat,
+    output: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    let prompt = &LazyInput::new(io::stdin()).resolve(prompt)?;
+    let comparison_id = Uuid::new_v4().to_string();
+    info!("Comparing {} models (comparison {})", models.len(), comparison_id);
+    
+    let (api_type, results) = match suffix {
+        Some(suffix) => {
+            let max_tokens = max_tokens.unwrap_or(config.max_tokens_code);
+            let clients = models
+                .iter()
+                .map(|m| Ok((m.clone(), CodestralClient::new(Config { code_model: m.clone(), ..config.clone() })?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            let clients = &clients;
+            let results = compare_models(models, |model| async move {
+                clients[&model].code_completion(prompt, suffix, max_tokens).await
+            }).await;
+            ("Code", results)
+        },
+        None => {
+            let max_tokens = max_tokens.or(Some(config.max_tokens_chat));
+            let clients = models
+                .iter()
+                .map(|m| Ok((m.clone(), MistralClient::new(Config { chat_model: m.clone(), ..config.clone() })?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            let clients = &clients;
+            let results = compare_models(models, |model| async move {
+                clients[&model].chat_with_messages(vec![ChatMessage::user(prompt)], max_tokens).await
+            }).await;
+            ("Chat", results)
+        },
+    };
+    
+    // Log every successful result under the shared comparison id
+    for result in &results {
+        if let Some(response) = &result.response {
+            let metadata = json!({ "comparison_id": comparison_id, "latency_ms": result.latency_ms });
+            log_generation_with_metadata(api_type, response, &metadata, config)?;
+        }
+    }
+    
+    let report = match format {
+        ReportFormat::Text => {
+            let width = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(120);
+            render_side_by_side(&results, width)
+        },
+        ReportFormat::Markdown => render_markdown(prompt, &comparison_id, &results),
+        ReportFormat::Html => render_html(prompt, &comparison_id, &results),
+        ReportFormat::Json => format!("{}\n", serde_json::to_string_pretty(&results)?),
+    };
+    
+    match output {
+        Some(path) => {
+            fs::write(path, report).with_context(|| format!("Failed to write report to {}", path))?;
+            println!("Comparison report written to {}", path);
+        },
+        None => print!("{}", report),
+    }
+    
+    Ok(())
+}
+
+/// Handle review command
+pub async fn handle_review(
+    diff: &DiffArgs,
+    format: FindingsFormat,
+    output: Option<&str>,
+    max_tokens: Option<u32>,
+    persona: &PersonaArgs,
+    config: &Config,
+) -> Result<()> {
+    let config = &apply_persona(persona, config)?;
+    let max_tokens = max_tokens.or(Some(config.max_tokens_chat));
+    
+    let text = git_diff(Path::new(&diff.repo), &diff.source())?;
+    let chunks = split_diff(&text, config.review_chunk_tokens)?;
+    
+    let mut findings = Vec::new();
+    if chunks.is_empty() {
+        eprintln!("No changes to review");
+    } else {
+        let review_id = Uuid::new_v4().to_string();
+        info!("Reviewing {} chunks (review {})", chunks.len(), review_id);
+        
+        let client = MistralClient::new(config.clone())?;
+        let schema = findings_schema();
+        
+        for (i, chunk) in chunks.iter().enumerate() {
+            info!("Reviewing chunk {} of {} ({})", i + 1, chunks.len(), chunk.path);
+            let outcome = client
+                .chat_structured_with_response::<ReviewResponse>(
+                    vec![ChatMessage::user(review_prompt(chunk))],
+                    Some(&schema),
+                    max_tokens,
+                    config.structured_max_retries,
+                )
+                .await?;
+            
+            let metadata = json!({
+                "review_id": review_id,
+                "file": chunk.path,
+                "chunk": i + 1,
+                "findings": outcome.value.findings.len(),
+            });
+            log_generation_with_metadata("Review", &extract_response_fields(&outcome.response), &metadata, config)?;
+            
+            // Each chunk covers a single file, whatever path the model repeated back
+            findings.extend(outcome.value.findings.into_iter().map(|mut finding| {
+                finding.file = chunk.path.clone();
+                finding
+            }));
+        }
+    }
+    sort_findings(&mut findings);
+    
+    let report = match format {
+        FindingsFormat::Text => render_text(&findings),
+        FindingsFormat::Json => format!("{}\n", serde_json::to_string_pretty(&findings)?),
+        FindingsFormat::Sarif => format!("{}\n", serde_json::to_string_pretty(&render_sarif(&findings))?),
+    };
+    
+    match output {
+        Some(path) => {
+            fs::write(path, report).with_context(|| format!("Failed to write findings to {}", path))?;
+            println!("{} findings written to {}", findings.len(), path);
+        },
+        None => print!("{}", report),
+    }
+    
+    Ok(())
+}
+
+/// Handle commit-msg command
+pub async fn handle_commit_msg(
+    install: bool,
+    force: bool,
+    output: Option<&str>,
+    repo: &str,
+    max_tokens: Option<u32>,
+    config_file: &str,
+    config: &Config,
+) -> Result<()> {
+    let repo = Path::new(repo);
+    if install {
+        let executable = std::env::current_exe()?;
+        let hook = install_hook(repo, &executable, Path::new(config_file), force)?;
+        println!("Installed prepare-commit-msg hook at {}", hook.display());
+        return Ok(());
+    }
+    
+    let max_tokens = max_tokens.or(Some(config.max_tokens_chat));
+    let diff = git_diff(repo, &DiffSource::Staged)?;
+    if diff.trim().is_empty() {
+        return Err(AppError::cli_usage("No staged changes to describe"));
+    }
+    
+    let client = MistralClient::new(config.clone())?;
+    let style_guide = config.commit_style_guide.as_deref().unwrap_or(DEFAULT_STYLE_GUIDE);
+    
+    // Long diffs are summarised per file first to stay within the context limit
+    let summarised = estimate_tokens(&diff) > config.commit_diff_tokens;
+    let changes = if summarised {
+        let chunks = split_diff(&diff, config.review_chunk_tokens)?;
+        info!("Summarising {} chunks of a long diff", chunks.len());
+        let prompts: Vec<String> = chunks.iter().map(summary_prompt).collect();
+        let responses: Vec<Value> = stream::iter(prompts.iter().map(|prompt| client.chat(prompt, max_tokens)))
+            .buffered(SUMMARY_CONCURRENCY)
+            .try_collect()
+            .await?;
+        
+        let mut summaries: Vec<(String, String)> = Vec::new();
+        for (chunk, response) in chunks.iter().zip(&responses) {
+            let api_response = extract_response_fields(response);
+            log_generation_with_metadata("CommitSummary", &api_response, &json!({ "file": chunk.path }), config)?;
+            let summary = api_response.content.unwrap_or_default();
+            match summaries.last_mut() {
+                Some((path, text)) if *path == chunk.path => text.push_str(&format!(" {}", summary.trim())),
+                _ => summaries.push((chunk.path.clone(), summary)),
+            }
+        }
+        summaries.extend(deleted_files(&diff).into_iter().map(|path| (path, "File deleted.".to_string())));
+        format_summaries(&summaries)
+    } else {
+        format!("```diff\n{}```", diff)
+    };
+    
+    let response = client.chat(&message_prompt(style_guide, &changes), max_tokens).await?;
+    let api_response = extract_response_fields(&response);
+    log_generation_with_metadata("CommitMsg", &api_response, &json!({ "summarised": summarised }), config)?;
+    
+    let message = clean_message(api_response.content.as_deref().unwrap_or_default());
+    if message.is_empty() {
+        return Err(AppError::api("The model returned an empty commit message"));
+    }
+    
+    match output {
+        Some(path) => write_message_file(Path::new(path), &message)?,
+        None => println!("{}", message),
+    }
+    Ok(())
+}
+
+/// Handle gen-tests command
+pub async fn handle_gen_tests(
+    file: &str,
+    function: &str,
+    integration: bool,
+    run: bool,
+    max_tokens: Option<u32>,
+    config: &Config,
+) -> Result<()> {
+    let path = Path::new(file);
+    let code = fs::read_to_string(path).with_context(|| format!("Failed to read {}", file))?;
+    let source = find_function(&code, function)?;
+    let name = tests_name(&source);
+    let crate_root = find_crate_root(path);
+    
+    let (target, output_path) = if integration {
+        let root = crate_root.clone()
+            .ok_or_else(|| AppError::cli_usage(format!("No Cargo.toml found above {}", file)))?;
+        let canonical = fs::canonicalize(path).with_context(|| format!("Failed to resolve {}", file))?;
+        let target = TestTarget::Integration { crate_name: crate_name(&root)?, module_path: module_path(&canonical, &root) };
+        (target, root.join("tests").join(format!("{}.rs", name)))
+    } else {
+        (TestTarget::Module { module_name: name.clone() }, path.to_path_buf())
+    };
+    if integration && output_path.exists() {
+        return Err(AppError::cli_usage(format!("{} already exists", output_path.display())));
+    }
+    if !integration && code.contains(&format!("mod {} ", name)) {
+        return Err(AppError::cli_usage(format!("{} already contains a module named {}", file, name)));
+    }
+    
+    info!("Generating tests for {} (lines {}-{} of {})", source.name, source.start_line, source.end_line, file);
+    let client = MistralClient::new(config.clone())?;
+    let mut messages = vec![ChatMessage::user(test_prompt(&source, &target))];
+    
+    // Re-prompt with the parse error while the generated code is not valid Rust
+    let mut attempt = 0;
+    let (content, api_response) = loop {
+        attempt += 1;
+        let response = client.chat_with_messages(messages.clone(), max_tokens.or(Some(config.max_tokens_chat))).await?;
+        let api_response = extract_response_fields(&response);
+        let tests = extract_code(api_response.content.as_deref().unwrap_or_default());
+        let content = match &target {
+            TestTarget::Module { module_name } => append_module(&code, &wrap_test_module(module_name, &tests)),
+            TestTarget::Integration { .. } => format!("{}\n", tests),
+        };
+        
+        let errors = check_rust_syntax(&content);
+        if errors.is_empty() {
+            break (content, api_response);
+        }
+        if attempt > config.verify_max_retries {
+            return Err(AppError::api(format!(
+                "Generated tests are not valid Rust after {} attempts: {}", attempt, errors[0].message
+            )));
+        }
+        warn!("Generated tests failed to parse (attempt {}): {}", attempt, errors[0].message);
+        messages.push(ChatMessage::assistant(api_response.content.unwrap_or_default()));
+        messages.push(ChatMessage::user(format!(
+            "That code does not parse: {}. Reply with the corrected code block.", errors[0].message
+        )));
+    };
+    
+    let metadata = json!({
+        "function": source.name,
+        "file": file,
+        "target": if integration { "integration" } else { "module" },
+        "attempts": attempt,
+    });
+    log_generation_with_metadata("GenTests", &api_response, &metadata, config)?;
+    
+    fs::write(&output_path, &content).with_context(|| format!("Failed to write {}", output_path.display()))?;
+    println!("Wrote tests for `{}` to {}", source.name, output_path.display());
+    
+    if run {
+        let root = crate_root.ok_or_else(|| AppError::cli_usage(format!("No Cargo.toml found above {}", file)))?;
+        let args = if integration {
+            vec!["--test".to_string(), name]
+        } else {
+            vec![format!("{}::", name)]
+        };
+        let results = run_cargo_test(&root, &args, Duration::from_secs(config.gen_tests_timeout)).await?;
+        for result in &results {
+            println!("{} {}", if result.passed { "PASS" } else { "FAIL" }, result.name);
+        }
+        let passed = results.iter().filter(|r| r.passed).count();
+        println!("{} of {} generated tests passed", passed, results.len());
+    }
+    
+    Ok(())
+}
+
+/// Handle gen-docs command
+pub async fn handle_gen_docs(path: &str, check: bool, write: bool, max_tokens: Option<u32>, config: &Config) -> Result<()> {
+    let files = collect_rust_files(Path::new(path), &config.index_file_name)?;
+    let max_tokens = max_tokens.unwrap_or(config.gen_docs_max_tokens);
+    
+    if check {
+        let mut count = 0;
+        for (file, content) in &files {
+            let items = match find_undocumented(content) {
+                Ok(items) => items,
+                Err(e) => {
+                    warn!("Skipping {}: {}", file, e);
+                    continue;
+                },
+            };
+            for item in items {
+                println!("{}:{}: undocumented pub {} `{}`", file, item.line, item.kind, item.name);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            return Err(AppError::cli_usage(format!("{} public items are missing documentation", count)));
+        }
+        println!("All public items are documented");
+        return Ok(());
+    }
+    
+    let client = CodestralClient::new(config.clone())?;
+    let mut documented = 0;
+    for (file, content) in &files {
+        let items = match find_undocumented(content) {
+            Ok(items) => items,
+            Err(e) => {
+                warn!("Skipping {}: {}", file, e);
+                continue;
+            },
+        };
+        
+        // Work bottom-up so inserted lines do not shift the items still to do
+        let mut updated = content.clone();
+        for item in items.iter().rev() {
+            info!("Documenting {} `{}` at {}:{}", item.kind, item.name, file, item.line);
+            let (prefix, suffix) = doc_fim_parts(&updated, item);
+            let response = client.code_completion(&prefix, &suffix, max_tokens).await?;
+            let api_response = extract_response_fields(&response);
+            
+            let metadata = json!({ "file": file, "item": item.name, "kind": item.kind, "line": item.line });
+            log_generation_with_metadata("GenDocs", &api_response, &metadata, config)?;
+            
+            let docs = doc_lines(api_response.content.as_deref().unwrap_or_default(), &item.indent);
+            if docs.is_empty() {
+                warn!("No documentation generated for `{}` in {}", item.name, file);
+                continue;
+            }
+            updated = insert_docs(&updated, item, &docs);
+            documented += 1;
+        }
+        
+        if updated == *content {
+            continue;
+        }
+        if write {
+            fs::write(file, &updated).with_context(|| format!("Failed to write {}", file))?;
+            println!("Documented {} in place", file);
+        } else {
+            print!("{}", unified_patch(file, content, &updated));
+        }
+    }
+    info!("Generated documentation for {} items", documented);
+    
+    Ok(())
+}
+
+/// Handle explain command
+pub async fn handle_explain(
+    target: &str,
+    depth: ExplainDepth,
+    raw: bool,
+    max_tokens: Option<u32>,
+    persona: &PersonaArgs,
+    config: &Config,
+) -> Result<()> {
+    let config = &apply_persona(persona, config)?;
+    let max_tokens = max_tokens.or(Some(config.max_tokens_chat));
+    let depth = depth.to_depth();
+    
+    let (path, range) = parse_target(target)?;
+    let selection = Selection::read(&path, range, config.explain_context_lines)?;
+    
+    // A user-defined "explain" template replaces the built-in prompt
+    let library = TemplateLibrary::load(config)?;
+    let builtin = default_template();
+    let template = library.get(EXPLAIN_TEMPLATE_NAME).unwrap_or(&builtin);
+    let message = template.render(&selection.template_vars(depth), None)?;
+    
+    info!("Explaining {} lines {}-{}", selection.path, selection.start, selection.end);
+    let client = MistralClient::new(config.clone())?;
+    let response = client.chat(&message, max_tokens).await?;
+    let api_response = extract_response_fields(&response);
+    
+    let metadata = json!({
+        "file": selection.path,
+        "start_line": selection.start,
+        "end_line": selection.end,
+        "depth": depth.as_str(),
+    });
+    log_generation_with_metadata("Explain", &api_response, &metadata, config)?;
+    write_generation_content("Explain", &api_response, Some(&message), config)?;
+    
+    let answer = api_response.content.as_deref().unwrap_or("No response was generated.");
+    if raw || !console::user_attended() {
+        println!("{}", answer);
+    } else {
+        print!("{}", render_terminal(answer, true));
+    }
+    
+    Ok(())
+}
+
+/// Handle serve-lsp command
+pub async fn handle_serve_lsp(debounce_ms: Option<u64>, max_tokens: Option<u32>, config: &Config) -> Result<()> {
+    let config = Config {
+        lsp_debounce_ms: debounce_ms.unwrap_or(config.lsp_debounce_ms),
+        lsp_max_tokens: max_tokens.unwrap_or(config.lsp_max_tokens),
+        ..config.clone()
+    };
+    info!("Starting language server with model '{}'", config.code_model);
+    serve_stdio(&config).await
+}
+
+/// Handle serve command
+pub async fn handle_serve(bind: Option<&str>, budget: Option<u64>, allow_remote: bool, config: &Config) -> Result<()> {
+    let config = Config { serve_token_budget: budget.or(config.serve_token_budget), ..config.clone() };
+    let bind = bind.unwrap_or(&config.serve_bind).to_string();
+    check_bind(&bind, allow_remote).await?;
+    let mut state = ProxyState::new(config.clone())?;
+    if allow_remote {
+        let token = std::env::var(&config.serve_token_env).ok().filter(|t| !t.is_empty()).ok_or_else(|| {
+            AppError::config(format!("--allow-remote needs a bearer token in the {} environment variable", config.serve_token_env))
+        })?;
+        state = state.with_token(token);
+    }
+    let listener = TcpListener::bind(&bind).await.with_context(|| format!("Failed to bind {}", bind))?;
+
+    let address = listener.local_addr().with_context(|| "Failed to read the bound address")?;
+    println!("Proxy listening on http://{}", address);
+    println!("  POST /v1/chat/completions -> {}", config.chat_api_url);
+    println!("  POST /v1/fim/completions  -> {}", config.code_api_url);
+
+    let state = Arc::new(state);
+    serve(listener, state.clone()).await?;
+    println!("Proxy stopped after spending {} tokens", state.tokens_used());
+    Ok(())
+}
+
+/// Handle daemon command. `config_file` is passed on to the detached daemon.
+pub async fn handle_daemon(action: &DaemonCommands, config_file: Option<&str>, config: &Config) -> Result<()> {
+    let socket = config.get_daemon_socket_path();
+    match action {
+        DaemonCommands::Start { foreground: true } => {
+            let listener = daemon::bind(&socket)?;
+            println!("Daemon listening on {}", socket.display());
+            Arc::new(Daemon::new(config.clone())).run(listener, &socket).await?;
+        },
+        DaemonCommands::Start { foreground: false } => {
+            if daemon::send(&socket, &DaemonRequest::Status).await.is_ok() {
+                return Err(AppError::cli_usage(format!("A daemon is already running on {}", socket.display())));
+            }
+            let pid = spawn_daemon(config_file, config)?;
+
+            // Wait up to five seconds for the daemon to answer
+            for _ in 0..50 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                if daemon::send(&socket, &DaemonRequest::Status).await.is_ok() {
+                    println!("Daemon started (pid {}) on {}", pid, socket.display());
+                    return Ok(());
+                }
+            }
+            return Err(AppError::unknown(format!(
+                "Daemon did not start; see {}",
+                config.get_log_directory_path().join("daemon.log").display()
+            )));
+        },
+        DaemonCommands::Stop => match daemon::send(&socket, &DaemonRequest::Stop).await {
+            Ok(_) => println!("Daemon stopped"),
+            Err(_) => println!("No daemon is running on {}", socket.display()),
+        },
+        DaemonCommands::Status => match daemon::send(&socket, &DaemonRequest::Status).await {
+            Ok(response) => {
+                let status: DaemonStatus = serde_json::from_value(response.into_result()?)?;
+                println!("Daemon running on {}", socket.display());
+                println!("  PID:        {}", status.pid);
+                println!("  Code model: {}", status.code_model);
+                println!("  Chat model: {}", status.chat_model);
+                println!("  Uptime:     {}s", status.uptime_secs);
+                println!("  Requests:   {}", status.requests);
+            },
+            Err(_) => println!("No daemon is running on {}", socket.display()),
+        },
+    }
+    Ok(())
+}
+
+/// Start this executable as a detached foreground daemon, logging to
+/// `daemon.log` in the log directory. Returns the child's process id.
+fn spawn_daemon(config_file: Option<&str>, config: &Config) -> Result<u32> {
+    let log_dir = config.get_log_directory_path();
+    fs::create_dir_all(&log_dir).with_context(|| format!("Failed to create log directory: {}", log_dir.display()))?;
+    let log_path = log_dir.join("daemon.log");
+    let log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Failed to open {}", log_path.display()))?;
+
+    let exe = std::env::current_exe().with_context(|| "Failed to locate the current executable")?;
+    let mut command = std::process::Command::new(exe);
+    if let Some(path) = config_file {
+        command.args(["--config", path]);
+    }
+    command
+        .args(["-d", "daemon", "start", "--foreground"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(log);
+    // Keep Ctrl-C in this terminal from reaching the daemon
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let child = command.spawn().with_context(|| "Failed to start the daemon")?;
+    Ok(child.id())
+}
+
+/// Handle watch command
+pub async fn handle_watch(
+    dir: &str,
+    sidecar: bool,
+    dry_run: bool,
+    ignore: &[String],
+    marker: &[String],
+    max_tokens: Option<u32>,
+    config: &Config,
+) -> Result<()> {
+    let markers = if marker.is_empty() { config.watch_markers.clone() } else { marker.to_vec() };
+    let ignore: Vec<String> = config.watch_ignore.iter().chain(ignore).cloned().collect();
+    let max_tokens = max_tokens.unwrap_or(config.max_tokens_code);
+    let options = PostProcessOptions::from_config(config);
+    let seed = config.random_seed.unwrap_or(0);
+    let client = CodestralClient::new(config.clone())?;
+    let provider = CodestralProvider::new(&config.code_model, client);
+
+    let mut watcher = FileWatcher::new(Path::new(dir), &ignore, Duration::from_millis(config.watch_debounce_ms))?;
+    println!("Watching {} for {} (Ctrl-C to stop)", watcher.root().display(), markers.join(", "));
+
+    loop {
+        let batch = tokio::select! {
+            batch = watcher.next_batch() => batch,
+            _ = tokio::signal::ctrl_c() => None,
+        };
+        let Some(batch) = batch else {
+            break;
+        };
+
+        for path in batch {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            if !watcher.mark_processed(&path, &content) || find_markers(&content, &markers).is_empty() {
+                continue;
+            }
+
+            let display = path.strip_prefix(watcher.root()).unwrap_or(&path).display().to_string();
+            let fill = match complete_markers(&content, &markers, &provider, max_tokens, seed, &options).await {
+                Ok(fill) => fill,
+                Err(e) => {
+                    eprintln!("Failed to complete {}: {}", display, e);
+                    continue;
+                },
+            };
+            if fill.unfilled > 0 {
+                eprintln!("{}: {} markers got empty completions and were left in place", display, fill.unfilled);
+            }
+            if fill.filled == 0 {
+                continue;
+            }
+            // A save while the completions were requested has its own change
+            // event, which processes the file again
+            if !dry_run && fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                eprintln!("{} changed while its markers were completed; skipping", display);
+                continue;
+            }
+
+            if dry_run {
+                print!("{}", unified_patch(&display, &content, &fill.content));
+            } else if sidecar {
+                let patch_path = sidecar_path(&path);
+                if let Err(e) = fs::write(&patch_path, unified_patch(&display, &content, &fill.content)) {
+                    eprintln!("Failed to write {}: {}", patch_path.display(), e);
+                    continue;
+                }
+                println!("Wrote a suggestion for {} markers in {} to {}", fill.filled, display, patch_path.display());
+            } else {
+                if let Err(e) = fs::write(&path, &fill.content) {
+                    eprintln!("Failed to write {}: {}", display, e);
+                    continue;
+                }
+                // Our own write should not trigger another round
+                watcher.mark_processed(&path, &fill.content);
+                println!("Completed {} markers in {}", fill.filled, display);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle eval command
+pub async fn handle_eval(
+    dataset: &str,
+    samples: usize,
+    models: &[String],
+    mock: bool,
+    max_tokens: Option<u32>,
+    output: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    if samples == 0 {
+        return Err(AppError::cli_usage("--samples must be at least 1"));
+    }
+    
+    let tasks = load_tasks(dataset)?;
+    info!("Loaded {} tasks from {}", tasks.len(), dataset);
+    
+    let max_tokens = max_tokens.unwrap_or(config.max_tokens_code);
+    let timeout = Duration::from_secs(config.eval_test_timeout);
+    
+    let providers: Vec<Box<dyn CompletionProvider>> = if mock {
+        vec![Box::new(mock_provider_for(&tasks))]
+    } else {
+        let models = if models.is_empty() { vec![config.code_model.clone()] } else { models.to_vec() };
+        models
+            .iter()
+            .map(|model| {
+                let client = CodestralClient::new(Config { code_model: model.clone(), ..config.clone() })?;
+                Ok(Box::new(CodestralProvider::new(model, client)) as Box<dyn CompletionProvider>)
+            })
+            .collect::<Result<_>>()?
+    };
+    
+    let mut reports = Vec::new();
+    for provider in &providers {
+        reports.push(run_eval(provider.as_ref(), &tasks, samples, max_tokens, timeout).await?);
+    }
+    
+    println!("{:<28} {:>6} {:>8} {:>8} {:>12} {:>10}", "Model", "Tasks", "pass@1", format!("pass@{}", samples), "Latency (ms)", "Tokens");
+    for report in &reports {
+        println!(
+            "{:<28} {:>6} {:>8.3} {:>8.3} {:>12.0} {:>10}",
+            report.model, report.tasks, report.pass_at_1, report.pass_at_k, report.mean_latency_ms, report.total_tokens
+        );
+    }
+    
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string_pretty(&reports)?)
+            .with_context(|| format!("Failed to write report to {}", path))?;
+        info!("Report written to {}", path);
+    }
+    
+    Ok(())
+}
+
 /// Handle configuration commands
-pub fn handle_config(action: &ConfigCommands, _config: &Config) -> Result<()> {
+pub async fn handle_config(action: &ConfigCommands, _config: &Config) -> Result<()> {
     match action {
         ConfigCommands::Generate { path } => {
             Config::generate_default_config(path)?;
@@ -117,8 +1444,73 @@ pub fn handle_config(action: &ConfigCommands, _config: &Config) -> Result<()> {
                     return Err(e);
                 }
             }
+        },
+        ConfigCommands::Validate { path } => {
+            let loaded_config = Config::load_from_file(path)?;
+            let client = ModelsClient::new(loaded_config.clone())?;
+            let models = client.list_models(false).await?;
+            
+            let warnings = loaded_config.validate_models(&models);
+            if warnings.is_empty() {
+                println!("Configuration at {} uses valid models.", path);
+            } else {
+                for warning in &warnings {
+                    println!("Warning: {}", warning);
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Handle models commands
+pub async fn handle_models(action: &ModelsCommands, config: &Config) -> Result<()> {
+    match action {
+        ModelsCommands::List { refresh } => {
+            let client = ModelsClient::new(config.clone())?;
+            let models = client.list_models(*refresh).await?;
+            
+            println!("{:<40} {:<16} {:>10}  DEPRECATION", "ID", "CAPABILITIES", "CONTEXT");
+            for model in &models {
+                let mut capabilities = Vec::new();
+                if model.capabilities.completion_fim {
+                    capabilities.push("fim");
+                }
+                if model.capabilities.completion_chat {
+                    capabilities.push("chat");
+                }
+                if model.capabilities.function_calling {
+                    capabilities.push("tools");
+                }
+                
+                let context = model.max_context_length
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                
+                println!(
+                    "{:<40} {:<16} {:>10}  {}",
+                    model.id,
+                    capabilities.join(","),
+                    context,
+                    model.deprecation.as_deref().unwrap_or("-")
+                );
+            }
         }
     }
     
     Ok(())
 }
+
+/// Warn about unknown or deprecated configured models using the cached model list
+pub fn warn_on_cached_models(config: &Config) {
+    match ModelCache::load(config.get_models_cache_path()) {
+        Ok(Some(cache)) => {
+            for warning in config.validate_models(&cache.models) {
+                warn!("{}", warning);
+            }
+        },
+        Ok(None) => {},
+        Err(e) => warn!("Could not read models cache: {}", e),
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::{AppError, Result, ErrorExt};
+use crate::review::ReviewChunk;
+use crate::tools::patch::parse_unified_diff;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Marker identifying hooks written by `commit-msg --install-hook`
+pub const HOOK_MARKER: &str = "# Installed by codestral-test-rs commit-msg --install-hook";
+
+/// Chunk summaries requested at once for long diffs, to stay under rate limits
+pub const SUMMARY_CONCURRENCY: usize = 4;
+
+/// Style guide used when `commit_style_guide` is not configured
+pub const DEFAULT_STYLE_GUIDE: &str = "Use the Conventional Commits format: `<type>(<optional scope>): <summary>`, \
+where type is one of feat, fix, docs, style, refactor, perf, test, build, ci or chore. \
+Keep the summary under 72 characters, in the imperative mood and without a trailing period. \
+If the change needs explaining, add a blank line and a short body wrapped at 72 characters.";
+
+/// Prompt asking for a short summary of one chunk of a large diff
+pub fn summary_prompt(chunk: &ReviewChunk) -> String {
+    format!(
+        "Summarise what this change to {} does in one or two sentences, for someone writing a commit message.\n\n```{}\n{}```",
+        chunk.path, chunk.language, chunk.diff
+    )
+}
+
+/// Paths of the files a diff deletes, which `split_diff` leaves out
+pub fn deleted_files(diff: &str) -> Vec<String> {
+    parse_unified_diff(diff)
+        .map(|patches| patches.into_iter().filter(|p| p.new_path.is_none()).filter_map(|p| p.old_path).collect())
+        .unwrap_or_default()
+}
+
+/// Prompt asking for the commit message, given either the full diff or
+/// per-file summaries of it
+pub fn message_prompt(style_guide: &str, changes: &str) -> String {
+    format!(
+        "Write a git commit message for the staged changes below.\n\nStyle guide:\n{}\n\n\
+         Reply with the commit message only, without quotes or code fences.\n\n{}",
+        style_guide, changes
+    )
+}
+
+/// Render per-file summaries as the body of the final prompt
+pub fn format_summaries(summaries: &[(String, String)]) -> String {
+    let mut text = String::from("Summary of the changes by file:\n");
+    for (path, summary) in summaries {
+        text.push_str(&format!("- {}: {}\n", path, summary.trim()));
+    }
+    text
+}
+
+/// Strip code fences, quotes and surrounding whitespace from the model's reply
+pub fn clean_message(reply: &str) -> String {
+    let mut message = reply.trim();
+    if let Some(rest) = message.strip_prefix("```") {
+        // Drop the fence's language tag line, if any
+        let rest = rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest);
+        message = rest.trim_end().strip_suffix("```").unwrap_or(rest).trim();
+    }
+    for quote in ['"', '\'', '`'] {
+        if message.len() > 1 && message.starts_with(quote) && message.ends_with(quote) {
+            message = message[1..message.len() - 1].trim();
+        }
+    }
+    message.to_string()
+}
+
+/// Put the message at the top of a commit message file, keeping what git
+/// already wrote there (comments and, for `--verbose`, the diff)
+pub fn write_message_file(path: &Path, message: &str) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let content = if existing.trim().is_empty() {
+        format!("{}\n", message)
+    } else {
+        format!("{}\n\n{}", message, existing.trim_start_matches('\n'))
+    };
+    fs::write(path, content).with_context(|| format!("Failed to write commit message to {}", path.display()))
+}
+
+/// Quote a path for a POSIX shell
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
+
+/// Shell script for the `prepare-commit-msg` hook. The configuration file is
+/// passed explicitly, since git runs hooks from the repository root.
+pub fn hook_script(executable: &Path, config_file: &Path) -> String {
+    format!(
+        "#!/bin/sh\n{}\n\
+         # Only fill in the message for a plain `git commit`, not for -m, merges,\n\
+         # squashes or amends, and never block the commit if generation fails\n\
+         if [ -z \"$2\" ]; then\n    \
+         {} --config {} commit-msg --output \"$1\" || true\nfi\n",
+        HOOK_MARKER, shell_quote(executable), shell_quote(config_file)
+    )
+}
+
+/// Install the `prepare-commit-msg` hook in a repository, returning its path.
+/// An existing hook is only replaced if it was installed by this tool or
+/// `force` is set.
+pub fn install_hook(repo: &Path, executable: &Path, config_file: &Path, force: bool) -> Result<PathBuf> {
+    let config_file = fs::canonicalize(config_file)
+        .with_context(|| format!("Failed to resolve {}", config_file.display()))?;
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(repo)
+        .output()
+        .with_context(|| format!("Failed to run git in {}", repo.display()))?;
+    if !output.status.success() {
+        return Err(AppError::cli_usage(format!(
+            "{} is not a git repository: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let hooks_dir = repo.join(String::from_utf8_lossy(&output.stdout).trim());
+    let hook = hooks_dir.join("prepare-commit-msg");
+    if let Ok(existing) = fs::read_to_string(&hook) {
+        if !force && !existing.contains(HOOK_MARKER) {
+            return Err(AppError::cli_usage(format!(
+                "{} already exists; use --force to replace it",
+                hook.display()
+            )));
+        }
+    }
+
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Failed to create directory {}", hooks_dir.display()))?;
+    fs::write(&hook, hook_script(executable, &config_file))
+        .with_context(|| format!("Failed to write hook {}", hook.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", hook.display()))?;
+    }
+
+    Ok(hook)
+}
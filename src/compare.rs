@@ -0,0 +1,182 @@
+use crate::api::extract_response_fields;
+use crate::error::Result;
+use crate::models::ApiResponse;
+use futures::future::join_all;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::time::Instant;
+
+/// Output of one model in a comparison
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonResult {
+    pub model: String,
+    pub latency_ms: u64,
+    /// Parsed response, or `None` if the request failed
+    #[serde(skip)]
+    pub response: Option<ApiResponse>,
+    pub content: Option<String>,
+    pub finish_reason: Option<String>,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub error: Option<String>,
+}
+
+/// Send the same request to every model concurrently. Failures are recorded
+/// per model instead of aborting the comparison.
+pub async fn compare_models<F, Fut>(models: &[String], request: F) -> Vec<ComparisonResult>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Value>>,
+{
+    let runs = models.iter().map(|model| {
+        let pending = request(model.clone());
+        async move {
+            let started = Instant::now();
+            let outcome = pending.await;
+            let latency_ms = started.elapsed().as_millis() as u64;
+
+            match outcome {
+                Ok(json) => {
+                    let response = extract_response_fields(&json);
+                    ComparisonResult {
+                        model: model.clone(),
+                        latency_ms,
+                        content: response.content.clone(),
+                        finish_reason: Some(response.finish_reason.clone()),
+                        completion_tokens: response.completion_tokens,
+                        total_tokens: response.total_tokens,
+                        response: Some(response),
+                        error: None,
+                    }
+                },
+                Err(e) => ComparisonResult {
+                    model: model.clone(),
+                    latency_ms,
+                    response: None,
+                    content: None,
+                    finish_reason: None,
+                    completion_tokens: 0,
+                    total_tokens: 0,
+                    error: Some(e.to_string()),
+                },
+            }
+        }
+    });
+    join_all(runs).await
+}
+
+fn output_text(result: &ComparisonResult) -> String {
+    match (&result.content, &result.error) {
+        (_, Some(error)) => format!("Error: {}", error),
+        (Some(content), None) => content.clone(),
+        (None, None) => "(no content)".to_string(),
+    }
+}
+
+fn stats_line(result: &ComparisonResult) -> String {
+    format!(
+        "{} ms, {} tokens, {}",
+        result.latency_ms,
+        result.total_tokens,
+        result.finish_reason.as_deref().unwrap_or("-")
+    )
+}
+
+/// Wrap text to lines of at most `width` characters, keeping existing line breaks
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        for chunk in chars.chunks(width.max(1)) {
+            lines.push(chunk.iter().collect());
+        }
+    }
+    lines
+}
+
+/// Render results as columns side by side, fitting `total_width` characters
+pub fn render_side_by_side(results: &[ComparisonResult], total_width: usize) -> String {
+    if results.is_empty() {
+        return String::new();
+    }
+    let separator = " | ";
+    let width = (total_width.saturating_sub(separator.len() * (results.len() - 1)) / results.len()).max(10);
+
+    let columns: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| {
+            let mut column = wrap(&r.model, width);
+            column.extend(wrap(&stats_line(r), width));
+            column.push("-".repeat(width));
+            column.extend(wrap(&output_text(r), width));
+            column
+        })
+        .collect();
+
+    let rows = columns.iter().map(|c| c.len()).max().unwrap_or(0);
+    let mut output = String::new();
+    for row in 0..rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| format!("{:<width$}", c.get(row).map(String::as_str).unwrap_or(""), width = width))
+            .collect();
+        output.push_str(cells.join(separator).trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+/// Render a Markdown report with a summary table and each model's output
+pub fn render_markdown(prompt: &str, comparison_id: &str, results: &[ComparisonResult]) -> String {
+    let mut report = format!("# Model comparison\n\nComparison id: `{}`\n\n## Prompt\n\n```\n{}\n```\n\n", comparison_id, prompt);
+    report.push_str("| Model | Latency (ms) | Completion tokens | Total tokens | Finish reason |\n");
+    report.push_str("|---|---:|---:|---:|---|\n");
+    for r in results {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            r.model,
+            r.latency_ms,
+            r.completion_tokens,
+            r.total_tokens,
+            r.finish_reason.as_deref().or(r.error.as_ref().map(|_| "error")).unwrap_or("-")
+        ));
+    }
+    for r in results {
+        report.push_str(&format!("\n## {}\n\n```\n{}\n```\n", r.model, output_text(r)));
+    }
+    report
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Render a standalone HTML report with the outputs in side-by-side columns
+pub fn render_html(prompt: &str, comparison_id: &str, results: &[ComparisonResult]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Model comparison</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         .columns { display: flex; gap: 1em; }\n\
+         .column { flex: 1; min-width: 0; border: 1px solid #ccc; padding: 0.5em; }\n\
+         pre { white-space: pre-wrap; }\n\
+         .error { color: #b00; }\n\
+         </style>\n</head>\n<body>\n<h1>Model comparison</h1>\n",
+    );
+    html.push_str(&format!("<p>Comparison id: <code>{}</code></p>\n", escape_html(comparison_id)));
+    html.push_str(&format!("<h2>Prompt</h2>\n<pre>{}</pre>\n<div class=\"columns\">\n", escape_html(prompt)));
+    for r in results {
+        html.push_str(&format!(
+            "<div class=\"column\">\n<h3>{}</h3>\n<p>{}</p>\n<pre{}>{}</pre>\n</div>\n",
+            escape_html(&r.model),
+            escape_html(&stats_line(r)),
+            if r.error.is_some() { " class=\"error\"" } else { "" },
+            escape_html(&output_text(r))
+        ));
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
@@ -1,10 +1,14 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use log::{info, error};
 use shellexpand;
 use crate::error::{AppError, Result, ErrorExt};
+use crate::fim::BlockTruncation;
+use crate::templates::TemplateDef;
+use crate::models::{ModelInfo, SamplingParams};
 
 fn default_code_model() -> String {
     "codestral-latest".to_string()
@@ -38,6 +42,50 @@ fn default_chat_api_url() -> String {
     "https://api.mistral.ai/v1/chat/completions".to_string()
 }
 
+fn default_models_api_url() -> String {
+    "https://api.mistral.ai/v1/models".to_string()
+}
+
+fn default_embed_model() -> String {
+    "mistral-embed".to_string()
+}
+
+fn default_embed_api_url() -> String {
+    "https://api.mistral.ai/v1/embeddings".to_string()
+}
+
+fn default_embed_batch_size() -> usize {
+    32
+}
+
+fn default_index_file_name() -> String {
+    ".codestral-index.json".to_string()
+}
+
+fn default_index_chunk_lines() -> usize {
+    60
+}
+
+fn default_context_token_budget() -> usize {
+    2000
+}
+
+fn default_tool_max_iterations() -> usize {
+    8
+}
+
+fn default_command_allowlist() -> Vec<String> {
+    vec!["cargo check".to_string(), "cargo test".to_string()]
+}
+
+fn default_tool_command_timeout() -> u64 {
+    300
+}
+
+fn default_structured_max_retries() -> usize {
+    2
+}
+
 fn default_max_tokens_code() -> u32 {
     512
 }
@@ -50,6 +98,123 @@ fn default_log_directory() -> String {
     "logs".to_string()
 }
 
+fn default_models_cache_path() -> String {
+    "cache/models.json".to_string()
+}
+
+fn default_models_cache_ttl() -> u64 {
+    86400
+}
+
+fn default_verify_max_retries() -> usize {
+    2
+}
+
+fn default_verify_check_timeout() -> u64 {
+    300
+}
+
+fn default_eval_test_timeout() -> u64 {
+    60
+}
+
+fn default_review_chunk_tokens() -> usize {
+    6000
+}
+
+fn default_commit_diff_tokens() -> usize {
+    4000
+}
+
+fn default_gen_tests_timeout() -> u64 {
+    600
+}
+
+fn default_gen_docs_max_tokens() -> u32 {
+    128
+}
+
+fn default_explain_context_lines() -> usize {
+    20
+}
+
+fn default_lsp_debounce_ms() -> u64 {
+    150
+}
+
+fn default_lsp_prefix_chars() -> usize {
+    6000
+}
+
+fn default_lsp_suffix_chars() -> usize {
+    2000
+}
+
+fn default_lsp_max_tokens() -> u32 {
+    128
+}
+
+fn default_lsp_cache_size() -> usize {
+    32
+}
+
+fn default_serve_bind() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_serve_cache_size() -> usize {
+    256
+}
+
+fn default_serve_max_retries() -> usize {
+    2
+}
+
+fn default_serve_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_serve_token_env() -> String {
+    "CODESTRAL_PROXY_TOKEN".to_string()
+}
+
+fn default_daemon_socket() -> String {
+    "~/.codestral-test-rs/daemon.sock".to_string()
+}
+
+fn default_daemon_timeout() -> u64 {
+    120
+}
+
+fn default_watch_markers() -> Vec<String> {
+    vec!["@complete".to_string(), "TODO(ai):".to_string()]
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+fn default_templates_dir() -> String {
+    "templates".to_string()
+}
+
+/// A named combination of system prompt, model and temperature for chat requests
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Persona {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_config_path() -> String {
     "config.toml".to_string()
 }
@@ -80,17 +245,187 @@ pub struct Config {
     #[serde(default = "default_chat_api_url")]
     pub chat_api_url: String,
     
+    #[serde(default = "default_models_api_url")]
+    pub models_api_url: String,
+    
+    #[serde(default = "default_embed_model")]
+    pub embed_model: String,
+    
+    #[serde(default = "default_embed_api_url")]
+    pub embed_api_url: String,
+    
+    #[serde(default = "default_embed_batch_size")]
+    pub embed_batch_size: usize,
+    
+    #[serde(default = "default_index_file_name")]
+    pub index_file_name: String,
+    
+    #[serde(default = "default_index_chunk_lines")]
+    pub index_chunk_lines: usize,
+    
+    #[serde(default = "default_context_token_budget")]
+    pub context_token_budget: usize,
+    
+    #[serde(default = "default_tool_max_iterations")]
+    pub tool_max_iterations: usize,
+    
+    #[serde(default = "default_command_allowlist")]
+    pub command_allowlist: Vec<String>,
+    
+    #[serde(default = "default_tool_command_timeout")]
+    pub tool_command_timeout: u64,
+    
+    #[serde(default = "default_structured_max_retries")]
+    pub structured_max_retries: usize,
+    
+    #[serde(default = "default_true")]
+    pub fim_trim_suffix_overlap: bool,
+    
+    #[serde(default)]
+    pub fim_block_truncation: BlockTruncation,
+    
+    #[serde(default = "default_true")]
+    pub fim_strip_trailing_whitespace: bool,
+    
+    #[serde(default = "default_true")]
+    pub fim_normalize_line_endings: bool,
+    
+    #[serde(default = "default_verify_max_retries")]
+    pub verify_max_retries: usize,
+    
+    #[serde(default = "default_verify_check_timeout")]
+    pub verify_check_timeout: u64,
+    
+    #[serde(default = "default_eval_test_timeout")]
+    pub eval_test_timeout: u64,
+    
+    #[serde(default = "default_review_chunk_tokens")]
+    pub review_chunk_tokens: usize,
+    
+    #[serde(default = "default_commit_diff_tokens")]
+    pub commit_diff_tokens: usize,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_style_guide: Option<String>,
+    
+    #[serde(default = "default_gen_tests_timeout")]
+    pub gen_tests_timeout: u64,
+    
+    #[serde(default = "default_gen_docs_max_tokens")]
+    pub gen_docs_max_tokens: u32,
+    
+    #[serde(default = "default_explain_context_lines")]
+    pub explain_context_lines: usize,
+    
+    #[serde(default = "default_lsp_debounce_ms")]
+    pub lsp_debounce_ms: u64,
+    
+    #[serde(default = "default_lsp_prefix_chars")]
+    pub lsp_prefix_chars: usize,
+    
+    #[serde(default = "default_lsp_suffix_chars")]
+    pub lsp_suffix_chars: usize,
+    
+    #[serde(default = "default_lsp_max_tokens")]
+    pub lsp_max_tokens: u32,
+    
+    #[serde(default = "default_lsp_cache_size")]
+    pub lsp_cache_size: usize,
+    
+    #[serde(default = "default_serve_bind")]
+    pub serve_bind: String,
+    
+    #[serde(default = "default_serve_cache_size")]
+    pub serve_cache_size: usize,
+    
+    #[serde(default = "default_serve_max_retries")]
+    pub serve_max_retries: usize,
+    
+    #[serde(default = "default_serve_retry_backoff_ms")]
+    pub serve_retry_backoff_ms: u64,
+    
+    #[serde(default = "default_serve_token_env")]
+    pub serve_token_env: String,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serve_token_budget: Option<u64>,
+    
+    #[serde(default)]
+    pub serve_redact_patterns: Vec<String>,
+    
+    #[serde(default = "default_daemon_socket")]
+    pub daemon_socket: String,
+    
+    #[serde(default = "default_true")]
+    pub use_daemon: bool,
+    
+    #[serde(default = "default_daemon_timeout")]
+    pub daemon_timeout: u64,
+    
+    #[serde(default = "default_watch_markers")]
+    pub watch_markers: Vec<String>,
+    
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
+    
     #[serde(default = "default_max_tokens_code")]
     pub max_tokens_code: u32,
     
     #[serde(default = "default_max_tokens_chat")]
     pub max_tokens_chat: u32,
     
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_tokens: Option<u32>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    
     #[serde(default = "default_log_directory")]
     pub log_directory: String,
     
+    #[serde(default = "default_models_cache_path")]
+    pub models_cache_path: String,
+    
+    #[serde(default = "default_models_cache_ttl")]
+    pub models_cache_ttl: u64,
+    
+    #[serde(default = "default_templates_dir")]
+    pub templates_dir: String,
+    
     #[serde(default = "default_config_path")]
     pub config_path: String,
+    
+    /// Inline prompt templates, keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, TemplateDef>,
+    
+    /// Chat personas, keyed by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub personas: BTreeMap<String, Persona>,
 }
 
 impl Default for Config {
@@ -104,10 +439,66 @@ impl Default for Config {
             chat_api_key_env: default_chat_api_key_env(),
             code_api_url: default_code_api_url(),
             chat_api_url: default_chat_api_url(),
+            models_api_url: default_models_api_url(),
+            embed_model: default_embed_model(),
+            embed_api_url: default_embed_api_url(),
+            embed_batch_size: default_embed_batch_size(),
+            index_file_name: default_index_file_name(),
+            index_chunk_lines: default_index_chunk_lines(),
+            context_token_budget: default_context_token_budget(),
+            tool_max_iterations: default_tool_max_iterations(),
+            command_allowlist: default_command_allowlist(),
+            tool_command_timeout: default_tool_command_timeout(),
+            structured_max_retries: default_structured_max_retries(),
+            fim_trim_suffix_overlap: default_true(),
+            fim_block_truncation: BlockTruncation::default(),
+            fim_strip_trailing_whitespace: default_true(),
+            fim_normalize_line_endings: default_true(),
+            verify_max_retries: default_verify_max_retries(),
+            verify_check_timeout: default_verify_check_timeout(),
+            eval_test_timeout: default_eval_test_timeout(),
+            review_chunk_tokens: default_review_chunk_tokens(),
+            commit_diff_tokens: default_commit_diff_tokens(),
+            commit_style_guide: None,
+            gen_tests_timeout: default_gen_tests_timeout(),
+            gen_docs_max_tokens: default_gen_docs_max_tokens(),
+            explain_context_lines: default_explain_context_lines(),
+            lsp_debounce_ms: default_lsp_debounce_ms(),
+            lsp_prefix_chars: default_lsp_prefix_chars(),
+            lsp_suffix_chars: default_lsp_suffix_chars(),
+            lsp_max_tokens: default_lsp_max_tokens(),
+            lsp_cache_size: default_lsp_cache_size(),
+            serve_bind: default_serve_bind(),
+            serve_cache_size: default_serve_cache_size(),
+            serve_max_retries: default_serve_max_retries(),
+            serve_retry_backoff_ms: default_serve_retry_backoff_ms(),
+            serve_token_env: default_serve_token_env(),
+            serve_token_budget: None,
+            serve_redact_patterns: Vec::new(),
+            daemon_socket: default_daemon_socket(),
+            use_daemon: default_true(),
+            daemon_timeout: default_daemon_timeout(),
+            watch_markers: default_watch_markers(),
+            watch_debounce_ms: default_watch_debounce_ms(),
+            watch_ignore: Vec::new(),
             max_tokens_code: default_max_tokens_code(),
             max_tokens_chat: default_max_tokens_chat(),
+            top_p: None,
+            stop: None,
+            random_seed: None,
+            min_tokens: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            n: None,
+            safe_prompt: None,
+            system_prompt: None,
             log_directory: default_log_directory(),
+            models_cache_path: default_models_cache_path(),
+            models_cache_ttl: default_models_cache_ttl(),
+            templates_dir: default_templates_dir(),
             config_path: default_config_path(),
+            templates: BTreeMap::new(),
+            personas: BTreeMap::new(),
         }
     }
 }
@@ -182,4 +573,92 @@ impl Config {
         let expanded_path = shellexpand::tilde(&self.log_directory);
         PathBuf::from(expanded_path.to_string())
     }
+    
+    pub fn get_models_cache_path(&self) -> PathBuf {
+        let expanded_path = shellexpand::tilde(&self.models_cache_path);
+        PathBuf::from(expanded_path.to_string())
+    }
+    
+    pub fn get_daemon_socket_path(&self) -> PathBuf {
+        let expanded_path = shellexpand::tilde(&self.daemon_socket);
+        PathBuf::from(expanded_path.to_string())
+    }
+    
+    pub fn get_templates_dir_path(&self) -> PathBuf {
+        let expanded_path = shellexpand::tilde(&self.templates_dir);
+        PathBuf::from(expanded_path.to_string())
+    }
+    
+    /// Default sampling parameters for completion and chat requests
+    pub fn sampling_params(&self) -> SamplingParams {
+        SamplingParams {
+            top_p: self.top_p,
+            stop: self.stop.clone(),
+            random_seed: self.random_seed,
+            min_tokens: self.min_tokens,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            n: self.n,
+            safe_prompt: self.safe_prompt,
+        }
+    }
+    
+    /// Apply a named persona, overriding the chat model, temperature and
+    /// system prompt with the values it sets
+    pub fn with_persona(&self, name: &str) -> Result<Config> {
+        let persona = self.personas.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.personas.keys().map(String::as_str).collect();
+            AppError::config(format!(
+                "Unknown persona '{}' (configured personas: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ))
+        })?;
+        
+        let mut config = self.clone();
+        if let Some(model) = &persona.model {
+            config.chat_model = model.clone();
+        }
+        if let Some(temperature) = persona.temperature {
+            config.chat_temperature = temperature;
+        }
+        if let Some(system_prompt) = &persona.system_prompt {
+            config.system_prompt = Some(system_prompt.clone());
+        }
+        Ok(config)
+    }
+    
+    /// Check the configured model names against a list of known models,
+    /// returning a warning for each unknown, deprecated or mismatched model
+    pub fn validate_models(&self, models: &[ModelInfo]) -> Vec<String> {
+        let mut warnings = Vec::new();
+        
+        check_model(&mut warnings, models, "code_model", &self.code_model, "FIM", |m| m.capabilities.completion_fim);
+        check_model(&mut warnings, models, "chat_model", &self.chat_model, "chat", |m| m.capabilities.completion_chat);
+        for (name, persona) in &self.personas {
+            if let Some(model) = &persona.model {
+                let field = format!("personas.{}.model", name);
+                check_model(&mut warnings, models, &field, model, "chat", |m| m.capabilities.completion_chat);
+            }
+        }
+        
+        warnings
+    }
+}
+
+fn check_model<F>(warnings: &mut Vec<String>, models: &[ModelInfo], field: &str, name: &str, capability: &str, supports: F)
+where
+    F: Fn(&ModelInfo) -> bool,
+{
+    match models.iter().find(|m| m.id == name) {
+        Some(model) => {
+            if let Some(date) = &model.deprecation {
+                warnings.push(format!("{} '{}' is deprecated (retirement date: {})", field, name, date));
+            }
+            if !supports(model) {
+                warnings.push(format!("{} '{}' does not support {} completions", field, name, capability));
+            }
+        },
+        None => warnings.push(format!("{} '{}' is not a known model name", field, name)),
+    }
 }
@@ -0,0 +1,264 @@
+use crate::index::{CodeIndex, Embedder, chunk_source, collect_source_files, index_path};
+use crate::error::{AppError, Result};
+use log::{debug, info};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Number of index hits considered before applying the token budget
+const INDEX_CANDIDATES: usize = 50;
+
+/// A piece of source code selected as context for a request
+#[derive(Debug, Clone)]
+pub struct ContextSnippet {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Where context snippets should be gathered from
+pub struct ContextSource<'a> {
+    /// Directory to search, using its semantic index when one exists
+    pub dir: Option<&'a Path>,
+    /// Glob patterns of files to rank lexically
+    pub file_globs: &'a [String],
+    /// Maximum estimated tokens of snippet text to include
+    pub token_budget: usize,
+    /// Lines per chunk when splitting files that are not indexed
+    pub chunk_lines: usize,
+    /// File name of the semantic index inside `dir`
+    pub index_file_name: &'a str,
+}
+
+/// Rough token count estimate (about four characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Split text into lowercase identifier-like terms, also splitting snake_case
+/// and camelCase identifiers into their parts
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        if word.len() < 2 {
+            continue;
+        }
+        terms.push(word.to_lowercase());
+
+        let mut part = String::new();
+        let mut prev_lower = false;
+        for c in word.chars() {
+            if c == '_' || (c.is_uppercase() && prev_lower) {
+                if part.len() >= 2 {
+                    terms.push(part.to_lowercase());
+                }
+                part.clear();
+            }
+            if c != '_' {
+                part.push(c);
+            }
+            prev_lower = c.is_lowercase();
+        }
+        if part.len() >= 2 && part.len() < word.len() {
+            terms.push(part.to_lowercase());
+        }
+    }
+    terms
+}
+
+/// Rank snippets against the query with a TF-IDF score, dropping snippets
+/// that share no terms with it
+pub fn lexical_rank(query: &str, snippets: Vec<ContextSnippet>) -> Vec<ContextSnippet> {
+    let query_terms: HashSet<String> = tokenize(query).into_iter().collect();
+    if query_terms.is_empty() || snippets.is_empty() {
+        return Vec::new();
+    }
+
+    let term_counts: Vec<HashMap<String, usize>> = snippets
+        .iter()
+        .map(|snippet| {
+            let mut counts = HashMap::new();
+            for term in tokenize(&snippet.text) {
+                if query_terms.contains(&term) {
+                    *counts.entry(term).or_insert(0) += 1;
+                }
+            }
+            counts
+        })
+        .collect();
+
+    let total = snippets.len() as f32;
+    let idf: HashMap<&String, f32> = query_terms
+        .iter()
+        .map(|term| {
+            let containing = term_counts.iter().filter(|c| c.contains_key(term)).count() as f32;
+            (term, (1.0 + total / (1.0 + containing)).ln())
+        })
+        .collect();
+
+    let mut ranked: Vec<ContextSnippet> = snippets
+        .into_iter()
+        .zip(term_counts)
+        .filter_map(|(mut snippet, counts)| {
+            let score: f32 = counts
+                .iter()
+                .map(|(term, count)| (1.0 + *count as f32).ln() * idf.get(term).copied().unwrap_or(0.0))
+                .sum();
+            if score > 0.0 {
+                snippet.score = score;
+                Some(snippet)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
+
+/// Scale scores so the best snippet scores 1.0, making cosine similarities
+/// and TF-IDF scores from different sources comparable
+pub fn normalize_scores(snippets: &mut [ContextSnippet]) {
+    let max = snippets.iter().map(|s| s.score).fold(0.0, f32::max);
+    if max > 0.0 {
+        for snippet in snippets.iter_mut() {
+            snippet.score /= max;
+        }
+    }
+}
+
+/// Greedily take ranked snippets until the token budget is used up
+pub fn select_within_budget(ranked: Vec<ContextSnippet>, token_budget: usize) -> Vec<ContextSnippet> {
+    let mut used = 0;
+    let mut selected = Vec::new();
+    for snippet in ranked {
+        let tokens = estimate_tokens(&snippet.text);
+        if used + tokens > token_budget {
+            continue;
+        }
+        used += tokens;
+        selected.push(snippet);
+    }
+    selected
+}
+
+/// Gather the most relevant snippets for a query from the given sources.
+/// Scores are normalized per source before the sources are merged.
+pub async fn gather_context(query: &str, source: &ContextSource<'_>, embedder: Option<&dyn Embedder>) -> Result<Vec<ContextSnippet>> {
+    let mut ranked = Vec::new();
+
+    if let Some(dir) = source.dir {
+        let path = index_path(dir, source.index_file_name);
+        let mut from_dir = Vec::new();
+        match (path.exists(), embedder) {
+            (true, Some(embedder)) => {
+                info!("Ranking context from index {}", path.display());
+                let index = CodeIndex::load(&path)?;
+                from_dir.extend(index.search(embedder, query, INDEX_CANDIDATES).await?.into_iter().map(|hit| ContextSnippet {
+                    path: hit.path,
+                    start_line: hit.start_line,
+                    end_line: hit.end_line,
+                    text: hit.text,
+                    score: hit.score,
+                }));
+            },
+            _ => {
+                info!("Ranking context lexically from {}", dir.display());
+                let mut snippets = Vec::new();
+                for (relative, content) in collect_source_files(dir, source.index_file_name)? {
+                    snippets.extend(chunk_snippets(&relative, &content, source.chunk_lines));
+                }
+                from_dir.extend(lexical_rank(query, snippets));
+            },
+        }
+        normalize_scores(&mut from_dir);
+        ranked.extend(from_dir);
+    }
+
+    if !source.file_globs.is_empty() {
+        let mut snippets = Vec::new();
+        for pattern in source.file_globs {
+            let paths = glob::glob(pattern)
+                .map_err(|e| AppError::cli_usage(format!("Invalid glob pattern '{}': {}", pattern, e)))?;
+            for path in paths.flatten().filter(|p| p.is_file()) {
+                match fs::read_to_string(&path) {
+                    Ok(content) => snippets.extend(chunk_snippets(&path.to_string_lossy(), &content, source.chunk_lines)),
+                    Err(e) => debug!("Skipping unreadable context file {}: {}", path.display(), e),
+                }
+            }
+        }
+        let mut from_globs = lexical_rank(query, snippets);
+        normalize_scores(&mut from_globs);
+        ranked.extend(from_globs);
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    Ok(select_within_budget(ranked, source.token_budget))
+}
+
+fn chunk_snippets(path: &str, content: &str, chunk_lines: usize) -> Vec<ContextSnippet> {
+    chunk_source(content, chunk_lines)
+        .into_iter()
+        .map(|chunk| ContextSnippet {
+            path: path.to_string(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            text: chunk.text,
+            score: 0.0,
+        })
+        .collect()
+}
+
+/// Render snippets as Markdown for inclusion in a chat system message
+pub fn format_for_system_message(snippets: &[ContextSnippet]) -> String {
+    let mut message = String::from("Use the following code from the user's repository as context when answering.\n");
+    for snippet in snippets {
+        message.push_str(&format!(
+            "\n### {}:{}-{}\n```\n{}\n```\n",
+            snippet.path, snippet.start_line, snippet.end_line, snippet.text
+        ));
+    }
+    message
+}
+
+/// Render snippets as line comments to prepend to a FIM prefix
+pub fn format_for_fim_prefix(snippets: &[ContextSnippet], comment: &str) -> String {
+    let mut prefix = String::new();
+    for snippet in snippets {
+        prefix.push_str(&format!("{} {}:{}-{}\n", comment, snippet.path, snippet.start_line, snippet.end_line));
+        for line in snippet.text.lines() {
+            prefix.push_str(&format!("{} {}\n", comment, line));
+        }
+        prefix.push('\n');
+    }
+    prefix
+}
+
+/// Line comment marker for a file, based on its extension
+pub fn comment_prefix_for(path: &str) -> &'static str {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension {
+        "py" | "rb" | "sh" | "bash" | "toml" | "yaml" | "yml" | "r" | "pl" => "#",
+        "sql" | "lua" | "hs" => "--",
+        _ => "//",
+    }
+}
+
+/// Summary of included snippets for the generation log
+pub fn snippets_metadata(snippets: &[ContextSnippet]) -> Value {
+    Value::Array(
+        snippets
+            .iter()
+            .map(|s| json!({
+                "path": s.path,
+                "start_line": s.start_line,
+                "end_line": s.end_line,
+                "score": s.score,
+            }))
+            .collect(),
+    )
+}
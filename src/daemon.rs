@@ -0,0 +1,241 @@
+use crate::api::make_api_request;
+use crate::config::Config;
+use crate::error::{AppError, Result, ErrorExt};
+use log::{debug, info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::Notify;
+
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+/// A request to the daemon, sent as one line of JSON
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    /// POST a JSON body to an API endpoint over the daemon's connection pool
+    Post { url: String, api_key: String, body: Value },
+    Status,
+    Stop,
+}
+
+/// The daemon's answer to a request, sent as one line of JSON
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DaemonResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DaemonResponse {
+    pub fn success(result: Value) -> Self {
+        Self { ok: true, result: Some(result), error: None }
+    }
+
+    pub fn failure(error: impl Into<String>) -> Self {
+        Self { ok: false, result: None, error: Some(error.into()) }
+    }
+
+    pub fn into_result(self) -> Result<Value> {
+        if self.ok {
+            Ok(self.result.unwrap_or(Value::Null))
+        } else {
+            Err(AppError::api(self.error.unwrap_or_else(|| "Daemon request failed".to_string())))
+        }
+    }
+}
+
+/// What `daemon status` reports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub code_model: String,
+    pub chat_model: String,
+    pub uptime_secs: u64,
+    pub requests: u64,
+}
+
+/// Long-running process holding the configuration and a warm HTTP client
+pub struct Daemon {
+    config: Config,
+    client: Client,
+    started: Instant,
+    requests: AtomicU64,
+    shutdown: Notify,
+}
+
+impl Daemon {
+    pub fn new(config: Config) -> Self {
+        Self { config, client: Client::new(), started: Instant::now(), requests: AtomicU64::new(0), shutdown: Notify::new() }
+    }
+
+    pub async fn handle(&self, request: &DaemonRequest) -> DaemonResponse {
+        match request {
+            DaemonRequest::Post { url, api_key, body } => {
+                self.requests.fetch_add(1, Ordering::SeqCst);
+                debug!("Forwarding request to {}", url);
+                match make_api_request(&self.client, url, api_key, body.clone()).await {
+                    Ok(response) => DaemonResponse::success(response),
+                    Err(e) => DaemonResponse::failure(e.to_string()),
+                }
+            },
+            DaemonRequest::Status => DaemonResponse::success(json!(DaemonStatus {
+                pid: std::process::id(),
+                code_model: self.config.code_model.clone(),
+                chat_model: self.config.chat_model.clone(),
+                uptime_secs: self.started.elapsed().as_secs(),
+                requests: self.requests.load(Ordering::SeqCst),
+            })),
+            DaemonRequest::Stop => DaemonResponse::success(Value::Null),
+        }
+    }
+
+    /// Answer the requests of one connection, a line at a time
+    pub async fn serve_connection<S: AsyncRead + AsyncWrite>(&self, stream: S) -> Result<()> {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        while let Some(line) = lines.next_line().await.with_context(|| "Failed to read daemon request")? {
+            let request = serde_json::from_str::<DaemonRequest>(&line);
+            let response = match &request {
+                Ok(request) => self.handle(request).await,
+                Err(e) => DaemonResponse::failure(format!("Invalid request: {}", e)),
+            };
+            let mut reply = serde_json::to_string(&response)?;
+            reply.push('\n');
+            write.write_all(reply.as_bytes()).await.with_context(|| "Failed to write daemon response")?;
+
+            // Stop only once the client has its answer
+            if matches!(request, Ok(DaemonRequest::Stop)) {
+                self.shutdown.notify_one();
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Accept connections until a stop request or Ctrl-C, then remove the socket
+    #[cfg(unix)]
+    pub async fn run(self: Arc<Self>, listener: UnixListener, socket: &Path) -> Result<()> {
+        info!("Daemon listening on {}", socket.display());
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted.with_context(|| "Failed to accept daemon connection")?;
+                    let daemon = self.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon.serve_connection(stream).await {
+                            warn!("Daemon connection failed: {}", e);
+                        }
+                    });
+                },
+                _ = self.shutdown.notified() => break,
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+        let _ = fs::remove_file(socket);
+        info!("Daemon stopped");
+        Ok(())
+    }
+}
+
+/// Bind the daemon socket, replacing a stale one left by a daemon that
+/// did not shut down cleanly. Fails if a daemon is already listening.
+#[cfg(unix)]
+pub fn bind(socket: &Path) -> Result<UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if socket.exists() {
+        if std::os::unix::net::UnixStream::connect(socket).is_ok() {
+            return Err(AppError::cli_usage(format!("A daemon is already running on {}", socket.display())));
+        }
+        fs::remove_file(socket).with_context(|| format!("Failed to remove stale socket {}", socket.display()))?;
+    }
+    if let Some(parent) = socket.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+
+    let listener = UnixListener::bind(socket).with_context(|| format!("Failed to bind {}", socket.display()))?;
+    // Requests carry API keys, so only the owner may connect
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to set permissions on {}", socket.display()))?;
+    Ok(listener)
+}
+
+/// Send one request to the daemon listening on `socket`
+#[cfg(unix)]
+pub async fn send(socket: &Path, request: &DaemonRequest) -> Result<DaemonResponse> {
+    let stream = UnixStream::connect(socket)
+        .await
+        .with_context(|| format!("No daemon is listening on {}", socket.display()))?;
+    exchange(stream, request).await
+}
+
+#[cfg(not(unix))]
+pub async fn send(_socket: &Path, _request: &DaemonRequest) -> Result<DaemonResponse> {
+    Err(AppError::cli_usage("The daemon needs Unix domain sockets, which this platform lacks"))
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite>(stream: S, request: &DaemonRequest) -> Result<DaemonResponse> {
+    let (read, mut write) = tokio::io::split(stream);
+    write_request(&mut write, request).await?;
+    read_response(read).await
+}
+
+async fn write_request<W: AsyncWrite + Unpin>(write: &mut W, request: &DaemonRequest) -> Result<()> {
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    write.write_all(line.as_bytes()).await.with_context(|| "Failed to send request to the daemon")
+}
+
+async fn read_response<R: AsyncRead + Unpin>(read: R) -> Result<DaemonResponse> {
+    let reply = BufReader::new(read)
+        .lines()
+        .next_line()
+        .await
+        .with_context(|| "Failed to read the daemon's response")?
+        .ok_or_else(|| AppError::api("The daemon closed the connection without answering"))?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+/// Post through the daemon when one is listening on `socket`. Returns `None`
+/// when the request could not be handed to a daemon, so the caller sends it
+/// itself. Once the daemon has the request, its failures, including no answer
+/// within `timeout`, are returned, since it may already have sent the request.
+pub async fn try_post(socket: Option<&Path>, timeout: Duration, url: &str, api_key: &str, body: &Value) -> Option<Result<Value>> {
+    let socket = socket.filter(|s| s.exists())?;
+    #[cfg(unix)]
+    {
+        let stream = match UnixStream::connect(socket).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Daemon not reachable on {}: {}", socket.display(), e);
+                return None;
+            },
+        };
+        debug!("Sending request to {} through the daemon", url);
+        let (read, mut write) = tokio::io::split(stream);
+        let request = DaemonRequest::Post { url: url.to_string(), api_key: api_key.to_string(), body: body.clone() };
+        if let Err(e) = write_request(&mut write, &request).await {
+            warn!("Failed to send the request to the daemon on {}, sending it directly: {}", socket.display(), e);
+            return None;
+        }
+        Some(match tokio::time::timeout(timeout, read_response(read)).await {
+            Ok(response) => response.and_then(DaemonResponse::into_result),
+            Err(_) => Err(AppError::api(format!("The daemon did not answer within {} seconds", timeout.as_secs()))),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (socket, timeout, url, api_key, body);
+        None
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::{AppError, Result};
+use serde_json::json;
+use std::io::{Read, Write};
+
+/// Magic bytes at the start of the compact binary embeddings format
+pub const BINARY_MAGIC: &[u8; 4] = b"CEMB";
+
+/// Current version of the compact binary embeddings format
+pub const BINARY_VERSION: u32 = 1;
+
+/// Write one JSON object per line with the input index, text and embedding
+pub fn write_jsonl<W: Write>(writer: &mut W, inputs: &[String], embeddings: &[Vec<f32>]) -> Result<()> {
+    for (index, (input, embedding)) in inputs.iter().zip(embeddings).enumerate() {
+        let line = json!({
+            "index": index,
+            "input": input,
+            "embedding": embedding,
+        });
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Write embeddings in the compact binary format.
+///
+/// Layout (all little-endian): magic `CEMB`, u32 version, u32 vector count,
+/// u32 dimension, then `count * dimension` f32 values.
+pub fn write_binary<W: Write>(writer: &mut W, embeddings: &[Vec<f32>]) -> Result<()> {
+    let dimension = embeddings.first().map(|e| e.len()).unwrap_or(0);
+    if embeddings.iter().any(|e| e.len() != dimension) {
+        return Err(AppError::unknown("All embeddings must have the same dimension for binary output"));
+    }
+
+    writer.write_all(BINARY_MAGIC)?;
+    writer.write_all(&BINARY_VERSION.to_le_bytes())?;
+    writer.write_all(&(embeddings.len() as u32).to_le_bytes())?;
+    writer.write_all(&(dimension as u32).to_le_bytes())?;
+    for value in embeddings.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read embeddings written by [`write_binary`]
+pub fn read_binary<R: Read>(reader: &mut R) -> Result<Vec<Vec<f32>>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != BINARY_MAGIC {
+        return Err(AppError::unknown("Not a binary embeddings file"));
+    }
+
+    let version = read_u32(reader)?;
+    if version != BINARY_VERSION {
+        return Err(AppError::unknown(format!("Unsupported binary embeddings version {}", version)));
+    }
+
+    let count = read_u32(reader)? as usize;
+    let dimension = read_u32(reader)? as usize;
+
+    let mut embeddings = Vec::with_capacity(count);
+    let mut buf = [0u8; 4];
+    for _ in 0..count {
+        let mut embedding = Vec::with_capacity(dimension);
+        for _ in 0..dimension {
+            reader.read_exact(&mut buf)?;
+            embedding.push(f32::from_le_bytes(buf));
+        }
+        embeddings.push(embedding);
+    }
+    Ok(embeddings)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
@@ -0,0 +1,249 @@
+mod provider;
+
+pub use provider::{CodestralProvider, Completion, CompletionProvider, MockProvider};
+
+use crate::error::{AppError, Result, ErrorExt};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// Characters of test output kept in each sample result
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+/// A FIM task: the completion is spliced between `prefix` and `suffix`,
+/// written to `file` in a temporary directory and checked by `test_command`
+/// or by comparing against `expected_output`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EvalTask {
+    pub id: String,
+    pub prefix: String,
+    pub suffix: String,
+    /// File the spliced code is written to, relative to the task directory
+    #[serde(default = "default_task_file")]
+    pub file: String,
+    /// Extra files to create in the task directory
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Shell command run in the task directory; exit status 0 means pass
+    #[serde(default)]
+    pub test_command: Option<String>,
+    /// Expected stdout of `test_command`, or the expected completion itself
+    /// when there is no command
+    #[serde(default)]
+    pub expected_output: Option<String>,
+    /// Reference completion, used to answer the task in offline runs
+    #[serde(default)]
+    pub canonical_solution: Option<String>,
+}
+
+fn default_task_file() -> String {
+    "main.rs".to_string()
+}
+
+/// Outcome of one sample of a task
+#[derive(Serialize, Clone, Debug)]
+pub struct SampleResult {
+    pub passed: bool,
+    pub latency_ms: u64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub completion: String,
+    pub output: String,
+}
+
+/// All samples of one task
+#[derive(Serialize, Clone, Debug)]
+pub struct TaskResult {
+    pub id: String,
+    pub passed: usize,
+    pub samples: Vec<SampleResult>,
+}
+
+/// Aggregate scores of one provider over a dataset
+#[derive(Serialize, Clone, Debug)]
+pub struct EvalReport {
+    pub model: String,
+    pub tasks: usize,
+    pub samples_per_task: usize,
+    pub pass_at_1: f64,
+    pub pass_at_k: f64,
+    pub mean_latency_ms: f64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub results: Vec<TaskResult>,
+}
+
+/// Read tasks from a JSONL file, skipping blank lines
+pub fn load_tasks<P: AsRef<Path>>(path: P) -> Result<Vec<EvalTask>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read dataset {}", path.display()))?;
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid task on line {} of {}", n + 1, path.display()))
+        })
+        .collect()
+}
+
+/// Mock provider answering every task with its canonical solution
+pub fn mock_provider_for(tasks: &[EvalTask]) -> MockProvider {
+    tasks.iter().fold(MockProvider::new(), |provider, task| match &task.canonical_solution {
+        Some(solution) => provider.with_answer(&task.prefix, solution),
+        None => provider,
+    })
+}
+
+/// Unbiased estimate of the probability that at least one of `k` samples
+/// passes, given `correct` passing samples out of `n`
+pub fn pass_at_k(n: usize, correct: usize, k: usize) -> f64 {
+    if n == 0 || k == 0 {
+        return 0.0;
+    }
+    let k = k.min(n);
+    if n - correct < k {
+        return 1.0;
+    }
+    // 1 - C(n - c, k) / C(n, k), computed as a running product
+    1.0 - (n - correct + 1..=n).map(|i| 1.0 - k as f64 / i as f64).product::<f64>()
+}
+
+/// Run `samples` completions per task through the provider and score them
+pub async fn run_eval(
+    provider: &dyn CompletionProvider,
+    tasks: &[EvalTask],
+    samples: usize,
+    max_tokens: u32,
+    timeout: Duration,
+) -> Result<EvalReport> {
+    let mut results = Vec::new();
+
+    for task in tasks {
+        info!("Evaluating task {} with {}", task.id, provider.name());
+        let mut sample_results = Vec::new();
+
+        for seed in 0..samples as u64 {
+            let started = Instant::now();
+            // A failed request counts as a failed sample instead of ending the run
+            let completion = match provider.complete(&task.prefix, &task.suffix, max_tokens, seed).await {
+                Ok(completion) => completion,
+                Err(e) => {
+                    warn!("Task {} sample {} failed: {}", task.id, seed + 1, e);
+                    sample_results.push(SampleResult {
+                        passed: false,
+                        latency_ms: started.elapsed().as_millis() as u64,
+                        completion_tokens: 0,
+                        total_tokens: 0,
+                        completion: String::new(),
+                        output: format!("Completion request failed: {}", e),
+                    });
+                    continue;
+                },
+            };
+            let latency_ms = started.elapsed().as_millis() as u64;
+
+            let (passed, output) = check_completion(task, &completion.text, timeout).await?;
+            debug!("Task {} sample {}: {}", task.id, seed + 1, if passed { "pass" } else { "fail" });
+
+            sample_results.push(SampleResult {
+                passed,
+                latency_ms,
+                completion_tokens: completion.completion_tokens,
+                total_tokens: completion.total_tokens,
+                completion: completion.text,
+                output,
+            });
+        }
+
+        results.push(TaskResult {
+            id: task.id.clone(),
+            passed: sample_results.iter().filter(|s| s.passed).count(),
+            samples: sample_results,
+        });
+    }
+
+    Ok(summarize(&provider.name(), samples, results))
+}
+
+fn summarize(model: &str, samples: usize, results: Vec<TaskResult>) -> EvalReport {
+    let tasks = results.len();
+    let mean = |f: &dyn Fn(&TaskResult) -> f64| {
+        if tasks == 0 { 0.0 } else { results.iter().map(f).sum::<f64>() / tasks as f64 }
+    };
+    let all_samples = || results.iter().flat_map(|r| &r.samples);
+    let sample_count = all_samples().count();
+
+    EvalReport {
+        model: model.to_string(),
+        tasks,
+        samples_per_task: samples,
+        pass_at_1: mean(&|r| pass_at_k(r.samples.len(), r.passed, 1)),
+        pass_at_k: mean(&|r| pass_at_k(r.samples.len(), r.passed, samples)),
+        mean_latency_ms: if sample_count == 0 {
+            0.0
+        } else {
+            all_samples().map(|s| s.latency_ms as f64).sum::<f64>() / sample_count as f64
+        },
+        completion_tokens: all_samples().map(|s| s.completion_tokens).sum(),
+        total_tokens: all_samples().map(|s| s.total_tokens).sum(),
+        results,
+    }
+}
+
+/// Check a completion against the task, returning whether it passed and the
+/// (truncated) test output
+pub async fn check_completion(task: &EvalTask, completion: &str, timeout: Duration) -> Result<(bool, String)> {
+    let Some(command) = &task.test_command else {
+        let passed = task.expected_output.as_deref().map(|e| e.trim() == completion.trim()).unwrap_or(false);
+        return Ok((passed, String::new()));
+    };
+
+    let dir = tempfile::tempdir()?;
+    for (name, content) in &task.files {
+        write_task_file(dir.path(), name, content)?;
+    }
+    write_task_file(dir.path(), &task.file, &format!("{}{}{}", task.prefix, completion, task.suffix))?;
+
+    let output = tokio::time::timeout(
+        timeout,
+        Command::new("sh")
+            .args(["-c", command])
+            .current_dir(dir.path())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+
+    let output = match output {
+        Ok(output) => output.with_context(|| format!("Failed to run test command for task {}", task.id))?,
+        Err(_) => return Ok((false, format!("Timed out after {} seconds", timeout.as_secs()))),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let passed = output.status.success()
+        && task.expected_output.as_deref().map(|e| e.trim() == stdout.trim()).unwrap_or(true);
+
+    let combined = format!("{}{}", stdout, stderr);
+    Ok((passed, combined.chars().take(MAX_OUTPUT_CHARS).collect()))
+}
+
+fn write_task_file(dir: &Path, name: &str, content: &str) -> Result<()> {
+    let relative = Path::new(name);
+    if relative.is_absolute() || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err(AppError::cli_usage(format!("Task file '{}' must be a relative path inside the task directory", name)));
+    }
+    let path = dir.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,86 @@
+use crate::api::{CodestralClient, extract_response_fields};
+use crate::error::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// A completion returned by a provider along with its token usage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+}
+
+/// Source of FIM completions for the evaluation harness.
+///
+/// Implemented by [`CodestralProvider`]; [`MockProvider`] runs the harness offline.
+#[async_trait]
+pub trait CompletionProvider: Send + Sync {
+    /// Name reported for this provider, usually the model id
+    fn name(&self) -> String;
+
+    /// Complete the code between `prefix` and `suffix`, using `seed` for sampling
+    async fn complete(&self, prefix: &str, suffix: &str, max_tokens: u32, seed: u64) -> Result<Completion>;
+}
+
+/// Codestral client for one model, as a completion provider
+pub struct CodestralProvider {
+    model: String,
+    client: CodestralClient,
+}
+
+impl CodestralProvider {
+    pub fn new(model: &str, client: CodestralClient) -> Self {
+        Self { model: model.to_string(), client }
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for CodestralProvider {
+    fn name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn complete(&self, prefix: &str, suffix: &str, max_tokens: u32, seed: u64) -> Result<Completion> {
+        let responses = self.client.code_completion_with_seeds(prefix, suffix, max_tokens, &[seed]).await?;
+        let response = responses.first().map(extract_response_fields);
+        Ok(response
+            .map(|r| Completion {
+                text: r.content.unwrap_or_default(),
+                completion_tokens: r.completion_tokens,
+                total_tokens: r.total_tokens,
+            })
+            .unwrap_or_default())
+    }
+}
+
+/// Offline provider answering each prefix with a fixed completion
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    answers: HashMap<String, String>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests whose prefix is `prefix` with `completion`
+    pub fn with_answer(mut self, prefix: &str, completion: &str) -> Self {
+        self.answers.insert(prefix.to_string(), completion.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for MockProvider {
+    fn name(&self) -> String {
+        "mock".to_string()
+    }
+
+    async fn complete(&self, prefix: &str, _suffix: &str, _max_tokens: u32, _seed: u64) -> Result<Completion> {
+        let text = self.answers.get(prefix).cloned().unwrap_or_default();
+        let tokens = text.split_whitespace().count() as i64;
+        Ok(Completion { text, completion_tokens: tokens, total_tokens: tokens })
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::{AppError, Result, ErrorExt};
+use crate::input::language_for;
+use crate::templates::{Template, TemplateDef, TemplateSource};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Name of the template used by `explain`; a template with this name in the
+/// configuration or templates directory replaces the built-in one
+pub const EXPLAIN_TEMPLATE_NAME: &str = "explain";
+
+/// Built-in explanation prompt
+pub const DEFAULT_EXPLAIN_TEMPLATE: &str = "\
+Explain the code from `{{path}}` (lines {{start}}-{{end}}) to a developer who is new to this codebase. \
+{{depth}} Answer in Markdown.
+
+Code before the selection, for context:
+```{{language}}
+{{before}}
+```
+
+Selected code:
+```{{language}}
+{{selection}}
+```
+
+Code after the selection, for context:
+```{{language}}
+{{after}}
+```";
+
+/// How much detail to ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Brief,
+    Detailed,
+}
+
+impl Depth {
+    /// Instruction inserted into the template as `{{depth}}`
+    pub fn instruction(self) -> &'static str {
+        match self {
+            Depth::Brief => "Keep it brief: a short summary of what the code does and why, in a few sentences.",
+            Depth::Detailed => "Be thorough: describe its purpose, walk through it step by step, and point out \
+                                edge cases, assumptions and potential problems.",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Depth::Brief => "brief",
+            Depth::Detailed => "detailed",
+        }
+    }
+}
+
+/// Parse `file`, `file:line` or `file:start-end` into a path and 1-based line range
+pub fn parse_target(target: &str) -> Result<(String, Option<(usize, usize)>)> {
+    let Some((path, range)) = target.rsplit_once(':') else {
+        return Ok((target.to_string(), None));
+    };
+    if range.is_empty() || !range.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Ok((target.to_string(), None));
+    }
+
+    let invalid = || AppError::cli_usage(format!("Invalid line range '{}'; expected START-END", range));
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start.parse().map_err(|_| invalid())?, end.parse().map_err(|_| invalid())?),
+        None => {
+            let line = range.parse().map_err(|_| invalid())?;
+            (line, line)
+        },
+    };
+    if start == 0 || end < start {
+        return Err(invalid());
+    }
+    Ok((path.to_string(), Some((start, end))))
+}
+
+/// Lines selected from a file, with surrounding context
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub path: String,
+    pub start: usize,
+    pub end: usize,
+    pub before: String,
+    pub text: String,
+    pub after: String,
+}
+
+impl Selection {
+    /// Select lines `start..=end` (the whole file if `None`) of `content`,
+    /// with up to `context_lines` lines on each side
+    pub fn from_content(path: &str, content: &str, range: Option<(usize, usize)>, context_lines: usize) -> Result<Self> {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.is_empty() {
+            return Err(AppError::cli_usage(format!("{} is empty", path)));
+        }
+
+        let (start, end) = range.unwrap_or((1, lines.len()));
+        if start > lines.len() {
+            return Err(AppError::cli_usage(format!(
+                "Line {} is past the end of {} ({} lines)", start, path, lines.len()
+            )));
+        }
+        let end = end.min(lines.len());
+
+        let before_start = (start - 1).saturating_sub(context_lines);
+        let after_end = (end + context_lines).min(lines.len());
+        Ok(Self {
+            path: path.to_string(),
+            start,
+            end,
+            before: lines[before_start..start - 1].join("\n"),
+            text: lines[start - 1..end].join("\n"),
+            after: lines[end..after_end].join("\n"),
+        })
+    }
+
+    pub fn read(path: &str, range: Option<(usize, usize)>, context_lines: usize) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        Self::from_content(path, &content, range, context_lines)
+    }
+
+    /// Template variables describing the selection
+    pub fn template_vars(&self, depth: Depth) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("path".to_string(), self.path.clone()),
+            ("language".to_string(), language_for(&self.path).to_string()),
+            ("start".to_string(), self.start.to_string()),
+            ("end".to_string(), self.end.to_string()),
+            ("before".to_string(), self.before.clone()),
+            ("selection".to_string(), self.text.clone()),
+            ("after".to_string(), self.after.clone()),
+            ("depth".to_string(), depth.instruction().to_string()),
+        ])
+    }
+}
+
+/// The built-in explanation template
+pub fn default_template() -> Template {
+    Template {
+        name: EXPLAIN_TEMPLATE_NAME.to_string(),
+        def: TemplateDef {
+            description: Some("Explain a region of code".to_string()),
+            text: DEFAULT_EXPLAIN_TEMPLATE.to_string(),
+            ..Default::default()
+        },
+        source: TemplateSource::Config,
+    }
+}
@@ -0,0 +1,13 @@
+mod postprocess;
+mod ranking;
+mod verify;
+
+pub use postprocess::{
+    BlockTruncation, PostProcessOptions, match_line_endings, postprocess, strip_trailing_whitespace,
+    trim_suffix_overlap, truncate_at_closing_brace, truncate_at_dedent,
+};
+pub use ranking::{RankedCandidate, bracket_errors, dedupe_candidates, rank_candidates, suffix_overlap, syntax_valid};
+pub use verify::{
+    Diagnostic, VerifyReport, cargo_check_spliced, check_rust_syntax, feedback_prefix, find_crate_root,
+    parse_cargo_messages, splice_at_cursor,
+};
@@ -0,0 +1,165 @@
+use crate::Config;
+use crate::fim::{bracket_errors, suffix_overlap};
+use serde::{Deserialize, Serialize};
+
+/// How to cut a completion that runs past the end of the block at the cursor
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTruncation {
+    /// Keep the whole completion
+    #[default]
+    Off,
+    /// Stop before the first brace that closes a block opened in the prefix
+    Braces,
+    /// Stop at the first line indented less than the cursor line
+    Indent,
+}
+
+/// Which post-processing steps to apply to FIM output
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcessOptions {
+    pub trim_suffix_overlap: bool,
+    pub block_truncation: BlockTruncation,
+    pub strip_trailing_whitespace: bool,
+    pub normalize_line_endings: bool,
+}
+
+impl PostProcessOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            trim_suffix_overlap: config.fim_trim_suffix_overlap,
+            block_truncation: config.fim_block_truncation,
+            strip_trailing_whitespace: config.fim_strip_trailing_whitespace,
+            normalize_line_endings: config.fim_normalize_line_endings,
+        }
+    }
+}
+
+/// Run the enabled post-processing steps over a completion, in order
+pub fn postprocess(completion: &str, prefix: &str, suffix: &str, options: &PostProcessOptions) -> String {
+    let mut text = completion.to_string();
+
+    if options.trim_suffix_overlap {
+        text = trim_suffix_overlap(&text, suffix);
+    }
+    text = match options.block_truncation {
+        BlockTruncation::Off => text,
+        BlockTruncation::Braces => truncate_at_closing_brace(&text),
+        BlockTruncation::Indent => truncate_at_dedent(&text, prefix),
+    };
+    if options.strip_trailing_whitespace {
+        text = strip_trailing_whitespace(&text);
+    }
+    if options.normalize_line_endings {
+        text = match_line_endings(&text, prefix, suffix);
+    }
+    text
+}
+
+/// Remove the end of the completion that repeats the start of the suffix.
+///
+/// The overlap is only removed when that leaves balanced brackets, or when it
+/// covers whole lines without adding bracket errors, so a bracket the
+/// completion opened and closed itself is kept. Otherwise the completion is
+/// returned unchanged.
+pub fn trim_suffix_overlap(completion: &str, suffix: &str) -> String {
+    let overlap = suffix_overlap(completion, suffix);
+    if overlap == 0 {
+        return completion.to_string();
+    }
+    let trimmed = completion.trim_end();
+    let kept = &trimmed[..trimmed.len() - overlap];
+
+    let errors = bracket_errors(kept);
+    let line_start = kept.trim_end_matches([' ', '\t']);
+    let whole_lines = line_start.is_empty() || line_start.ends_with('\n');
+    if errors == 0 || (whole_lines && errors <= bracket_errors(completion)) {
+        kept.to_string()
+    } else {
+        completion.to_string()
+    }
+}
+
+/// Cut the completion before the first closing brace that has no opening
+/// brace in the completion itself. String literals and `//` comments are skipped.
+pub fn truncate_at_closing_brace(completion: &str) -> String {
+    let mut depth: i32 = 0;
+    let mut in_string = false;
+    let mut chars = completion.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => { chars.next(); },
+                '"' => in_string = false,
+                _ => {},
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if chars.peek().map(|(_, n)| *n) == Some('/') => {
+                for (_, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            },
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    // Drop the indentation before the brace as well
+                    return completion[..i].trim_end_matches([' ', '\t']).to_string();
+                }
+            },
+            _ => {},
+        }
+    }
+    completion.to_string()
+}
+
+/// Cut the completion at the first non-blank line, after its first line, that
+/// is indented less than the line the cursor is on
+pub fn truncate_at_dedent(completion: &str, prefix: &str) -> String {
+    let cursor_line = prefix.rsplit('\n').next().unwrap_or("");
+    let base = indentation(cursor_line);
+
+    let mut offset = 0;
+    for (n, line) in completion.split_inclusive('\n').enumerate() {
+        if n > 0 && !line.trim().is_empty() && indentation(line) < base {
+            return completion[..offset].to_string();
+        }
+        offset += line.len();
+    }
+    completion.to_string()
+}
+
+/// Remove spaces and tabs at the end of every line
+pub fn strip_trailing_whitespace(completion: &str) -> String {
+    completion
+        .split('\n')
+        .map(|line| match line.strip_suffix('\r') {
+            Some(line) => format!("{}\r", line.trim_end_matches([' ', '\t'])),
+            None => line.trim_end_matches([' ', '\t']).to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Use `\r\n` line endings if the surrounding source does, `\n` otherwise
+pub fn match_line_endings(completion: &str, prefix: &str, suffix: &str) -> String {
+    let normalized = completion.replace("\r\n", "\n");
+    if prefix.contains("\r\n") || suffix.contains("\r\n") {
+        normalized.replace('\n', "\r\n")
+    } else {
+        normalized
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A completion candidate with its local ranking score and the heuristics behind it
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedCandidate {
+    pub text: String,
+    pub score: f64,
+    /// Bracket errors introduced by the candidate
+    pub bracket_errors: usize,
+    /// Whether the spliced code parses, if the language is supported
+    pub syntax_valid: Option<bool>,
+    /// Characters at the end of the candidate that repeat the start of the suffix
+    pub suffix_overlap: usize,
+    pub lines: usize,
+}
+
+/// Drop candidates that only differ in whitespace, keeping the first of each
+pub fn dedupe_candidates(candidates: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|c| seen.insert(c.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .collect()
+}
+
+/// Count unmatched and mismatched brackets, ignoring string literals and
+/// `//` line comments
+pub fn bracket_errors(code: &str) -> usize {
+    let mut stack = Vec::new();
+    let mut errors = 0;
+    let mut chars = code.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            match c {
+                '\\' => { chars.next(); },
+                '"' => in_string = false,
+                _ => {},
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if chars.peek() == Some(&'/') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            },
+            '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.last() == Some(&expected) {
+                    stack.pop();
+                } else {
+                    errors += 1;
+                }
+            },
+            _ => {},
+        }
+    }
+    errors + stack.len()
+}
+
+/// Parse code in the given language, returning `None` for unsupported languages
+pub fn syntax_valid(code: &str, language: &str) -> Option<bool> {
+    match language.to_lowercase().as_str() {
+        "rust" | "rs" => Some(syn::parse_file(code).is_ok()),
+        "json" => Some(serde_json::from_str::<serde_json::Value>(code).is_ok()),
+        _ => None,
+    }
+}
+
+/// Length of the longest end of `candidate` that repeats the start of `suffix`,
+/// ignoring surrounding whitespace
+pub fn suffix_overlap(candidate: &str, suffix: &str) -> usize {
+    let candidate = candidate.trim_end();
+    let suffix = suffix.trim_start();
+    let max = candidate.len().min(suffix.len());
+    (1..=max)
+        .rev()
+        .find(|&n| suffix.is_char_boundary(n) && candidate.is_char_boundary(candidate.len() - n) && candidate.ends_with(&suffix[..n]))
+        .unwrap_or(0)
+}
+
+/// Score candidates by how well they fit between `prefix` and `suffix`, best first.
+///
+/// Candidates lose points for introducing bracket errors or syntax errors,
+/// for repeating the suffix and for being empty or long.
+pub fn rank_candidates(candidates: &[String], prefix: &str, suffix: &str, language: Option<&str>) -> Vec<RankedCandidate> {
+    let baseline_errors = bracket_errors(&format!("{}{}", prefix, suffix));
+    let baseline_valid = language.and_then(|l| syntax_valid(&format!("{}{}", prefix, suffix), l));
+
+    let mut ranked: Vec<RankedCandidate> = candidates
+        .iter()
+        .map(|text| {
+            let spliced = format!("{}{}{}", prefix, text, suffix);
+            let bracket_errors = bracket_errors(&spliced).saturating_sub(baseline_errors);
+            let syntax_valid = language.and_then(|l| syntax_valid(&spliced, l));
+            let suffix_overlap = suffix_overlap(text, suffix);
+            let lines = text.lines().count();
+
+            let mut score = -2.0 * bracket_errors as f64;
+            score += match (syntax_valid, baseline_valid) {
+                (Some(true), _) => 1.5,
+                // Only penalize a parse failure if the code parsed without the candidate
+                (Some(false), Some(true)) => -1.5,
+                _ => 0.0,
+            };
+            score -= (suffix_overlap as f64 / 20.0).min(1.5);
+            score -= if text.trim().is_empty() { 3.0 } else { 0.1 * (1.0 + lines as f64).ln() };
+
+            RankedCandidate {
+                text: text.clone(),
+                score,
+                bracket_errors,
+                syntax_valid,
+                suffix_overlap,
+                lines,
+            }
+        })
+        .collect();
+
+    // Stable sort keeps the provider's order among equal scores
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
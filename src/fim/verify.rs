@@ -0,0 +1,180 @@
+use crate::error::{AppError, Result, ErrorExt};
+use ignore::WalkBuilder;
+use log::{debug, info};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// An error or warning found while verifying a completion
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Diagnostic {
+    /// "syntax" for parse errors, otherwise the compiler level ("error", "warning")
+    pub level: String,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level == "syntax" || self.level == "error"
+    }
+}
+
+/// Outcome of verifying one completion attempt
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub attempt: usize,
+    pub completion: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub passed: bool,
+}
+
+/// Parse a complete Rust file with `syn`, returning the syntax error if any
+pub fn check_rust_syntax(code: &str) -> Vec<Diagnostic> {
+    match syn::parse_file(code) {
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            let start = e.span().start();
+            vec![Diagnostic {
+                level: "syntax".to_string(),
+                message: e.to_string(),
+                line: Some(start.line),
+                column: Some(start.column + 1),
+            }]
+        },
+    }
+}
+
+/// Find the nearest directory at or above `path` containing a `Cargo.toml`
+pub fn find_crate_root(path: &Path) -> Option<PathBuf> {
+    let start = if path.is_dir() { path } else { path.parent()? };
+    let start = fs::canonicalize(start).ok()?;
+    start.ancestors().find(|dir| dir.join("Cargo.toml").is_file()).map(Path::to_path_buf)
+}
+
+/// Extract diagnostics for `file` (relative to the crate root) from the output
+/// of `cargo check --message-format=json`
+pub fn parse_cargo_messages(output: &str, file: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|msg| msg.get("reason").and_then(|r| r.as_str()) == Some("compiler-message"))
+        .filter_map(|msg| {
+            let message = msg.get("message")?;
+            let level = message.get("level")?.as_str()?.to_string();
+            let span = message
+                .get("spans")?
+                .as_array()?
+                .iter()
+                .find(|s| s.get("file_name").and_then(|f| f.as_str()) == Some(file))?;
+
+            Some(Diagnostic {
+                level,
+                message: message.get("message")?.as_str()?.to_string(),
+                line: span.get("line_start").and_then(|l| l.as_u64()).map(|l| l as usize),
+                column: span.get("column_start").and_then(|c| c.as_u64()).map(|c| c as usize),
+            })
+        })
+        .collect()
+}
+
+/// Splice a completion into the full content of the file the prompt and
+/// suffix were taken from, at the end of the first occurrence of the prompt
+/// that the suffix follows. Whitespace may separate the two, since shells drop
+/// trailing newlines from `$(...)` arguments.
+pub fn splice_at_cursor(file: &str, prompt: &str, completion: &str, suffix: &str) -> Option<String> {
+    let suffix = suffix.trim_start();
+    let cursor = file
+        .match_indices(prompt)
+        .map(|(start, _)| start + prompt.len())
+        .find(|&cursor| file[cursor..].trim_start().starts_with(suffix))?;
+    Some(format!("{}{}{}", &file[..cursor], completion, &file[cursor..]))
+}
+
+/// The workspace a crate belongs to: the nearest directory at or above it
+/// whose `Cargo.toml` has a `[workspace]` table, or the crate itself
+fn workspace_root(crate_root: &Path) -> &Path {
+    crate_root
+        .ancestors()
+        .find(|dir| fs::read_to_string(dir.join("Cargo.toml")).is_ok_and(|m| m.contains("[workspace]")))
+        .unwrap_or(crate_root)
+}
+
+/// Copy the files under `root` to `dest`, leaving out `.git/` and `target/`
+fn copy_sources(root: &Path, dest: &Path) -> Result<()> {
+    let walker = WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git" && entry.file_name() != "target")
+        .build();
+    for entry in walker {
+        let entry = entry.map_err(|e| AppError::unknown(format!("Failed to list {}: {}", root.display(), e)))?;
+        let target = dest.join(entry.path().strip_prefix(root).unwrap_or(entry.path()));
+        if entry.file_type().is_some_and(|t| t.is_dir()) {
+            fs::create_dir_all(&target).with_context(|| format!("Failed to create {}", target.display()))?;
+        } else if entry.file_type().is_some_and(|t| t.is_file()) {
+            fs::copy(entry.path(), &target).with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `cargo check` on a copy of the crate containing `file`, with `content`
+/// in place of the file, returning the diagnostics reported for that file.
+/// The crate's own target directory is reused so dependencies stay built.
+pub async fn cargo_check_spliced(file: &Path, content: &str, timeout: Duration) -> Result<Vec<Diagnostic>> {
+    let crate_root = find_crate_root(file)
+        .ok_or_else(|| AppError::cli_usage(format!("No Cargo.toml found above {}", file.display())))?;
+    let root = workspace_root(&crate_root);
+    let canonical = fs::canonicalize(file)
+        .with_context(|| format!("Failed to resolve {}", file.display()))?;
+    let relative = canonical.strip_prefix(root).unwrap_or(&canonical);
+
+    let copy = tempfile::tempdir().with_context(|| "Failed to create a temporary directory")?;
+    copy_sources(root, copy.path())?;
+    fs::write(copy.path().join(relative), content)
+        .with_context(|| format!("Failed to write the copy of {}", file.display()))?;
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    info!("Running cargo check on a copy of {}", root.display());
+
+    let mut command = Command::new("cargo");
+    command
+        .args(["check", "--message-format=json", "--quiet"])
+        .current_dir(copy.path().join(crate_root.strip_prefix(root).unwrap_or(Path::new(""))))
+        .kill_on_drop(true);
+    if std::env::var_os("CARGO_TARGET_DIR").is_none() {
+        command.env("CARGO_TARGET_DIR", root.join("target"));
+    }
+    let output = tokio::time::timeout(timeout, command.output())
+        .await
+        .map_err(|_| AppError::unknown(format!("cargo check timed out after {} seconds", timeout.as_secs())))?
+        .with_context(|| "Failed to run cargo check")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let diagnostics = parse_cargo_messages(&stdout, &relative);
+    debug!("cargo check reported {} diagnostics for {}", diagnostics.len(), relative);
+    Ok(diagnostics)
+}
+
+/// Prefix for a retry request: the failed completion and its errors as line
+/// comments, followed by the original prompt
+pub fn feedback_prefix(prompt: &str, completion: &str, diagnostics: &[Diagnostic], comment: &str) -> String {
+    let mut prefix = format!("{} A previous completion at the cursor failed to compile:\n", comment);
+    for line in completion.lines() {
+        prefix.push_str(&format!("{}     {}\n", comment, line));
+    }
+    prefix.push_str(&format!("{} Errors:\n", comment));
+    for diagnostic in diagnostics.iter().filter(|d| d.is_error()) {
+        match diagnostic.line {
+            Some(line) => prefix.push_str(&format!("{} - line {}: {}\n", comment, line, diagnostic.message)),
+            None => prefix.push_str(&format!("{} - {}\n", comment, diagnostic.message)),
+        }
+    }
+    prefix.push_str(&format!("{} Write a corrected completion.\n\n", comment));
+    prefix.push_str(prompt);
+    prefix
+}
@@ -0,0 +1,173 @@
+use crate::error::{AppError, Result, ErrorExt};
+use crate::index::collect_source_files;
+use serde::Serialize;
+use similar::TextDiff;
+use std::fs;
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::{Attribute, ImplItem, Item, Visibility};
+
+/// Most lines of a generated doc comment that are kept
+pub const MAX_DOC_LINES: usize = 12;
+
+/// A public item without doc comments
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UndocumentedItem {
+    pub kind: &'static str,
+    pub name: String,
+    /// 1-based line where the item starts, including its attributes;
+    /// the doc comment is inserted above it
+    pub line: usize,
+    pub indent: String,
+}
+
+/// Collect the Rust files at `path`, which may be a single file or a
+/// directory walked while respecting `.gitignore`. Paths are returned as
+/// given or joined to the directory.
+pub fn collect_rust_files(path: &Path, index_file_name: &str) -> Result<Vec<(String, String)>> {
+    if path.is_file() {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(vec![(path.display().to_string(), content)]);
+    }
+
+    Ok(collect_source_files(path, index_file_name)?
+        .into_iter()
+        .filter(|(relative, _)| relative.ends_with(".rs"))
+        .map(|(relative, content)| (path.join(relative).display().to_string(), content))
+        .collect())
+}
+
+/// Find public items without doc comments, in source order
+pub fn find_undocumented(code: &str) -> Result<Vec<UndocumentedItem>> {
+    let file = syn::parse_file(code).map_err(|e| AppError::cli_usage(format!("Failed to parse file: {}", e)))?;
+    let lines: Vec<&str> = code.lines().collect();
+    let mut items = Vec::new();
+    collect_items(&file.items, &lines, &mut items);
+    items.sort_by_key(|item| item.line);
+    Ok(items)
+}
+
+fn collect_items(items: &[Item], lines: &[&str], found: &mut Vec<UndocumentedItem>) {
+    for item in items {
+        let mut push = |kind: &'static str, name: String, attrs: &[Attribute], vis: &Visibility, span: proc_macro2::Span| {
+            if matches!(vis, Visibility::Public(_)) && !has_docs(attrs) {
+                let line = span.start().line;
+                found.push(UndocumentedItem { kind, name, line, indent: indent_of(lines, line) });
+            }
+        };
+
+        match item {
+            Item::Fn(f) => push("fn", f.sig.ident.to_string(), &f.attrs, &f.vis, item.span()),
+            Item::Struct(s) => push("struct", s.ident.to_string(), &s.attrs, &s.vis, item.span()),
+            Item::Enum(e) => push("enum", e.ident.to_string(), &e.attrs, &e.vis, item.span()),
+            Item::Trait(t) => push("trait", t.ident.to_string(), &t.attrs, &t.vis, item.span()),
+            Item::Type(t) => push("type", t.ident.to_string(), &t.attrs, &t.vis, item.span()),
+            Item::Const(c) => push("const", c.ident.to_string(), &c.attrs, &c.vis, item.span()),
+            Item::Static(s) => push("static", s.ident.to_string(), &s.attrs, &s.vis, item.span()),
+            Item::Union(u) => push("union", u.ident.to_string(), &u.attrs, &u.vis, item.span()),
+            Item::Mod(m) => {
+                // Only inline modules; `pub mod x;` is documented in its own file with `//!`
+                if let Some((_, content)) = &m.content {
+                    push("mod", m.ident.to_string(), &m.attrs, &m.vis, item.span());
+                    collect_items(content, lines, found);
+                }
+            },
+            // Methods of trait impls are documented on the trait
+            Item::Impl(imp) if imp.trait_.is_none() => {
+                for impl_item in &imp.items {
+                    if let ImplItem::Fn(f) = impl_item {
+                        if matches!(f.vis, Visibility::Public(_)) && !has_docs(&f.attrs) {
+                            let line = impl_item.span().start().line;
+                            found.push(UndocumentedItem {
+                                kind: "method",
+                                name: f.sig.ident.to_string(),
+                                line,
+                                indent: indent_of(lines, line),
+                            });
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+fn has_docs(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("doc"))
+}
+
+fn indent_of(lines: &[&str], line: usize) -> String {
+    let text = lines.get(line.saturating_sub(1)).copied().unwrap_or("");
+    text[..text.len() - text.trim_start().len()].to_string()
+}
+
+/// FIM prompt and suffix for documenting an item: the prefix ends with an
+/// opened `/// ` line above the item
+pub fn doc_fim_parts(code: &str, item: &UndocumentedItem) -> (String, String) {
+    let lines: Vec<&str> = code.lines().collect();
+    let split = item.line.saturating_sub(1).min(lines.len());
+
+    let mut prefix = lines[..split].join("\n");
+    if !prefix.is_empty() {
+        prefix.push('\n');
+    }
+    prefix.push_str(&format!("{}/// ", item.indent));
+
+    let suffix = format!("\n{}\n", lines[split..].join("\n"));
+    (prefix, suffix)
+}
+
+/// Turn a completion of an opened `/// ` line into doc comment lines,
+/// stopping at the first line that is not part of the comment
+pub fn doc_lines(completion: &str, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, line) in completion.lines().enumerate() {
+        let text = if i == 0 {
+            line.trim()
+        } else {
+            match line.trim_start().strip_prefix("///") {
+                Some(rest) => rest.trim(),
+                None => break,
+            }
+        };
+        if i == 0 && text.is_empty() {
+            break;
+        }
+        lines.push(if text.is_empty() { format!("{}///", indent) } else { format!("{}/// {}", indent, text) });
+        if lines.len() == MAX_DOC_LINES {
+            break;
+        }
+    }
+
+    // Trailing blank comment lines add nothing
+    while lines.last().is_some_and(|l| l.trim_end().ends_with("///")) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Insert doc lines above an item
+pub fn insert_docs(code: &str, item: &UndocumentedItem, docs: &[String]) -> String {
+    let mut lines: Vec<&str> = code.lines().collect();
+    let at = item.line.saturating_sub(1).min(lines.len());
+    for (i, doc) in docs.iter().enumerate() {
+        lines.insert(at + i, doc);
+    }
+
+    let mut result = lines.join("\n");
+    if code.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Unified diff between the original and documented content of a file
+pub fn unified_patch(path: &str, original: &str, updated: &str) -> String {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    TextDiff::from_lines(original, updated)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
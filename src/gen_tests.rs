@@ -0,0 +1,291 @@
+use crate::error::{AppError, Result, ErrorExt};
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use syn::spanned::Spanned;
+use syn::{ImplItem, Item};
+use tokio::process::Command;
+
+/// Source of a function found in a file, with the type definitions it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSource {
+    /// Name as given on the command line, e.g. `parse` or `Parser::parse`
+    pub name: String,
+    /// Type the function is a method of, if any
+    pub self_type: Option<String>,
+    pub source: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Source of structs, enums, traits and type aliases from the same file
+    /// that the function mentions
+    pub related_types: Vec<String>,
+}
+
+/// Where generated tests are written
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestTarget {
+    /// A `#[cfg(test)]` module appended to the source file
+    Module { module_name: String },
+    /// A file under `tests/`, which can only use the crate's public API
+    Integration { crate_name: String, module_path: Option<String> },
+}
+
+/// Result of one generated test
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TestOutcome {
+    pub name: String,
+    pub passed: bool,
+}
+
+/// Find a free function or method by name (`name` or `Type::name`)
+pub fn find_function(code: &str, name: &str) -> Result<FunctionSource> {
+    let file = syn::parse_file(code).map_err(|e| AppError::cli_usage(format!("Failed to parse file: {}", e)))?;
+    let (wanted_type, wanted_fn) = match name.rsplit_once("::") {
+        Some((ty, f)) => (Some(ty), f),
+        None => (None, name),
+    };
+
+    let mut matches = Vec::new();
+    for item in &file.items {
+        match item {
+            Item::Fn(f) if wanted_type.is_none() && f.sig.ident == wanted_fn => {
+                matches.push((None, item.span()));
+            },
+            Item::Impl(imp) => {
+                let self_type = type_name(&imp.self_ty);
+                if wanted_type.is_some_and(|t| Some(t) != self_type.as_deref()) {
+                    continue;
+                }
+                for impl_item in &imp.items {
+                    if let ImplItem::Fn(f) = impl_item {
+                        if f.sig.ident == wanted_fn {
+                            matches.push((self_type.clone(), impl_item.span()));
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let (self_type, span) = match matches.len() {
+        0 => return Err(AppError::cli_usage(format!("Function '{}' not found", name))),
+        1 => matches.remove(0),
+        _ => {
+            let types: Vec<String> = matches.iter().filter_map(|(t, _)| t.clone()).collect();
+            return Err(AppError::cli_usage(format!(
+                "Function '{}' is ambiguous; qualify it with one of: {}",
+                name,
+                types.iter().map(|t| format!("{}::{}", t, wanted_fn)).collect::<Vec<_>>().join(", ")
+            )));
+        },
+    };
+
+    let (start_line, end_line) = (span.start().line, span.end().line);
+    let source = source_lines(code, start_line, end_line);
+
+    let mut mentioned: BTreeSet<&str> = identifiers(&source).collect();
+    if let Some(self_type) = &self_type {
+        mentioned.insert(self_type);
+    }
+    let related_types = file
+        .items
+        .iter()
+        .filter(|item| item_name(item).is_some_and(|n| mentioned.contains(n.as_str())))
+        .map(|item| source_lines(code, item.span().start().line, item.span().end().line))
+        .collect();
+
+    Ok(FunctionSource { name: name.to_string(), self_type, source, start_line, end_line, related_types })
+}
+
+fn type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Struct(s) => Some(s.ident.to_string()),
+        Item::Enum(e) => Some(e.ident.to_string()),
+        Item::Trait(t) => Some(t.ident.to_string()),
+        Item::Type(t) => Some(t.ident.to_string()),
+        Item::Union(u) => Some(u.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn identifiers(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|w| !w.is_empty())
+}
+
+/// Lines `start..=end` (1-based) of the code
+fn source_lines(code: &str, start: usize, end: usize) -> String {
+    code.lines()
+        .skip(start.saturating_sub(1))
+        .take(end + 1 - start.max(1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Package name from a crate's `Cargo.toml`, as used in `use` paths
+pub fn crate_name(crate_root: &Path) -> Result<String> {
+    let manifest_path = crate_root.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let manifest: toml::Value = toml::from_str(&manifest)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+    let name = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| manifest.get("package").and_then(|p| p.get("name")))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| AppError::config(format!("No package name in {}", manifest_path.display())))?;
+    Ok(name.replace('-', "_"))
+}
+
+/// Module path of a source file within its crate, e.g. `fim::ranking` for
+/// `src/fim/ranking.rs`. `None` for the crate root.
+pub fn module_path(file: &Path, crate_root: &Path) -> Option<String> {
+    let relative = file.strip_prefix(crate_root.join("src")).ok()?;
+    let mut parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if matches!(parts.last().map(String::as_str), Some("mod" | "lib" | "main")) {
+        parts.pop();
+    }
+    (!parts.is_empty()).then(|| parts.join("::"))
+}
+
+/// Name of the module or test file the tests for a function are written to
+pub fn tests_name(function: &FunctionSource) -> String {
+    let name = function.name.replace("::", "_");
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    format!("{}_generated_tests", snake)
+}
+
+/// Build the request asking for tests of a function
+pub fn test_prompt(function: &FunctionSource, target: &TestTarget) -> String {
+    let mut prompt = format!(
+        "Write unit tests for the Rust function `{}` below. Cover normal inputs, edge cases and error cases. \
+         Reply with a single ```rust code block containing only `use` declarations and `#[test]` functions \
+         (use `#[tokio::test]` for async functions), without a surrounding module.\n\n",
+        function.name
+    );
+    match target {
+        TestTarget::Module { .. } => {
+            prompt.push_str("The tests go in a child module of the file, so items are in scope through `use super::*;`, which is added for you.\n\n");
+        },
+        TestTarget::Integration { crate_name, module_path } => {
+            let path = match module_path {
+                Some(module) => format!("{}::{}", crate_name, module),
+                None => crate_name.clone(),
+            };
+            prompt.push_str(&format!(
+                "The tests go in an integration test file and can only use public items, imported from `{}`.\n\n",
+                path
+            ));
+        },
+    }
+
+    prompt.push_str(&format!("Function:\n```rust\n{}\n```\n", function.source));
+    if !function.related_types.is_empty() {
+        prompt.push_str(&format!("\nRelated types:\n```rust\n{}\n```\n", function.related_types.join("\n\n")));
+    }
+    prompt
+}
+
+/// Take the first Rust code block from a reply, or the whole reply if it has none
+pub fn extract_code(reply: &str) -> String {
+    for fence in ["```rust", "```rs", "```"] {
+        if let Some(start) = reply.find(fence) {
+            let body = &reply[start + fence.len()..];
+            let body = body.strip_prefix('\n').unwrap_or(body);
+            let end = body.find("```").unwrap_or(body.len());
+            return body[..end].trim_end().to_string();
+        }
+    }
+    reply.trim().to_string()
+}
+
+/// Wrap generated tests in a `#[cfg(test)]` module
+pub fn wrap_test_module(module_name: &str, code: &str) -> String {
+    let mut module = format!("#[cfg(test)]\nmod {} {{\n    use super::*;\n", module_name);
+    for line in code.lines() {
+        if line.trim() == "use super::*;" {
+            continue;
+        }
+        if line.is_empty() {
+            module.push('\n');
+        } else {
+            module.push_str(&format!("    {}\n", line));
+        }
+    }
+    module.push_str("}\n");
+    module
+}
+
+/// Append a test module to a source file's content
+pub fn append_module(content: &str, module: &str) -> String {
+    format!("{}\n\n{}", content.trim_end(), module)
+}
+
+/// Parse the `test <name> ... ok` lines printed by `cargo test`
+pub fn parse_test_results(output: &str) -> Vec<TestOutcome> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("test ")?;
+            let (name, status) = rest.rsplit_once(" ... ")?;
+            let passed = match status.trim() {
+                "ok" => true,
+                s if s.starts_with("FAILED") => false,
+                _ => return None,
+            };
+            Some(TestOutcome { name: name.to_string(), passed })
+        })
+        .collect()
+}
+
+/// Run `cargo test` in a crate with the given filter arguments
+pub async fn run_cargo_test(crate_root: &Path, args: &[String], timeout: Duration) -> Result<Vec<TestOutcome>> {
+    info!("Running cargo test {} in {}", args.join(" "), crate_root.display());
+    let output = tokio::time::timeout(
+        timeout,
+        Command::new("cargo")
+            .arg("test")
+            .args(args)
+            .current_dir(crate_root)
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| AppError::unknown(format!("cargo test timed out after {} seconds", timeout.as_secs())))?
+    .with_context(|| "Failed to run cargo test")?;
+
+    let results = parse_test_results(&String::from_utf8_lossy(&output.stdout));
+    if results.is_empty() && !output.status.success() {
+        return Err(AppError::unknown(format!(
+            "cargo test failed before running tests:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(results)
+}
@@ -0,0 +1,83 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A contiguous range of lines from a source file (1-based, inclusive)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Maximum indentation (in columns) at which a definition starts a new chunk
+const MAX_BOUNDARY_INDENT: usize = 4;
+
+fn boundary_regex() -> &'static Regex {
+    static BOUNDARY: OnceLock<Regex> = OnceLock::new();
+    BOUNDARY.get_or_init(|| {
+        Regex::new(
+            r"^(pub(\([^)]*\))?\s+)?(export\s+)?(default\s+)?(async\s+)?(unsafe\s+)?(fn|impl|struct|enum|trait|mod|def|class|function|func|interface)\b"
+        ).expect("Invalid boundary regex")
+    })
+}
+
+/// Whether a line starts a function or type definition
+fn is_boundary(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    indent <= MAX_BOUNDARY_INDENT && boundary_regex().is_match(trimmed)
+}
+
+/// Split source text into chunks on function/type boundaries, falling back to
+/// fixed windows of `max_lines` for long definitions or unstructured files.
+///
+/// Comment and attribute lines directly above a definition stay with it.
+pub fn chunk_source(content: &str, max_lines: usize) -> Vec<Chunk> {
+    let max_lines = max_lines.max(1);
+    let lines: Vec<&str> = content.lines().collect();
+
+    // Find the line index where each segment starts
+    let mut starts = vec![0];
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 && is_boundary(line) {
+            // Pull preceding doc comments and attributes into the segment
+            let mut start = i;
+            while start > 0 && is_leading_decoration(lines[start - 1]) {
+                start -= 1;
+            }
+            if start > *starts.last().unwrap_or(&0) {
+                starts.push(start);
+            }
+        }
+    }
+
+    let mut chunks = Vec::new();
+    for (n, &start) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+        for window_start in (start..end).step_by(max_lines) {
+            let window_end = (window_start + max_lines).min(end);
+            let text = lines[window_start..window_end].join("\n");
+            if text.trim().is_empty() {
+                continue;
+            }
+            chunks.push(Chunk {
+                start_line: window_start + 1,
+                end_line: window_end,
+                text,
+            });
+        }
+    }
+
+    chunks
+}
+
+fn is_leading_decoration(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("///")
+        || trimmed.starts_with("//!")
+        || trimmed.starts_with("#[")
+        || trimmed.starts_with('@')
+        || trimmed.starts_with("/**")
+        || trimmed.starts_with("* ")
+        || trimmed.starts_with("*/")
+}
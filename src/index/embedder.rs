@@ -0,0 +1,32 @@
+use crate::api::EmbeddingsClient;
+use crate::error::Result;
+use async_trait::async_trait;
+
+/// Source of embedding vectors for the code index.
+///
+/// Implemented by [`EmbeddingsClient`]; tests can plug in a deterministic fake.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed the inputs, returning one vector per input in the same order
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+#[async_trait]
+impl Embedder for EmbeddingsClient {
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        EmbeddingsClient::embed(self, inputs).await
+    }
+}
+
+/// Cosine similarity between two vectors, or 0.0 if either has zero length
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
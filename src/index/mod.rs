@@ -0,0 +1,259 @@
+mod chunker;
+mod embedder;
+
+pub use chunker::{Chunk, chunk_source};
+pub use embedder::{Embedder, cosine_similarity};
+
+use crate::error::{AppError, Result, ErrorExt};
+use ignore::WalkBuilder;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Version of the on-disk index layout
+pub const INDEX_VERSION: u32 = 1;
+
+/// Files larger than this are skipped when indexing
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// An embedded chunk of a source file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexedChunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub vector: Vec<f32>,
+}
+
+/// All chunks of a file along with the hash of the content they came from
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexedFile {
+    pub hash: String,
+    pub chunks: Vec<IndexedChunk>,
+}
+
+/// Counts of the work done by a build or update
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IndexStats {
+    pub files_embedded: usize,
+    pub files_unchanged: usize,
+    pub files_removed: usize,
+    pub chunks_embedded: usize,
+}
+
+/// A ranked search result
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f32,
+    pub text: String,
+}
+
+/// Semantic index of a source tree, keyed by path relative to the root
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CodeIndex {
+    pub version: u32,
+    pub chunk_lines: usize,
+    pub files: BTreeMap<String, IndexedFile>,
+}
+
+impl CodeIndex {
+    pub fn new(chunk_lines: usize) -> Self {
+        Self {
+            version: INDEX_VERSION,
+            chunk_lines,
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read index {}", path.display()))?;
+        let index: CodeIndex = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse index {}", path.display()))?;
+
+        if index.version != INDEX_VERSION {
+            return Err(AppError::unknown(format!(
+                "Index {} has version {}, expected {}; rebuild it with `index build`",
+                path.display(), index.version, INDEX_VERSION
+            )));
+        }
+        Ok(index)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = serde_json::to_string(self)?;
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write index {}", path.display()))?;
+        info!("Index saved to {}", path.display());
+        Ok(())
+    }
+
+    /// Build a fresh index of every source file under `root`
+    pub async fn build(root: &Path, embedder: &dyn Embedder, chunk_lines: usize, index_file_name: &str) -> Result<(Self, IndexStats)> {
+        let mut index = Self::new(chunk_lines);
+        let stats = index.update(root, embedder, index_file_name).await?;
+        Ok((index, stats))
+    }
+
+    /// Re-embed files whose content hash changed and drop files that no longer exist
+    pub async fn update(&mut self, root: &Path, embedder: &dyn Embedder, index_file_name: &str) -> Result<IndexStats> {
+        let mut stats = IndexStats::default();
+        let mut present = BTreeSet::new();
+        let mut changed = Vec::new();
+
+        for (relative, content) in collect_source_files(root, index_file_name)? {
+            let hash = content_hash(&content);
+            let unchanged = self.files.get(&relative).map(|f| f.hash == hash).unwrap_or(false);
+            if unchanged {
+                stats.files_unchanged += 1;
+            } else {
+                changed.push((relative.clone(), hash, content));
+            }
+            present.insert(relative);
+        }
+
+        // Remove files that disappeared from the tree
+        let removed: Vec<String> = self.files.keys().filter(|k| !present.contains(*k)).cloned().collect();
+        for path in removed {
+            debug!("Removing {} from index", path);
+            self.files.remove(&path);
+            stats.files_removed += 1;
+        }
+
+        // Chunk every changed file and embed all chunks together
+        let pending: Vec<(String, String, Vec<Chunk>)> = changed
+            .into_iter()
+            .map(|(path, hash, content)| {
+                let chunks = chunk_source(&content, self.chunk_lines);
+                (path, hash, chunks)
+            })
+            .collect();
+
+        let texts: Vec<String> = pending
+            .iter()
+            .flat_map(|(path, _, chunks)| chunks.iter().map(move |c| format!("{}\n{}", path, c.text)))
+            .collect();
+
+        let vectors = if texts.is_empty() {
+            Vec::new()
+        } else {
+            embedder.embed(&texts).await?
+        };
+        if vectors.len() != texts.len() {
+            return Err(AppError::api(format!("Expected {} embeddings but received {}", texts.len(), vectors.len())));
+        }
+
+        let mut vectors_iter = vectors.into_iter();
+        for (path, hash, chunks) in pending {
+            let chunks = chunks
+                .into_iter()
+                .zip(vectors_iter.by_ref())
+                .map(|(chunk, vector)| IndexedChunk {
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    text: chunk.text,
+                    vector,
+                })
+                .collect::<Vec<_>>();
+
+            stats.files_embedded += 1;
+            stats.chunks_embedded += chunks.len();
+            self.files.insert(path, IndexedFile { hash, chunks });
+        }
+
+        Ok(stats)
+    }
+
+    /// Rank all chunks by cosine similarity to the query vector
+    pub fn search_vector(&self, query: &[f32], top_k: usize) -> Vec<SearchHit> {
+        let mut hits: Vec<SearchHit> = self
+            .files
+            .iter()
+            .flat_map(|(path, file)| {
+                file.chunks.iter().map(move |chunk| SearchHit {
+                    path: path.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score: cosine_similarity(query, &chunk.vector),
+                    text: chunk.text.clone(),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top_k);
+        hits
+    }
+
+    /// Embed the query text and rank all chunks against it
+    pub async fn search(&self, embedder: &dyn Embedder, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+        let vectors = embedder.embed(&[query.to_string()]).await?;
+        let query_vector = vectors
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::api("No embedding returned for the search query"))?;
+        Ok(self.search_vector(&query_vector, top_k))
+    }
+}
+
+/// Path of the index file for a source tree
+pub fn index_path(root: &Path, index_file_name: &str) -> PathBuf {
+    root.join(index_file_name)
+}
+
+/// SHA-256 of file content as a hex string
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Collect text files under `root`, respecting `.gitignore` and skipping
+/// hidden, binary and oversized files. Paths are relative with `/` separators.
+pub fn collect_source_files(root: &Path, index_file_name: &str) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    for entry in walker {
+        let entry = entry.map_err(|e| AppError::unknown(format!("Failed to walk {}: {}", root.display(), e)))?;
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.file_name().map(|n| n == index_file_name).unwrap_or(false) {
+            continue;
+        }
+        if entry.metadata().map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+            debug!("Skipping large file {}", path.display());
+            continue;
+        }
+
+        // Binary and non-UTF-8 files are skipped
+        let content = match fs::read_to_string(path) {
+            Ok(content) if !content.contains('\0') => content,
+            _ => continue,
+        };
+
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((relative, content));
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,132 @@
+use crate::error::{AppError, Result, ErrorExt};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Argument value standing for standard input
+pub const STDIN_ARG: &str = "-";
+
+/// Input that is read at most once, however many places ask for it
+///
+/// A message given as `-` and a template using `{{stdin}}` both see the same text.
+pub struct LazyInput<R: Read> {
+    reader: Option<R>,
+    content: Option<String>,
+}
+
+impl<R: Read> LazyInput<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader: Some(reader), content: None }
+    }
+
+    /// Read the whole input on first use and return it
+    pub fn read(&mut self) -> Result<String> {
+        if let Some(mut reader) = self.reader.take() {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)
+                .with_context(|| "Failed to read from stdin")?;
+            self.content = Some(content);
+        }
+        Ok(self.content.clone().unwrap_or_default())
+    }
+
+    /// Resolve an argument that may be `-`, reading the input in that case
+    pub fn resolve(&mut self, arg: &str) -> Result<String> {
+        if arg == STDIN_ARG {
+            self.read()
+        } else {
+            Ok(arg.to_string())
+        }
+    }
+}
+
+/// Read a message from a file, or from the input when the path is `-`
+pub fn read_message_file<R: Read>(path: &str, input: &mut LazyInput<R>) -> Result<String> {
+    if path == STDIN_ARG {
+        return input.read();
+    }
+    fs::read_to_string(path).with_context(|| format!("Failed to read message file {}", path))
+}
+
+/// A file attached to a chat message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub path: String,
+    pub language: &'static str,
+    pub content: String,
+}
+
+impl Attachment {
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).is_file() {
+            return Err(AppError::cli_usage(format!("Attachment {} is not a file", path)));
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read attachment {} (only UTF-8 text files can be attached)", path))?;
+        Ok(Self { path: path.to_string(), language: language_for(path), content })
+    }
+
+    /// Render as the path followed by a fenced code block
+    pub fn to_markdown(&self) -> String {
+        // Use a longer fence when the file itself contains one
+        let mut fence = "```".to_string();
+        while self.content.contains(&fence) {
+            fence.push('`');
+        }
+        format!("{}:\n{}{}\n{}\n{}", self.path, fence, self.language, self.content.trim_end_matches('\n'), fence)
+    }
+}
+
+/// Code fence language for a file, based on its name and extension
+pub fn language_for(path: &str) -> &'static str {
+    let path = Path::new(path);
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match file_name {
+        "Dockerfile" => return "dockerfile",
+        "Makefile" => return "makefile",
+        _ => {},
+    }
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "sh" | "bash" => "bash",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        "diff" | "patch" => "diff",
+        "lua" => "lua",
+        "hs" => "haskell",
+        _ => "",
+    }
+}
+
+/// Append attachments to a message, each as a fenced block
+pub fn with_attachments(message: &str, attachments: &[Attachment]) -> String {
+    let mut message = message.trim_end().to_string();
+    for attachment in attachments {
+        if !message.is_empty() {
+            message.push_str("\n\n");
+        }
+        message.push_str(&attachment.to_markdown());
+    }
+    message
+}
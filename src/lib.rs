@@ -1,11 +1,31 @@
 pub mod api;
 pub mod cli;
+pub mod commit_msg;
+pub mod compare;
 pub mod config;
+pub mod context;
+pub mod daemon;
+pub mod embeddings;
 pub mod error;
+pub mod eval;
+pub mod explain;
+pub mod fim;
+pub mod gen_docs;
+pub mod gen_tests;
+pub mod index;
+pub mod input;
 pub mod logging;
+pub mod lsp;
+pub mod markdown;
 pub mod models;
+pub mod proxy;
+pub mod review;
+pub mod structured;
+pub mod templates;
+pub mod tools;
+pub mod watch;
 
 // Re-export commonly used items
-pub use config::Config;
+pub use config::{Config, Persona};
 pub use error::{AppError, Result, ErrorExt};
-pub use models::{ApiResponse, ChatMessage, ChatCompletionRequest, CompletionRequest};
+pub use models::{ApiResponse, ChatMessage, ChatCompletionRequest, CompletionRequest, EmbeddingRequest, SamplingParams, Tool, ToolCall};
@@ -5,10 +5,15 @@ use std::fs::{self, OpenOptions};
 use std::io::Write;
 use chrono::Local;
 use log::{info, error};
-use serde_json::json;
+use serde_json::{json, Value};
 
 // Log generation metrics to a log file
 pub fn log_generation(api_type: &str, response: &ApiResponse, config: &Config) -> Result<()> {
+    log_generation_with_metadata(api_type, response, &Value::Null, config)
+}
+
+// Log generation metrics along with extra fields from a JSON object
+pub fn log_generation_with_metadata(api_type: &str, response: &ApiResponse, metadata: &Value, config: &Config) -> Result<()> {
     let log_dir = config.get_log_directory_path();
     
     // Ensure log directory exists
@@ -30,7 +35,7 @@ pub fn log_generation(api_type: &str, response: &ApiResponse, config: &Config) -
     let timestamp = now.format("%Y-%m-%d %H:%M:%S").to_string();
     
     // JSON formatted log entry with all fields
-    let log_entry = json!({
+    let mut log_entry = json!({
         "completion_tokens": response.completion_tokens,
         "created": response.created,
         "finish_reason": response.finish_reason,
@@ -40,7 +45,20 @@ pub fn log_generation(api_type: &str, response: &ApiResponse, config: &Config) -
         "timestamp": timestamp,
         "total_tokens": response.total_tokens,
         "type": api_type.to_lowercase()
-    }).to_string();
+    });
+    
+    // Record how many choices were returned when more than one was requested
+    if response.choices.len() > 1 {
+        log_entry["choices"] = json!(response.choices.len());
+    }
+    
+    // Merge extra metadata fields into the entry
+    if let (Some(entry), Some(extra)) = (log_entry.as_object_mut(), metadata.as_object()) {
+        for (key, value) in extra {
+            entry.insert(key.clone(), value.clone());
+        }
+    }
+    let log_entry = log_entry.to_string();
     
     // Write to log file (append newline)
     file.write_all(format!("{}\n", log_entry).as_bytes())
@@ -87,10 +105,16 @@ pub fn write_generation_content(api_type: &str, response: &ApiResponse, user_inp
         output_entry.push_str(&format!("Input:\n{}\n\n", input));
     }
     
-    output_entry.push_str(&format!("Output:\n{}\n\n", content));
+    if response.choices.len() > 1 {
+        for (i, choice) in response.choices.iter().enumerate() {
+            output_entry.push_str(&format!("Output {}:\n{}\n\n", i + 1, choice));
+        }
+    } else {
+        output_entry.push_str(&format!("Output:\n{}\n\n", content));
+    }
     
     // Add metadata section to include all fields from the response
-    output_entry.push_str(&format!("Metadata:\n"));
+    output_entry.push_str("Metadata:\n");
     output_entry.push_str(&format!("ID: {}\n", response.id));
     output_entry.push_str(&format!("Model: {}\n", response.model));
     output_entry.push_str(&format!("Object: {}\n", response.object));
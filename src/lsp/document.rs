@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use tower_lsp::lsp_types::Position;
+
+/// Byte offset of an LSP position, whose character is counted in UTF-16
+/// code units. Positions past the end of a line or the text are clamped.
+pub fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line_end = text[line_start..].find('\n').map(|i| line_start + i).unwrap_or(text.len());
+    let mut units = 0;
+    for (i, c) in text[line_start..line_end].char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_end
+}
+
+/// LSP position of a byte offset
+pub fn position_at(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Position::new(line as u32, character as u32)
+}
+
+/// FIM prefix and suffix around a byte offset, limited to roughly
+/// `prefix_chars` and `suffix_chars` bytes and cut at line boundaries
+pub fn fim_parts(text: &str, offset: usize, prefix_chars: usize, suffix_chars: usize) -> (String, String) {
+    let (before, after) = text.split_at(offset);
+
+    let mut start = before.len().saturating_sub(prefix_chars);
+    if start > 0 {
+        while !before.is_char_boundary(start) {
+            start += 1;
+        }
+        // Start on a whole line when the prefix is truncated
+        start = before[start..].find('\n').map(|i| start + i + 1).unwrap_or(start);
+    }
+
+    let mut end = suffix_chars.min(after.len());
+    if end < after.len() {
+        while !after.is_char_boundary(end) {
+            end -= 1;
+        }
+        end = after[..end].rfind('\n').map(|i| i + 1).unwrap_or(end);
+    }
+
+    (before[start..].to_string(), after[..end].to_string())
+}
+
+/// Identifier characters immediately before a byte offset
+pub fn word_before(text: &str, offset: usize) -> &str {
+    let before = &text[..offset];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    &before[start..]
+}
+
+/// Recent completions for one document
+#[derive(Debug, Clone)]
+pub struct CompletionCache {
+    entries: VecDeque<(String, String, String)>,
+    capacity: usize,
+}
+
+impl CompletionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: VecDeque::new(), capacity }
+    }
+
+    /// Look up a completion for a prefix and suffix. Besides exact matches,
+    /// a cached completion is reused when the user has since typed its start:
+    /// the rest of it is returned.
+    pub fn get(&self, prefix: &str, suffix: &str) -> Option<String> {
+        self.entries.iter().rev().find_map(|(cached_prefix, cached_suffix, completion)| {
+            if cached_suffix != suffix {
+                return None;
+            }
+            let typed = prefix.strip_prefix(cached_prefix.as_str())?;
+            let rest = completion.strip_prefix(typed)?;
+            (!rest.is_empty()).then(|| rest.to_string())
+        })
+    }
+
+    pub fn insert(&mut self, prefix: &str, suffix: &str, completion: &str) {
+        if self.capacity == 0 || completion.is_empty() {
+            return;
+        }
+        self.entries.retain(|(p, s, _)| p != prefix || s != suffix);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((prefix.to_string(), suffix.to_string(), completion.to_string()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,262 @@
+mod document;
+
+pub use document::{CompletionCache, fim_parts, offset_at, position_at, word_before};
+
+use crate::api::CodestralClient;
+use crate::config::Config;
+use crate::error::Result;
+use crate::eval::{CodestralProvider, CompletionProvider};
+use crate::fim::{PostProcessOptions, postprocess};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tower_lsp::jsonrpc;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+/// Method name of inline completion requests, from LSP 3.18
+pub const INLINE_COMPLETION_METHOD: &str = "textDocument/inlineCompletion";
+
+/// Settings of the language server, taken from the configuration
+#[derive(Debug, Clone)]
+pub struct LspOptions {
+    /// How long to wait for more typing before sending a request
+    pub debounce: Duration,
+    pub prefix_chars: usize,
+    pub suffix_chars: usize,
+    pub max_tokens: u32,
+    /// Completions remembered per open document
+    pub cache_size: usize,
+    pub seed: u64,
+    pub postprocess: PostProcessOptions,
+}
+
+impl LspOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            debounce: Duration::from_millis(config.lsp_debounce_ms),
+            prefix_chars: config.lsp_prefix_chars,
+            suffix_chars: config.lsp_suffix_chars,
+            max_tokens: config.lsp_max_tokens,
+            cache_size: config.lsp_cache_size,
+            seed: config.random_seed.unwrap_or(0),
+            postprocess: PostProcessOptions::from_config(config),
+        }
+    }
+}
+
+/// Parameters of `textDocument/inlineCompletion`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCompletionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCompletionItem {
+    pub insert_text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct InlineCompletionList {
+    pub items: Vec<InlineCompletionItem>,
+}
+
+/// An open document
+struct Document {
+    text: String,
+    /// Bumped by every edit and completion request, so a debounced request
+    /// can tell it has been superseded
+    generation: u64,
+    cache: CompletionCache,
+}
+
+/// A completion for the text of a document at a byte offset
+struct Suggestion {
+    text: String,
+    offset: usize,
+    completion: String,
+}
+
+/// Language server answering completion requests with FIM completions
+pub struct Backend {
+    client: Client,
+    provider: Arc<dyn CompletionProvider>,
+    options: LspOptions,
+    documents: Mutex<HashMap<Url, Document>>,
+}
+
+impl Backend {
+    pub fn new(client: Client, provider: Arc<dyn CompletionProvider>, options: LspOptions) -> Self {
+        Self { client, provider, options, documents: Mutex::new(HashMap::new()) }
+    }
+
+    /// Handle `textDocument/inlineCompletion`
+    pub async fn inline_completion(&self, params: InlineCompletionParams) -> jsonrpc::Result<Option<InlineCompletionList>> {
+        let Some(suggestion) = self.suggest(&params.text_document.uri, params.position).await else {
+            return Ok(None);
+        };
+        let position = position_at(&suggestion.text, suggestion.offset);
+        Ok(Some(InlineCompletionList {
+            items: vec![InlineCompletionItem {
+                insert_text: suggestion.completion,
+                range: Some(Range::new(position, position)),
+            }],
+        }))
+    }
+
+    /// Complete a document at a position, from the cache if possible. Returns
+    /// `None` when the request is superseded by an edit or a newer request
+    /// while it waits or runs.
+    async fn suggest(&self, uri: &Url, position: Position) -> Option<Suggestion> {
+        let (text, offset, prefix, suffix, generation) = {
+            let mut documents = self.documents.lock().unwrap();
+            let document = documents.get_mut(uri)?;
+            let offset = offset_at(&document.text, position);
+            let (prefix, suffix) = fim_parts(&document.text, offset, self.options.prefix_chars, self.options.suffix_chars);
+            if let Some(completion) = document.cache.get(&prefix, &suffix) {
+                debug!("Completion cache hit for {}", uri);
+                return Some(Suggestion { text: document.text.clone(), offset, completion });
+            }
+            document.generation += 1;
+            (document.text.clone(), offset, prefix, suffix, document.generation)
+        };
+
+        tokio::time::sleep(self.options.debounce).await;
+        if !self.is_current(uri, generation) {
+            debug!("Dropping superseded completion request for {}", uri);
+            return None;
+        }
+
+        let completion = match self.provider.complete(&prefix, &suffix, self.options.max_tokens, self.options.seed).await {
+            Ok(completion) => completion,
+            Err(e) => {
+                self.client.log_message(MessageType::ERROR, format!("Completion failed: {}", e)).await;
+                return None;
+            },
+        };
+        let completion = postprocess(&completion.text, &prefix, &suffix, &self.options.postprocess);
+
+        let mut documents = self.documents.lock().unwrap();
+        let document = documents.get_mut(uri)?;
+        document.cache.insert(&prefix, &suffix, &completion);
+        // The answer stays cached for when the editor asks again after the edit
+        if document.generation != generation || completion.is_empty() {
+            return None;
+        }
+        Some(Suggestion { text, offset, completion })
+    }
+
+    fn is_current(&self, uri: &Url, generation: u64) -> bool {
+        self.documents.lock().unwrap().get(uri).is_some_and(|d| d.generation == generation)
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, _params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+                completion_provider: Some(CompletionOptions::default()),
+                experimental: Some(json!({ "inlineCompletionProvider": {} })),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _params: InitializedParams) {
+        info!("Language server initialized with provider '{}'", self.provider.name());
+    }
+
+    async fn shutdown(&self) -> jsonrpc::Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = Document {
+            text: params.text_document.text,
+            generation: 0,
+            cache: CompletionCache::new(self.options.cache_size),
+        };
+        self.documents.lock().unwrap().insert(params.text_document.uri, document);
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // Full sync: the last change holds the whole text
+        let Some(change) = params.content_changes.into_iter().last() else {
+            return;
+        };
+        if let Some(document) = self.documents.lock().unwrap().get_mut(&params.text_document.uri) {
+            document.text = change.text;
+            document.generation += 1;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.documents.lock().unwrap().remove(&params.text_document.uri);
+    }
+
+    async fn completion(&self, params: CompletionParams) -> jsonrpc::Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let Some(suggestion) = self.suggest(&position.text_document.uri, position.position).await else {
+            return Ok(None);
+        };
+
+        // Replace the word being typed so editors filter the item against it
+        let word = word_before(&suggestion.text, suggestion.offset);
+        let start = position_at(&suggestion.text, suggestion.offset - word.len());
+        let end = position_at(&suggestion.text, suggestion.offset);
+        let new_text = format!("{}{}", word, suggestion.completion);
+        let label = new_text.lines().next().unwrap_or_default().trim().to_string();
+
+        Ok(Some(CompletionResponse::Array(vec![CompletionItem {
+            label,
+            kind: Some(CompletionItemKind::TEXT),
+            detail: Some(self.provider.name()),
+            filter_text: Some(word.to_string()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(Range::new(start, end), new_text))),
+            ..Default::default()
+        }])))
+    }
+}
+
+/// Build the language service for a provider
+pub fn service(provider: Arc<dyn CompletionProvider>, options: LspOptions) -> (LspService<Backend>, tower_lsp::ClientSocket) {
+    LspService::build(|client| Backend::new(client, provider, options))
+        .custom_method(INLINE_COMPLETION_METHOD, Backend::inline_completion)
+        .finish()
+}
+
+/// Serve the language server over a pair of streams until the client exits
+pub async fn serve<I, O>(input: I, output: O, provider: Arc<dyn CompletionProvider>, options: LspOptions)
+where
+    I: AsyncRead + Unpin,
+    O: AsyncWrite,
+{
+    let (service, socket) = service(provider, options);
+    Server::new(input, output, socket).serve(service).await;
+}
+
+/// Serve the language server over stdin and stdout, completing with the
+/// configured code model
+pub async fn serve_stdio(config: &Config) -> Result<()> {
+    let client = CodestralClient::new(config.clone())?;
+    let provider: Arc<dyn CompletionProvider> = Arc::new(CodestralProvider::new(&config.code_model, client));
+    serve(tokio::io::stdin(), tokio::io::stdout(), provider, LspOptions::from_config(config)).await;
+    Ok(())
+}
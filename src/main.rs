@@ -1,5 +1,5 @@
 use codestral_test_rs::{
-    cli::{Cli, Commands, handle_code_completion, handle_chat, handle_config}, 
+    cli::{Cli, Commands, handle_code_completion, handle_chat, handle_embed, handle_index, handle_search, handle_compare, handle_eval, handle_review, handle_commit_msg, handle_gen_tests, handle_gen_docs, handle_explain, handle_serve_lsp, handle_serve, handle_daemon, handle_watch, handle_template, handle_persona, handle_config, handle_models, warn_on_cached_models}, 
     Config, 
     error::{Result}
 };
@@ -27,17 +27,69 @@ async fn main() -> Result<()> {
     let config_path = cli.config.as_deref().unwrap_or("config.toml");
     let config = Config::load_from_file(config_path)?;
     info!("Loaded configuration with code model '{}' and chat model '{}'", config.code_model, config.chat_model);
+    warn_on_cached_models(&config);
     
     // Handle subcommands
     match &cli.command {
-        Commands::CodeCompletion { prompt, suffix, max_tokens } => {
-            handle_code_completion(prompt, suffix, *max_tokens, &config).await?
+        Commands::CodeCompletion { prompt, suffix, max_tokens, options } => {
+            handle_code_completion(prompt, suffix, *max_tokens, options, &config).await?
         },
-        Commands::Chat { message, max_tokens } => {
-            handle_chat(message, *max_tokens, &config).await?
+        Commands::Chat { message, max_tokens, options } => {
+            handle_chat(message.as_deref(), *max_tokens, options, &config).await?
+        },
+        Commands::Embed { inputs, files, lines, format, output } => {
+            handle_embed(inputs, files, *lines, *format, output.as_deref(), &config).await?
+        },
+        Commands::Index { action } => {
+            handle_index(action, &config).await?
+        },
+        Commands::Search { query, dir, top_k } => {
+            handle_search(query, dir, *top_k, &config).await?
+        },
+        Commands::Compare { prompt, models, suffix, max_tokens, format, output } => {
+            handle_compare(prompt, models, suffix.as_deref(), *max_tokens, *format, output.as_deref(), &config).await?
+        },
+        Commands::Eval { dataset, samples, models, mock, max_tokens, output } => {
+            handle_eval(dataset, *samples, models, *mock, *max_tokens, output.as_deref(), &config).await?
+        },
+        Commands::Review { diff, format, output, max_tokens, persona } => {
+            handle_review(diff, *format, output.as_deref(), *max_tokens, persona, &config).await?
+        },
+        Commands::CommitMsg { install_hook, force, output, repo, max_tokens } => {
+            handle_commit_msg(*install_hook, *force, output.as_deref(), repo, *max_tokens, config_path, &config).await?
+        },
+        Commands::GenTests { file, function, integration, run, max_tokens } => {
+            handle_gen_tests(file, function, *integration, *run, *max_tokens, &config).await?
+        },
+        Commands::GenDocs { path, check, write, max_tokens } => {
+            handle_gen_docs(path, *check, *write, *max_tokens, &config).await?
+        },
+        Commands::Explain { target, depth, raw, max_tokens, persona } => {
+            handle_explain(target, *depth, *raw, *max_tokens, persona, &config).await?
+        },
+        Commands::ServeLsp { debounce_ms, max_tokens } => {
+            handle_serve_lsp(*debounce_ms, *max_tokens, &config).await?
+        },
+        Commands::Serve { bind, budget, allow_remote } => {
+            handle_serve(bind.as_deref(), *budget, *allow_remote, &config).await?
+        },
+        Commands::Daemon { action } => {
+            handle_daemon(action, cli.config.as_deref(), &config).await?
+        },
+        Commands::Watch { dir, sidecar, dry_run, ignore, marker, max_tokens } => {
+            handle_watch(dir, *sidecar, *dry_run, ignore, marker, *max_tokens, &config).await?
+        },
+        Commands::Template { action } => {
+            handle_template(action, &config)?
+        },
+        Commands::Persona { action } => {
+            handle_persona(action, &config)?
         },
         Commands::Config { action } => {
-            handle_config(action, &config)?
+            handle_config(action, &config).await?
+        },
+        Commands::Models { action } => {
+            handle_models(action, &config).await?
         },
     }
 
@@ -0,0 +1,121 @@
+use console::Style;
+
+/// Width of horizontal rules and code block borders
+const RULE_WIDTH: usize = 40;
+
+struct Styles {
+    heading: Style,
+    code: Style,
+    bold: Style,
+    italic: Style,
+    dim: Style,
+}
+
+impl Styles {
+    fn new(styled: bool) -> Self {
+        Self {
+            heading: Style::new().bold().underlined().force_styling(styled),
+            code: Style::new().cyan().force_styling(styled),
+            bold: Style::new().bold().force_styling(styled),
+            italic: Style::new().italic().force_styling(styled),
+            dim: Style::new().dim().force_styling(styled),
+        }
+    }
+}
+
+/// Render Markdown for the terminal: headings, lists, quotes, rules, code
+/// blocks and inline code, bold and italic text. With `styled` false the
+/// layout is kept but no escape codes are written.
+pub fn render_terminal(markdown: &str, styled: bool) -> String {
+    let styles = Styles::new(styled);
+    let mut output = String::new();
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+
+        if let Some(language) = trimmed.strip_prefix("```") {
+            let border = if in_code || language.trim().is_empty() {
+                "─".repeat(RULE_WIDTH)
+            } else {
+                format!("── {} {}", language.trim(), "─".repeat(RULE_WIDTH.saturating_sub(language.trim().len() + 4)))
+            };
+            output.push_str(&format!("{}\n", styles.dim.apply_to(border)));
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            output.push_str(&format!("  {}\n", styles.code.apply_to(line)));
+            continue;
+        }
+
+        let rendered = if let Some(heading) = heading_text(trimmed) {
+            styles.heading.apply_to(render_inline(heading, &styles, false)).to_string()
+        } else if is_rule(trimmed) {
+            styles.dim.apply_to("─".repeat(RULE_WIDTH)).to_string()
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            format!("{} {}", styles.dim.apply_to("│"), render_inline(quote.trim_start(), &styles, true))
+        } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            let indent = &line[..line.len() - trimmed.len()];
+            format!("{}  • {}", indent, render_inline(item, &styles, true))
+        } else {
+            render_inline(line, &styles, true)
+        };
+        output.push_str(&rendered);
+        output.push('\n');
+    }
+
+    output
+}
+
+fn heading_text(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) {
+        line[level..].strip_prefix(' ').map(str::trim)
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && ['-', '*', '_'].iter().any(|&c| line.chars().all(|ch| ch == c))
+}
+
+/// Style inline code, `**bold**` and `*italic*` spans
+fn render_inline(text: &str, styles: &Styles, emphasis: bool) -> String {
+    let mut output = String::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('`') {
+            if let Some(end) = after.find('`') {
+                output.push_str(&styles.code.apply_to(&after[..end]).to_string());
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        if emphasis {
+            if let Some(after) = rest.strip_prefix("**") {
+                if let Some(end) = after.find("**").filter(|&e| e > 0) {
+                    output.push_str(&styles.bold.apply_to(render_inline(&after[..end], styles, false)).to_string());
+                    rest = &after[end + 2..];
+                    continue;
+                }
+            }
+            if let Some(after) = rest.strip_prefix('*') {
+                if let Some(end) = after.find('*').filter(|&e| e > 0 && !after.starts_with(' ')) {
+                    output.push_str(&styles.italic.apply_to(&after[..end]).to_string());
+                    rest = &after[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        let next = rest.char_indices().skip(1).find(|&(_, c)| c == '`' || c == '*').map(|(i, _)| i).unwrap_or(rest.len());
+        output.push_str(&rest[..next]);
+        rest = &rest[next..];
+    }
+
+    output
+}
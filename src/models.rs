@@ -1,29 +1,192 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
-#[derive(Serialize, Deserialize, Clone)]
+fn default_tool_type() -> String {
+    "function".to_string()
+}
+
+// Assistant messages carrying tool calls may have a null content
+fn null_as_empty<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Tool call arguments may arrive as a JSON string or as an object
+fn arguments_as_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => Ok(s),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Optional sampling parameters shared by completion and chat requests.
+/// Unset values are left out of the request body.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SamplingParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+}
+
+impl SamplingParams {
+    /// Combine with another set of parameters, whose values take precedence
+    pub fn merge(&self, overrides: &SamplingParams) -> SamplingParams {
+        SamplingParams {
+            top_p: overrides.top_p.or(self.top_p),
+            stop: overrides.stop.clone().or_else(|| self.stop.clone()),
+            random_seed: overrides.random_seed.or(self.random_seed),
+            min_tokens: overrides.min_tokens.or(self.min_tokens),
+            presence_penalty: overrides.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: overrides.frequency_penalty.or(self.frequency_penalty),
+            n: overrides.n.or(self.n),
+            safe_prompt: overrides.safe_prompt.or(self.safe_prompt),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct CompletionRequest {
     pub model: String,
     pub prompt: String,
     pub suffix: String,
     pub max_tokens: u32,
     pub temperature: f64,
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct ChatMessage {
     pub role: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl ChatMessage {
+    pub fn system<S: Into<String>>(content: S) -> Self {
+        Self { role: "system".to_string(), content: content.into(), ..Default::default() }
+    }
+    
+    pub fn user<S: Into<String>>(content: S) -> Self {
+        Self { role: "user".to_string(), content: content.into(), ..Default::default() }
+    }
+    
+    pub fn assistant<S: Into<String>>(content: S) -> Self {
+        Self { role: "assistant".to_string(), content: content.into(), ..Default::default() }
+    }
+    
+    /// Result of a tool call, answering the call with the given id
+    pub fn tool<S: Into<String>>(tool_call_id: &str, name: &str, content: S) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: content.into(),
+            tool_call_id: Some(tool_call_id.to_string()),
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Function the model may call, with its parameters as a JSON Schema
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    pub fn function(name: &str, description: &str, parameters: Value) -> Self {
+        Self {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: description.to_string(),
+                parameters,
+            },
+        }
+    }
+}
+
+/// Function name and JSON-encoded arguments chosen by the model
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(deserialize_with = "arguments_as_string")]
+    pub arguments: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ToolCall {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub call_type: String,
+    pub function: FunctionCall,
+}
+
+/// Output format constraint for chat responses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    pub format_type: String,
+}
+
+impl ResponseFormat {
+    /// Constrain the model to reply with a single JSON object
+    pub fn json_object() -> Self {
+        Self { format_type: "json_object".to_string() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub max_tokens: Option<u32>,
     pub temperature: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
 pub struct ApiResponse {
     pub id: String,
     pub model: String,
@@ -31,6 +194,29 @@ pub struct ApiResponse {
     pub created: i64,
     pub finish_reason: String,
     pub content: Option<String>,
+    /// Content of every choice, in order; `content` is the first of these
+    pub choices: Vec<String>,
     pub completion_tokens: i64,
     pub total_tokens: i64,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ModelCapabilities {
+    #[serde(default)]
+    pub completion_chat: bool,
+    #[serde(default)]
+    pub completion_fim: bool,
+    #[serde(default)]
+    pub function_calling: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub capabilities: ModelCapabilities,
+    #[serde(default)]
+    pub max_context_length: Option<u64>,
+    #[serde(default)]
+    pub deprecation: Option<String>,
+}
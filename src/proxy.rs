@@ -0,0 +1,378 @@
+use crate::api::extract_response_fields;
+use crate::config::Config;
+use crate::error::{AppError, Result, ErrorExt};
+use crate::logging::log_generation_with_metadata;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use log::{info, warn};
+use regex::Regex;
+use reqwest::{Client, header};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Replacement for redacted text
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Secrets redacted from every proxied request. Only formats that are
+/// unlikely to match ordinary code are included; a `keep` group in a pattern
+/// is kept in front of the replacement.
+const SECRET_PATTERNS: &[&str] = &[
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bsk-[A-Za-z0-9_-]{20,}\b",
+    r"\bxox[abpr]-[A-Za-z0-9-]{10,}\b",
+];
+
+/// Endpoints served by the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Chat,
+    Fim,
+}
+
+impl Endpoint {
+    pub fn path(self) -> &'static str {
+        match self {
+            Endpoint::Chat => "/v1/chat/completions",
+            Endpoint::Fim => "/v1/fim/completions",
+        }
+    }
+
+    /// Generation type written to the log
+    pub fn api_type(self) -> &'static str {
+        match self {
+            Endpoint::Chat => "ProxyChat",
+            Endpoint::Fim => "ProxyFim",
+        }
+    }
+}
+
+/// Replaces secrets in request text
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Built-in secret patterns plus `extra` regular expressions
+    pub fn new(extra: &[String]) -> Result<Self> {
+        let patterns = SECRET_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(extra.iter().cloned())
+            .map(|p| Regex::new(&p).map_err(|e| AppError::config(format!("Invalid redaction pattern '{}': {}", p, e))))
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Redact a string, returning the result and the number of replacements
+    pub fn redact(&self, text: &str) -> (String, usize) {
+        let mut text = text.to_string();
+        let mut count = 0;
+        for pattern in &self.patterns {
+            let found = pattern.find_iter(&text).count();
+            if found > 0 {
+                count += found;
+                text = pattern.replace_all(&text, format!("${{keep}}{}", REDACTED)).into_owned();
+            }
+        }
+        (text, count)
+    }
+
+    /// Redact every string in a JSON value, returning the number of replacements
+    pub fn redact_value(&self, value: &mut Value) -> usize {
+        match value {
+            Value::String(text) => {
+                let (redacted, count) = self.redact(text);
+                if count > 0 {
+                    *text = redacted;
+                }
+                count
+            },
+            Value::Array(items) => items.iter_mut().map(|item| self.redact_value(item)).sum(),
+            Value::Object(map) => map.values_mut().map(|item| self.redact_value(item)).sum(),
+            _ => 0,
+        }
+    }
+}
+
+/// Responses to recent identical requests, evicted oldest first
+pub struct ResponseCache {
+    entries: HashMap<String, Value>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), order: VecDeque::new(), capacity }
+    }
+
+    /// Key of a request body sent to an endpoint
+    pub fn key(endpoint: Endpoint, body: &Value) -> String {
+        let digest = Sha256::digest(format!("{}\n{}", endpoint.path(), body).as_bytes());
+        format!("{:x}", digest)
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.entries.get(key).cloned()
+    }
+
+    pub fn insert(&mut self, key: String, response: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), response).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Tokens spent by the proxy, against an optional limit
+pub struct TokenBudget {
+    limit: Option<u64>,
+    used: AtomicU64,
+}
+
+impl TokenBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self { limit, used: AtomicU64::new(0) }
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used() >= limit)
+    }
+
+    pub fn record(&self, tokens: u64) {
+        self.used.fetch_add(tokens, Ordering::SeqCst);
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+}
+
+/// Shared state of the proxy server
+pub struct ProxyState {
+    config: Config,
+    http: Client,
+    redactor: Redactor,
+    cache: Mutex<ResponseCache>,
+    budget: TokenBudget,
+    token: Option<String>,
+}
+
+impl ProxyState {
+    pub fn new(config: Config) -> Result<Self> {
+        Ok(Self {
+            http: Client::new(),
+            redactor: Redactor::new(&config.serve_redact_patterns)?,
+            cache: Mutex::new(ResponseCache::new(config.serve_cache_size)),
+            budget: TokenBudget::new(config.serve_token_budget),
+            token: None,
+            config,
+        })
+    }
+
+    /// Require `Authorization: Bearer <token>` on every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // Compare digests so the time taken does not depend on the token
+        Sha256::digest(given.as_bytes()) == Sha256::digest(token.as_bytes())
+    }
+
+    pub fn tokens_used(&self) -> u64 {
+        self.budget.used()
+    }
+
+    fn upstream(&self, endpoint: Endpoint) -> (&str, &str, &str) {
+        match endpoint {
+            Endpoint::Chat => (&self.config.chat_api_url, &self.config.chat_api_key_env, &self.config.chat_model),
+            Endpoint::Fim => (&self.config.code_api_url, &self.config.code_api_key_env, &self.config.code_model),
+        }
+    }
+
+    /// Forward a request to the configured provider
+    async fn forward(&self, endpoint: Endpoint, headers: &HeaderMap, mut body: Value) -> Response {
+        if !self.authorized(headers) {
+            return error_response(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token", "authentication_error");
+        }
+        if !body.is_object() {
+            return error_response(StatusCode::BAD_REQUEST, "Request body must be a JSON object", "invalid_request_error");
+        }
+        if body.get("stream").and_then(Value::as_bool) == Some(true) {
+            return error_response(StatusCode::BAD_REQUEST, "Streaming is not supported by this proxy", "invalid_request_error");
+        }
+
+        let (url, key_env, default_model) = self.upstream(endpoint);
+        if body.get("model").is_none_or(Value::is_null) {
+            body["model"] = json!(default_model);
+        }
+        let redactions = self.redactor.redact_value(&mut body);
+        if redactions > 0 {
+            info!("Redacted {} secrets from a {} request", redactions, endpoint.path());
+        }
+
+        let key = ResponseCache::key(endpoint, &body);
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return with_cache_header(Json(cached).into_response(), "hit");
+        }
+        if self.budget.exhausted() {
+            return error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                &format!("Token budget of {} exhausted", self.config.serve_token_budget.unwrap_or_default()),
+                "budget_exceeded",
+            );
+        }
+        let Ok(api_key) = env::var(key_env) else {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("Environment variable {} is not set on the proxy", key_env),
+                "server_error",
+            );
+        };
+
+        let (status, response, attempts) = match self.send_with_retries(url, &api_key, &body).await {
+            Ok(result) => result,
+            Err(e) => return error_response(StatusCode::BAD_GATEWAY, &e.to_string(), "upstream_error"),
+        };
+        if !status.is_success() {
+            return (status, Json(response)).into_response();
+        }
+
+        let fields = extract_response_fields(&response);
+        self.budget.record(fields.total_tokens.max(0) as u64);
+        self.cache.lock().unwrap().insert(key, response.clone());
+        let metadata = json!({ "proxy": true, "attempts": attempts, "redactions": redactions });
+        if let Err(e) = log_generation_with_metadata(endpoint.api_type(), &fields, &metadata, &self.config) {
+            warn!("Failed to log proxied generation: {}", e);
+        }
+        with_cache_header(Json(response).into_response(), "miss")
+    }
+
+    /// Send a request, retrying network errors, rate limits and server errors
+    /// with exponential backoff. Returns the final status, body and attempt count.
+    async fn send_with_retries(&self, url: &str, api_key: &str, body: &Value) -> Result<(StatusCode, Value, usize)> {
+        let max_attempts = self.config.serve_max_retries + 1;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = self
+                .http
+                .post(url)
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
+                .json(body)
+                .send()
+                .await;
+
+            let retryable = match result {
+                Ok(response) => {
+                    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+                    if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) || attempt == max_attempts {
+                        let text = response.text().await.with_context(|| format!("Failed to read response from {}", url))?;
+                        let body = serde_json::from_str(&text)
+                            .unwrap_or_else(|_| json!({ "error": { "message": text, "type": "upstream_error" } }));
+                        return Ok((status, body, attempt));
+                    }
+                    format!("status {}", status)
+                },
+                Err(e) if attempt == max_attempts => {
+                    return Err(AppError::api(format!("Failed to send request to {}: {}", url, e)));
+                },
+                Err(e) => e.to_string(),
+            };
+
+            let backoff = Duration::from_millis(self.config.serve_retry_backoff_ms << (attempt - 1).min(10));
+            warn!("Upstream request failed ({}), retrying in {:?}", retryable, backoff);
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: &str, kind: &str) -> Response {
+    (status, Json(json!({ "error": { "message": message, "type": kind } }))).into_response()
+}
+
+fn with_cache_header(mut response: Response, value: &'static str) -> Response {
+    response.headers_mut().insert("x-cache", header::HeaderValue::from_static(value));
+    response
+}
+
+async fn chat_completions(State(state): State<Arc<ProxyState>>, headers: HeaderMap, Json(body): Json<Value>) -> Response {
+    state.forward(Endpoint::Chat, &headers, body).await
+}
+
+async fn fim_completions(State(state): State<Arc<ProxyState>>, headers: HeaderMap, Json(body): Json<Value>) -> Response {
+    state.forward(Endpoint::Fim, &headers, body).await
+}
+
+/// Check that the proxy may listen on `bind`. Loopback addresses are always
+/// allowed; anything else only with `allow_remote`.
+pub async fn check_bind(bind: &str, allow_remote: bool) -> Result<()> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host(bind)
+        .await
+        .with_context(|| format!("Invalid address {}", bind))?
+        .collect();
+    if allow_remote || addresses.iter().all(|a| a.ip().is_loopback()) {
+        Ok(())
+    } else {
+        Err(AppError::cli_usage(format!(
+            "Refusing to listen on {}, which is reachable from other machines; pass --allow-remote to do so",
+            bind
+        )))
+    }
+}
+
+/// Routes of the proxy server
+pub fn router(state: Arc<ProxyState>) -> Router {
+    Router::new()
+        .route(Endpoint::Chat.path(), post(chat_completions))
+        .route(Endpoint::Fim.path(), post(fim_completions))
+        .with_state(state)
+}
+
+/// Serve the proxy on a bound listener until Ctrl-C
+pub async fn serve(listener: TcpListener, state: Arc<ProxyState>) -> Result<()> {
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .with_context(|| "Proxy server failed")
+}
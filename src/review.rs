@@ -0,0 +1,259 @@
+use crate::context::estimate_tokens;
+use crate::error::{AppError, Result, ErrorExt};
+use crate::input::language_for;
+use crate::tools::patch::{FilePatch, Hunk, HunkLine, parse_unified_diff};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+
+/// Rule id used for review findings in SARIF output
+pub const SARIF_RULE_ID: &str = "code-review";
+
+/// Instructions sent with every chunk of the diff
+pub const REVIEW_INSTRUCTIONS: &str = "Review the following change for bugs, security problems, \
+performance issues and unclear code. Lines are prefixed with their line number in the new version \
+of the file and `+` for added, `-` for removed or ` ` for unchanged lines. Only comment on added \
+lines, cite the new line number, and leave `findings` empty if there is nothing worth changing.";
+
+/// Which changes to review
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffSource {
+    /// Unstaged changes in the working tree
+    WorkingTree,
+    /// Changes staged for the next commit
+    Staged,
+    /// Changes between two revisions, e.g. `main..feature`
+    Range(String),
+}
+
+impl DiffSource {
+    fn git_args(&self) -> Vec<&str> {
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+        match self {
+            DiffSource::WorkingTree => {},
+            DiffSource::Staged => args.push("--cached"),
+            DiffSource::Range(range) => args.push(range),
+        }
+        args
+    }
+}
+
+/// Run `git diff` in a repository
+pub fn git_diff(repo: &Path, source: &DiffSource) -> Result<String> {
+    let output = Command::new("git")
+        .args(source.git_args())
+        .current_dir(repo)
+        .output()
+        .with_context(|| format!("Failed to run git in {}", repo.display()))?;
+
+    if !output.status.success() {
+        return Err(AppError::cli_usage(format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Part of a diff small enough to review in one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewChunk {
+    pub path: String,
+    pub language: &'static str,
+    /// Hunk lines annotated with their new line numbers
+    pub diff: String,
+}
+
+/// Split a unified diff into per-file chunks of at most `token_budget`
+/// estimated tokens. Deleted files are skipped, and hunks larger than the
+/// budget are split between lines. Diffs with no text changes, e.g. only
+/// binary files, give no chunks.
+pub fn split_diff(diff: &str, token_budget: usize) -> Result<Vec<ReviewChunk>> {
+    if !diff.lines().any(|l| l.starts_with("--- ")) {
+        return Ok(Vec::new());
+    }
+    let patches = parse_unified_diff(diff)?;
+
+    let mut chunks = Vec::new();
+    for patch in patches.iter().filter(|p| p.new_path.is_some()) {
+        chunks.extend(split_file(patch, token_budget));
+    }
+    Ok(chunks)
+}
+
+fn split_file(patch: &FilePatch, token_budget: usize) -> Vec<ReviewChunk> {
+    let path = patch.path().to_string();
+    let language = language_for(&path);
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for hunk in &patch.hunks {
+        for piece in annotate_hunk(hunk, token_budget) {
+            if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(&piece) > token_budget {
+                pieces.push(std::mem::take(&mut current));
+            }
+            current.push_str(&piece);
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+        .into_iter()
+        .map(|diff| ReviewChunk { path: path.clone(), language, diff })
+        .collect()
+}
+
+/// Render a hunk with line numbers, in pieces that each fit the budget
+fn annotate_hunk(hunk: &Hunk, token_budget: usize) -> Vec<String> {
+    const SEPARATOR: &str = "...\n";
+    let mut pieces = Vec::new();
+    let mut piece = String::from(SEPARATOR);
+    let mut new_line = hunk.new_start;
+
+    for line in &hunk.lines {
+        let rendered = match line {
+            HunkLine::Context(text) => {
+                new_line += 1;
+                format!("{:>5}   {}\n", new_line - 1, text)
+            },
+            HunkLine::Add(text) => {
+                new_line += 1;
+                format!("{:>5} + {}\n", new_line - 1, text)
+            },
+            HunkLine::Remove(text) => format!("{:>5} - {}\n", "", text),
+        };
+        if piece.len() > SEPARATOR.len() && estimate_tokens(&piece) + estimate_tokens(&rendered) > token_budget {
+            pieces.push(std::mem::replace(&mut piece, String::from(SEPARATOR)));
+        }
+        piece.push_str(&rendered);
+    }
+    pieces.push(piece);
+    pieces
+}
+
+/// Build the review request for a chunk
+pub fn review_prompt(chunk: &ReviewChunk) -> String {
+    format!("{}\n\nFile: {}\n```{}\n{}```", REVIEW_INSTRUCTIONS, chunk.path, chunk.language, chunk.diff)
+}
+
+/// Severity of a review finding
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    #[serde(alias = "note")]
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+    
+    /// SARIF result level
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "note",
+        }
+    }
+}
+
+/// A review comment on a line of a file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub file: String,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Findings returned by the model for one chunk
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ReviewResponse {
+    pub findings: Vec<Finding>,
+}
+
+/// JSON Schema the model's reply is validated against
+pub fn findings_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "findings": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "file": { "type": "string" },
+                        "line": { "type": "integer", "minimum": 1 },
+                        "severity": { "type": "string", "enum": ["error", "warning", "info"] },
+                        "message": { "type": "string" }
+                    },
+                    "required": ["file", "line", "severity", "message"]
+                }
+            }
+        },
+        "required": ["findings"]
+    })
+}
+
+/// Sort findings by file, line and severity
+pub fn sort_findings(findings: &mut [Finding]) {
+    findings.sort_by(|a, b| (&a.file, a.line, a.severity).cmp(&(&b.file, b.line, b.severity)));
+}
+
+/// Render findings as `file:line: severity: message` lines
+pub fn render_text(findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return "No findings.\n".to_string();
+    }
+    findings
+        .iter()
+        .map(|f| format!("{}:{}: {}: {}\n", f.file, f.line, f.severity.as_str(), f.message))
+        .collect()
+}
+
+/// Render findings as a SARIF 2.1.0 log for code scanning tools
+pub fn render_sarif(findings: &[Finding]) -> Value {
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|f| json!({
+            "ruleId": SARIF_RULE_ID,
+            "level": f.severity.sarif_level(),
+            "message": { "text": f.message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": f.file },
+                    "region": { "startLine": f.line.max(1) }
+                }
+            }]
+        }))
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [{
+                        "id": SARIF_RULE_ID,
+                        "shortDescription": { "text": "Model-assisted code review finding" }
+                    }]
+                }
+            },
+            "results": results
+        }]
+    })
+}
@@ -0,0 +1,60 @@
+use crate::error::{AppError, Result};
+use serde_json::Value;
+
+/// Parse a model reply as JSON, tolerating a surrounding Markdown code fence
+pub fn parse_json_content(content: &str) -> Result<Value> {
+    let trimmed = content.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+
+    serde_json::from_str(unfenced.trim())
+        .map_err(|e| AppError::api(format!("Response is not valid JSON: {}", e)))
+}
+
+/// Check that the schema itself is a valid JSON Schema
+pub fn check_schema(schema: &Value) -> Result<()> {
+    jsonschema::validator_for(schema)
+        .map(|_| ())
+        .map_err(|e| AppError::config(format!("Invalid JSON Schema: {}", e)))
+}
+
+/// Validate an instance against a JSON Schema, returning one message per violation
+pub fn schema_errors(schema: &Value, instance: &Value) -> Result<Vec<String>> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| AppError::config(format!("Invalid JSON Schema: {}", e)))?;
+
+    Ok(validator
+        .iter_errors(instance)
+        .map(|error| {
+            let path = error.instance_path.to_string();
+            if path.is_empty() {
+                error.to_string()
+            } else {
+                format!("{}: {}", path, error)
+            }
+        })
+        .collect())
+}
+
+/// System instruction asking for JSON output, optionally matching a schema
+pub fn json_instruction(schema: Option<&Value>) -> String {
+    match schema {
+        Some(schema) => format!(
+            "Respond only with a single JSON object that conforms to this JSON Schema:\n{}",
+            serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string())
+        ),
+        None => "Respond only with a single JSON object.".to_string(),
+    }
+}
+
+/// Follow-up message asking the model to fix output that failed validation
+pub fn correction_prompt(errors: &[String]) -> String {
+    let mut prompt = String::from("Your previous response was not valid. Fix these problems and reply with the corrected JSON only:\n");
+    for error in errors {
+        prompt.push_str(&format!("- {}\n", error));
+    }
+    prompt
+}
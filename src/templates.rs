@@ -0,0 +1,259 @@
+use crate::Config;
+use crate::error::{AppError, Result, ErrorExt};
+use log::{debug, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// A prompt template as written in `config.toml` or a `.toml` template file
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TemplateDef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub text: String,
+    /// Default values for variables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, String>,
+}
+
+/// Where a template was loaded from
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateSource {
+    Config,
+    File(PathBuf),
+}
+
+/// A named template ready to render
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub def: TemplateDef,
+    pub source: TemplateSource,
+}
+
+/// A `{{...}}` placeholder in template text
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placeholder {
+    /// `{{name}}` or `{{name|default}}`
+    Variable { name: String, default: Option<String> },
+    /// `{{file:path}}`, replaced with the file's content
+    File(String),
+    /// `{{stdin}}`, replaced with standard input
+    Stdin,
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\{\{\s*([^{}]+?)\s*\}\}").expect("valid placeholder regex"))
+}
+
+fn parse_placeholder(inner: &str) -> Placeholder {
+    if inner == "stdin" {
+        return Placeholder::Stdin;
+    }
+    if let Some(path) = inner.strip_prefix("file:") {
+        return Placeholder::File(path.trim().to_string());
+    }
+    match inner.split_once('|') {
+        Some((name, default)) => Placeholder::Variable {
+            name: name.trim().to_string(),
+            default: Some(default.trim().to_string()),
+        },
+        None => Placeholder::Variable { name: inner.to_string(), default: None },
+    }
+}
+
+/// All placeholders in the text, in order of appearance
+pub fn placeholders(text: &str) -> Vec<Placeholder> {
+    placeholder_regex()
+        .captures_iter(text)
+        .map(|c| parse_placeholder(&c[1]))
+        .collect()
+}
+
+/// Parse `KEY=VALUE` pairs from the command line
+pub fn parse_vars(pairs: &[String]) -> Result<BTreeMap<String, String>> {
+    pairs
+        .iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.to_string()))
+                .filter(|(k, _)| !k.is_empty())
+                .ok_or_else(|| AppError::cli_usage(format!("Invalid variable '{}', expected KEY=VALUE", pair)))
+        })
+        .collect()
+}
+
+impl Template {
+    /// Names of variables used by the template, without duplicates
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for placeholder in placeholders(&self.def.text) {
+            if let Placeholder::Variable { name, .. } = placeholder {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Whether rendering needs standard input
+    pub fn uses_stdin(&self) -> bool {
+        placeholders(&self.def.text).contains(&Placeholder::Stdin)
+    }
+
+    /// Variables that have neither a value in `vars` nor a default
+    pub fn missing_variables(&self, vars: &BTreeMap<String, String>) -> Vec<String> {
+        let mut missing = Vec::new();
+        for placeholder in placeholders(&self.def.text) {
+            if let Placeholder::Variable { name, default: None } = placeholder {
+                if !vars.contains_key(&name) && !self.def.defaults.contains_key(&name) && !missing.contains(&name) {
+                    missing.push(name);
+                }
+            }
+        }
+        missing
+    }
+
+    /// Fill in every placeholder. Values in `vars` take precedence over the
+    /// template's defaults, which take precedence over inline `|default`s.
+    pub fn render(&self, vars: &BTreeMap<String, String>, stdin: Option<&str>) -> Result<String> {
+        let missing = self.missing_variables(vars);
+        if !missing.is_empty() {
+            return Err(AppError::cli_usage(format!(
+                "Template '{}' is missing values for: {} (pass them with --var KEY=VALUE)",
+                self.name, missing.join(", ")
+            )));
+        }
+
+        let mut rendered = String::new();
+        let mut last = 0;
+        for captures in placeholder_regex().captures_iter(&self.def.text) {
+            let whole = captures.get(0).expect("capture 0 always exists");
+            rendered.push_str(&self.def.text[last..whole.start()]);
+            last = whole.end();
+
+            match parse_placeholder(&captures[1]) {
+                Placeholder::Variable { name, default } => {
+                    let value = vars.get(&name).or_else(|| self.def.defaults.get(&name)).or(default.as_ref());
+                    rendered.push_str(value.map(String::as_str).unwrap_or(""));
+                },
+                Placeholder::File(path) => {
+                    let content = fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to include file {} in template '{}'", path, self.name))?;
+                    rendered.push_str(&content);
+                },
+                Placeholder::Stdin => {
+                    let input = stdin.ok_or_else(|| AppError::cli_usage(format!("Template '{}' needs standard input", self.name)))?;
+                    rendered.push_str(input);
+                },
+            }
+        }
+        rendered.push_str(&self.def.text[last..]);
+        Ok(rendered)
+    }
+}
+
+/// Templates from `config.toml` and the templates directory
+#[derive(Clone, Debug, Default)]
+pub struct TemplateLibrary {
+    templates: BTreeMap<String, Template>,
+}
+
+impl TemplateLibrary {
+    /// Load inline templates from the configuration, then files from
+    /// `templates_dir`. A file overrides an inline template of the same name.
+    pub fn load(config: &Config) -> Result<Self> {
+        let mut templates = BTreeMap::new();
+        for (name, def) in &config.templates {
+            templates.insert(name.clone(), Template { name: name.clone(), def: def.clone(), source: TemplateSource::Config });
+        }
+
+        let dir = config.get_templates_dir_path();
+        if dir.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+                .with_context(|| format!("Failed to read templates directory {}", dir.display()))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect();
+            entries.sort();
+
+            for path in entries {
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+                    continue;
+                };
+                let def = match load_template_file(&path) {
+                    Ok(def) => def,
+                    Err(e) => {
+                        warn!("Skipping template {}: {}", path.display(), e);
+                        continue;
+                    },
+                };
+                if templates.contains_key(&name) {
+                    debug!("Template file {} overrides template '{}'", path.display(), name);
+                }
+                templates.insert(name.clone(), Template { name, def, source: TemplateSource::File(path) });
+            }
+        }
+
+        Ok(Self { templates })
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &Template> {
+        self.templates.values()
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Template> {
+        self.templates.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+            AppError::cli_usage(format!(
+                "Unknown template '{}'; available templates: {}",
+                name,
+                if known.is_empty() { "(none)".to_string() } else { known.join(", ") }
+            ))
+        })
+    }
+}
+
+/// Read a template file: `.toml` files hold a full definition, any other
+/// file is used as the template text
+pub fn load_template_file(path: &Path) -> Result<TemplateDef> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template {}", path.display()))?;
+    if path.extension().and_then(|e| e.to_str()) == Some("toml") {
+        toml::from_str(&content).with_context(|| format!("Failed to parse template {}", path.display()))
+    } else {
+        Ok(TemplateDef { text: content, ..Default::default() })
+    }
+}
+
+/// Create a new `.toml` template in `dir` with a starter text
+pub fn create_template(dir: &Path, name: &str, description: Option<&str>) -> Result<PathBuf> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(AppError::cli_usage(format!(
+            "Invalid template name '{}'; use letters, digits, '-' and '_'", name
+        )));
+    }
+
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create templates directory {}", dir.display()))?;
+    let path = dir.join(format!("{}.toml", name));
+    if path.exists() {
+        return Err(AppError::cli_usage(format!("Template {} already exists", path.display())));
+    }
+
+    let def = TemplateDef {
+        description: description.map(str::to_string),
+        text: "You are helping with {{lang|Rust}} code.\n\n{{input}}\n".to_string(),
+        defaults: BTreeMap::new(),
+    };
+    let content = toml::to_string(&def)
+        .with_context(|| "Failed to serialize template to TOML")?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write template {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,459 @@
+use crate::tools::ToolRegistry;
+use crate::tools::patch::{apply_hunks, parse_unified_diff};
+use crate::error::{AppError, Result, ErrorExt};
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Maximum characters of tool output returned to the model
+const MAX_OUTPUT_CHARS: usize = 20_000;
+
+/// Maximum number of matching lines returned by `grep`
+const MAX_GREP_MATCHES: usize = 200;
+
+/// Decides whether a tool call may run, given the tool name and its arguments
+pub type ConfirmFn = Arc<dyn Fn(&str, &Value) -> bool + Send + Sync>;
+
+/// Ask on the terminal before running each tool call. Answers are read from
+/// the terminal rather than stdin, which may hold piped input, so this fails
+/// when there is no terminal to ask on.
+pub fn prompt_confirmation() -> Result<ConfirmFn> {
+    let terminal = open_terminal().ok_or_else(|| {
+        AppError::cli_usage("Tool calls need confirmation on a terminal; pass --yes to allow them without asking")
+    })?;
+    let terminal = Mutex::new(io::BufReader::new(terminal));
+
+    Ok(Arc::new(move |name: &str, arguments: &Value| {
+        eprint!("Allow tool call {} {}? [y/N] ", name, arguments);
+        let _ = io::stderr().flush();
+
+        let mut answer = String::new();
+        let Ok(mut terminal) = terminal.lock() else {
+            return false;
+        };
+        if terminal.read_line(&mut answer).is_err() {
+            return false;
+        }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }))
+}
+
+#[cfg(unix)]
+fn open_terminal() -> Option<Box<dyn Read + Send>> {
+    fs::File::open("/dev/tty").ok().map(|tty| Box::new(tty) as Box<dyn Read + Send>)
+}
+
+#[cfg(not(unix))]
+fn open_terminal() -> Option<Box<dyn Read + Send>> {
+    use std::io::IsTerminal;
+    io::stdin().is_terminal().then(|| Box::new(io::stdin()) as Box<dyn Read + Send>)
+}
+
+/// Approve every tool call without asking
+pub fn auto_approve() -> ConfirmFn {
+    Arc::new(|_: &str, _: &Value| true)
+}
+
+/// Local workspace that the built-in tools are confined to
+pub struct Workspace {
+    root: PathBuf,
+    command_allowlist: Vec<String>,
+    command_timeout: Duration,
+    confirm: ConfirmFn,
+}
+
+impl Workspace {
+    pub fn new<P: AsRef<Path>>(root: P, command_allowlist: Vec<String>, command_timeout: Duration, confirm: ConfirmFn) -> Result<Self> {
+        let root = root.as_ref();
+        let root = root.canonicalize()
+            .with_context(|| format!("Workspace root {} is not accessible", root.display()))?;
+
+        Ok(Self {
+            root,
+            command_allowlist,
+            command_timeout,
+            confirm,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a workspace-relative path, rejecting anything outside the root
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let mut resolved = self.root.clone();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {},
+                Component::ParentDir => {
+                    if !resolved.pop() || !resolved.starts_with(&self.root) {
+                        return Err(outside_workspace(path));
+                    }
+                },
+                Component::RootDir | Component::Prefix(_) => {
+                    // Absolute paths are only accepted inside the workspace
+                    let absolute = Path::new(path);
+                    if absolute.starts_with(&self.root) {
+                        return self.resolve(&absolute.strip_prefix(&self.root).unwrap_or(absolute).to_string_lossy());
+                    }
+                    return Err(outside_workspace(path));
+                },
+            }
+        }
+
+        if !resolved.starts_with(&self.root) {
+            return Err(outside_workspace(path));
+        }
+        self.check_inside(&resolved, path)?;
+        Ok(resolved)
+    }
+
+    /// Follow symlinks in the nearest existing ancestor of `resolved` (or the
+    /// path itself) so a linked directory cannot lead outside the root
+    fn check_inside(&self, resolved: &Path, path: &str) -> Result<()> {
+        let existing = resolved
+            .ancestors()
+            .find(|ancestor| ancestor.symlink_metadata().is_ok())
+            .ok_or_else(|| outside_workspace(path))?;
+        // Dangling symlinks fail to canonicalize and are rejected too
+        match existing.canonicalize() {
+            Ok(canonical) if canonical.starts_with(&self.root) => Ok(()),
+            _ => Err(outside_workspace(path)),
+        }
+    }
+
+    fn confirm(&self, name: &str, arguments: &Value) -> Result<()> {
+        if (self.confirm)(name, arguments) {
+            Ok(())
+        } else {
+            Err(AppError::cli_usage(format!("The user declined the {} call", name)))
+        }
+    }
+
+    /// Read a file, optionally limited to a 1-based inclusive line range
+    pub fn read_file(&self, path: &str, start_line: Option<usize>, end_line: Option<usize>) -> Result<String> {
+        let content = fs::read_to_string(self.resolve(path)?)
+            .with_context(|| format!("Failed to read {}", path))?;
+
+        if start_line.is_none() && end_line.is_none() {
+            return Ok(truncate_output(content));
+        }
+
+        let start = start_line.unwrap_or(1).max(1);
+        let end = end_line.unwrap_or(usize::MAX);
+        let selected = content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i + 1 >= start && *i < end)
+            .map(|(i, line)| format!("{:>5} {}", i + 1, line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(truncate_output(selected))
+    }
+
+    /// List directory entries, marking subdirectories with a trailing slash
+    pub fn list_dir(&self, path: &str) -> Result<String> {
+        let dir = self.resolve(path)?;
+        let mut entries = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to list {}", path))?
+            .filter_map(|e| e.ok())
+            .map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                if e.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                    format!("{}/", name)
+                } else {
+                    name
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+        Ok(entries.join("\n"))
+    }
+
+    /// Search files under a path for a regex, respecting `.gitignore`
+    pub fn grep(&self, pattern: &str, path: &str) -> Result<String> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| AppError::cli_usage(format!("Invalid regex '{}': {}", pattern, e)))?;
+        let start = self.resolve(path)?;
+
+        let mut matches = Vec::new();
+        let walker = WalkBuilder::new(&start).require_git(false).sort_by_file_name(|a, b| a.cmp(b)).build();
+        'files: for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(entry.path()) else { continue };
+            let relative = entry.path().strip_prefix(&self.root).unwrap_or(entry.path()).to_string_lossy().to_string();
+
+            for (i, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    matches.push(format!("{}:{}: {}", relative, i + 1, line.trim_end()));
+                    if matches.len() >= MAX_GREP_MATCHES {
+                        matches.push(format!("(stopped after {} matches)", MAX_GREP_MATCHES));
+                        break 'files;
+                    }
+                }
+            }
+        }
+
+        if matches.is_empty() {
+            Ok("No matches found.".to_string())
+        } else {
+            Ok(truncate_output(matches.join("\n")))
+        }
+    }
+
+    /// Apply a unified diff to files in the workspace
+    pub fn apply_patch(&self, patch: &str) -> Result<String> {
+        let file_patches = parse_unified_diff(patch)?;
+
+        // Compute every result before writing so a failing hunk changes nothing
+        let mut updates = Vec::new();
+        for file_patch in &file_patches {
+            let path = self.resolve(file_patch.path())?;
+            match (&file_patch.old_path, &file_patch.new_path) {
+                (_, None) => updates.push((path, None)),
+                (None, Some(new)) => {
+                    if path.symlink_metadata().is_ok() {
+                        return Err(AppError::cli_usage(format!("Cannot create {}: it already exists", new)));
+                    }
+                    updates.push((path, Some(apply_hunks("", &file_patch.hunks)?)));
+                },
+                (Some(old), Some(new)) => {
+                    let old_path = self.resolve(old)?;
+                    if old != new && path.symlink_metadata().is_ok() {
+                        return Err(AppError::cli_usage(format!("Cannot rename {} to {}: it already exists", old, new)));
+                    }
+                    let original = fs::read_to_string(&old_path)
+                        .with_context(|| format!("Failed to read {}", old))?;
+                    updates.push((path, Some(apply_hunks(&original, &file_patch.hunks)?)));
+                    // A rename writes the new path first, then removes the old one
+                    if old != new {
+                        updates.push((old_path, None));
+                    }
+                },
+            }
+        }
+
+        let mut summary = Vec::new();
+        for (path, content) in updates {
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path).display().to_string();
+            match content {
+                Some(content) => {
+                    if let Some(parent) = path.parent() {
+                        self.check_inside(parent, &relative)?;
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&path, content).with_context(|| format!("Failed to write {}", relative))?;
+                    summary.push(format!("patched {}", relative));
+                },
+                None => {
+                    fs::remove_file(&path).with_context(|| format!("Failed to delete {}", relative))?;
+                    summary.push(format!("deleted {}", relative));
+                },
+            }
+        }
+        Ok(summary.join("\n"))
+    }
+
+    /// Run an allowlisted command in the workspace root without a shell
+    pub fn run_command(&self, command: &str) -> Result<String> {
+        let args: Vec<&str> = command.split_whitespace().collect();
+        if !self.is_allowed(&args) {
+            return Err(AppError::cli_usage(format!(
+                "Command '{}' is not allowed; allowed commands: {}", command, self.command_allowlist.join(", ")
+            )));
+        }
+
+        let mut child = Command::new(args[0])
+            .args(&args[1..])
+            .current_dir(&self.root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start '{}'", command))?;
+
+        // Drain output on separate threads so a full pipe cannot block the child
+        let stdout = child.stdout.take().map(read_in_background);
+        let stderr = child.stderr.take().map(read_in_background);
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() > self.command_timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::unknown(format!(
+                    "Command '{}' timed out after {} seconds", command, self.command_timeout.as_secs()
+                )));
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+        let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+        Ok(truncate_output(format!(
+            "exit status: {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+            status.code().map(|c| c.to_string()).unwrap_or_else(|| "signal".to_string()),
+            stdout,
+            stderr
+        )))
+    }
+
+    /// A command is allowed when its words equal an allowlist entry exactly.
+    /// An entry ending in `*` also accepts extra arguments after its words,
+    /// as long as none of them is a flag: flags such as `--config` or
+    /// `--manifest-path` could make an allowed command run arbitrary code.
+    fn is_allowed(&self, args: &[&str]) -> bool {
+        !args.is_empty() && self.command_allowlist.iter().any(|allowed| {
+            let mut allowed: Vec<&str> = allowed.split_whitespace().collect();
+            let open = allowed.last() == Some(&"*");
+            if open {
+                allowed.pop();
+            }
+            if allowed.is_empty() || args.len() < allowed.len() || args[..allowed.len()] != allowed[..] {
+                return false;
+            }
+            let extra = &args[allowed.len()..];
+            extra.is_empty() || (open && extra.iter().all(|arg| !arg.starts_with('-')))
+        })
+    }
+
+    /// Register `read_file`, `list_dir`, `grep`, `apply_patch` and `run_command`
+    pub fn register_tools(self: &Arc<Self>, registry: &mut ToolRegistry) {
+        let ws = Arc::clone(self);
+        registry.register(
+            "read_file",
+            "Read a text file in the workspace, optionally only a range of lines",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path relative to the workspace root" },
+                    "start_line": { "type": "integer", "description": "First line to read (1-based)" },
+                    "end_line": { "type": "integer", "description": "Last line to read (inclusive)" }
+                },
+                "required": ["path"]
+            }),
+            move |args| {
+                ws.confirm("read_file", &args)?;
+                let path = required_str(&args, "path")?;
+                let start = args.get("start_line").and_then(|v| v.as_u64()).map(|v| v as usize);
+                let end = args.get("end_line").and_then(|v| v.as_u64()).map(|v| v as usize);
+                ws.read_file(path, start, end)
+            },
+        );
+
+        let ws = Arc::clone(self);
+        registry.register(
+            "list_dir",
+            "List the entries of a directory in the workspace",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Directory relative to the workspace root" }
+                },
+                "required": ["path"]
+            }),
+            move |args| {
+                ws.confirm("list_dir", &args)?;
+                ws.list_dir(args.get("path").and_then(|v| v.as_str()).unwrap_or("."))
+            },
+        );
+
+        let ws = Arc::clone(self);
+        registry.register(
+            "grep",
+            "Search workspace files for lines matching a regular expression",
+            json!({
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "description": "Regular expression to search for" },
+                    "path": { "type": "string", "description": "File or directory to search, relative to the workspace root" }
+                },
+                "required": ["pattern"]
+            }),
+            move |args| {
+                ws.confirm("grep", &args)?;
+                let pattern = required_str(&args, "pattern")?;
+                ws.grep(pattern, args.get("path").and_then(|v| v.as_str()).unwrap_or("."))
+            },
+        );
+
+        let ws = Arc::clone(self);
+        registry.register(
+            "apply_patch",
+            "Apply a unified diff to files in the workspace",
+            json!({
+                "type": "object",
+                "properties": {
+                    "patch": { "type": "string", "description": "Unified diff with ---/+++ headers and @@ hunks" }
+                },
+                "required": ["patch"]
+            }),
+            move |args| {
+                ws.confirm("apply_patch", &args)?;
+                ws.apply_patch(required_str(&args, "patch")?)
+            },
+        );
+
+        let ws = Arc::clone(self);
+        let allowed = self.command_allowlist.join(", ");
+        registry.register(
+            "run_command",
+            &format!("Run a command in the workspace root. Allowed commands: {}", allowed),
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "description": "Command line to run, e.g. 'cargo check'" }
+                },
+                "required": ["command"]
+            }),
+            move |args| {
+                ws.confirm("run_command", &args)?;
+                ws.run_command(required_str(&args, "command")?)
+            },
+        );
+    }
+}
+
+fn required_str<'a>(args: &'a Value, key: &str) -> Result<&'a str> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| AppError::cli_usage(format!("Missing string argument '{}'", key)))
+}
+
+fn outside_workspace(path: &str) -> AppError {
+    AppError::cli_usage(format!("Path '{}' is outside the workspace", path))
+}
+
+fn read_in_background<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf);
+        String::from_utf8_lossy(&buf).to_string()
+    })
+}
+
+fn truncate_output(mut output: String) -> String {
+    if output.len() > MAX_OUTPUT_CHARS {
+        let mut end = MAX_OUTPUT_CHARS;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        output.truncate(end);
+        output.push_str("\n(output truncated)");
+    }
+    output
+}
@@ -0,0 +1,101 @@
+mod builtin;
+pub mod patch;
+
+pub use builtin::{ConfirmFn, Workspace, auto_approve, prompt_confirmation};
+
+use crate::models::{Tool, ToolCall};
+use crate::error::{AppError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A Rust closure callable by the model, receiving the parsed JSON arguments
+pub type ToolHandler = Box<dyn Fn(Value) -> Result<String> + Send + Sync>;
+
+struct RegisteredTool {
+    definition: Tool,
+    handler: ToolHandler,
+}
+
+/// Record of a tool call executed during a chat loop
+#[derive(Serialize, Debug, Clone)]
+pub struct ToolInvocation {
+    pub name: String,
+    pub arguments: Value,
+    pub result: String,
+    pub is_error: bool,
+}
+
+/// Set of tools offered to the model, keyed by function name
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, RegisteredTool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a closure as a tool; `parameters` is a JSON Schema for its arguments
+    pub fn register<F>(&mut self, name: &str, description: &str, parameters: Value, handler: F) -> &mut Self
+    where
+        F: Fn(Value) -> Result<String> + Send + Sync + 'static,
+    {
+        self.tools.insert(name.to_string(), RegisteredTool {
+            definition: Tool::function(name, description, parameters),
+            handler: Box::new(handler),
+        });
+        self
+    }
+    
+    /// Tool definitions to send with a chat request
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.tools.values().map(|t| t.definition.clone()).collect()
+    }
+    
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+    
+    /// Execute a tool call from the model
+    pub fn call(&self, call: &ToolCall) -> Result<String> {
+        let tool = self.tools.get(&call.function.name)
+            .ok_or_else(|| AppError::api(format!("Model called unknown tool '{}'", call.function.name)))?;
+        
+        let arguments = parse_arguments(&call.function.arguments)?;
+        (tool.handler)(arguments)
+    }
+    
+    /// Execute a tool call, capturing failures as an error result for the model
+    pub fn invoke(&self, call: &ToolCall) -> ToolInvocation {
+        let arguments = parse_arguments(&call.function.arguments).unwrap_or(Value::Null);
+        let (result, is_error) = match self.call(call) {
+            Ok(result) => (result, false),
+            Err(e) => (format!("Error: {}", e), true),
+        };
+        
+        ToolInvocation {
+            name: call.function.name.clone(),
+            arguments,
+            result,
+            is_error,
+        }
+    }
+}
+
+fn parse_arguments(arguments: &str) -> Result<Value> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+    serde_json::from_str(arguments)
+        .map_err(|e| AppError::api(format!("Invalid tool call arguments: {}", e)))
+}
@@ -0,0 +1,171 @@
+use crate::error::{AppError, Result};
+
+/// One line of a unified diff hunk
+#[derive(Debug, Clone, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A `@@ -a,b +c,d @@` section of a unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub new_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+/// Changes to a single file. `old_path` is `None` for new files and
+/// `new_path` is `None` for deleted files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// Path the patch applies to
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or("")
+    }
+}
+
+/// Parse a unified diff, as produced by `git diff` or `diff -u`
+pub fn parse_unified_diff(text: &str) -> Result<Vec<FilePatch>> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new_line = lines.next()
+                .ok_or_else(|| AppError::cli_usage("Patch ends after '---' header"))?;
+            let new = new_line.strip_prefix("+++ ")
+                .ok_or_else(|| AppError::cli_usage(format!("Expected '+++' header after '{}'", line)))?;
+
+            patches.push(FilePatch {
+                old_path: header_path(old),
+                new_path: header_path(new),
+                hunks: Vec::new(),
+            });
+        } else if let Some(range) = line.strip_prefix("@@ ") {
+            let patch = patches.last_mut()
+                .ok_or_else(|| AppError::cli_usage("Hunk found before any file header"))?;
+            let (old_start, new_start, mut remaining) = parse_range(range)?;
+
+            let mut hunk_lines = Vec::new();
+            while let Some(next) = lines.peek() {
+                // While the header's line counts are not used up, a `--- ` line
+                // is a removed line starting with `-- ` rather than a file header
+                let in_counts = remaining.is_some_and(|(old, new)| old + new > 0);
+                if next.starts_with("@@ ") || next.starts_with("diff ") || (next.starts_with("--- ") && !in_counts) {
+                    break;
+                }
+                let next = lines.next().unwrap_or_default();
+                let (old, new) = if let Some(rest) = next.strip_prefix('+') {
+                    hunk_lines.push(HunkLine::Add(rest.to_string()));
+                    (0, 1)
+                } else if let Some(rest) = next.strip_prefix('-') {
+                    hunk_lines.push(HunkLine::Remove(rest.to_string()));
+                    (1, 0)
+                } else if let Some(rest) = next.strip_prefix(' ') {
+                    hunk_lines.push(HunkLine::Context(rest.to_string()));
+                    (1, 1)
+                } else if next.is_empty() {
+                    hunk_lines.push(HunkLine::Context(String::new()));
+                    (1, 1)
+                } else {
+                    // "\ No newline at end of file" and other markers are ignored
+                    (0, 0)
+                };
+                if let Some((old_left, new_left)) = remaining.as_mut() {
+                    *old_left = old_left.saturating_sub(old);
+                    *new_left = new_left.saturating_sub(new);
+                }
+            }
+
+            patch.hunks.push(Hunk { old_start, new_start, lines: hunk_lines });
+        }
+    }
+
+    if patches.is_empty() {
+        return Err(AppError::cli_usage("Patch contains no file changes"));
+    }
+    Ok(patches)
+}
+
+/// Apply the hunks of a file patch to the original content.
+///
+/// Each hunk is matched near its stated line first and then anywhere later in
+/// the file, so patches with slightly stale line numbers still apply.
+pub fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<String> {
+    let mut lines: Vec<String> = original.lines().map(|l| l.to_string()).collect();
+    let mut offset: isize = 0;
+    let mut search_from = 0;
+
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+            HunkLine::Add(_) => None,
+        }).collect();
+        let new: Vec<String> = hunk.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(s) | HunkLine::Add(s) => Some(s.clone()),
+            HunkLine::Remove(_) => None,
+        }).collect();
+
+        let expected = ((hunk.old_start.max(1) - 1) as isize + offset).max(0) as usize;
+        let position = if matches_at(&lines, &old, expected) {
+            Some(expected)
+        } else {
+            (search_from..=lines.len().saturating_sub(old.len())).find(|&i| matches_at(&lines, &old, i))
+        }.ok_or_else(|| AppError::cli_usage(format!("Hunk {} does not match the file content", n + 1)))?;
+
+        lines.splice(position..position + old.len(), new.iter().cloned());
+        offset += new.len() as isize - old.len() as isize;
+        search_from = position + new.len();
+    }
+
+    let mut result = lines.join("\n");
+    if !result.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+fn matches_at(lines: &[String], old: &[&str], position: usize) -> bool {
+    position + old.len() <= lines.len()
+        && lines[position..position + old.len()].iter().zip(old).all(|(a, b)| a.trim_end() == b.trim_end())
+}
+
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or("").trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Lines of the old and new side a hunk header says are left to read
+type LineCounts = (usize, usize);
+
+/// Start lines of the old and new side of a hunk header, and the line
+/// counts of both sides when the header has them. The new start falls back
+/// to the old one for hand-written headers without it.
+fn parse_range(range: &str) -> Result<(usize, usize, Option<LineCounts>)> {
+    let mut parts = range.split_whitespace();
+    // `-a,b` or `-a`, where a missing count means one line
+    let side = |part: Option<&str>, sign: char| -> Option<(usize, usize)> {
+        let mut numbers = part?.strip_prefix(sign)?.split(',');
+        let start = numbers.next()?.parse().ok()?;
+        let count = numbers.next().map_or(Some(1), |c| c.parse().ok())?;
+        Some((start, count))
+    };
+    let (old_start, old_count) = side(parts.next(), '-')
+        .ok_or_else(|| AppError::cli_usage(format!("Invalid hunk header '@@ {}'", range)))?;
+    match side(parts.next(), '+') {
+        Some((new_start, new_count)) => Ok((old_start, new_start, Some((old_count, new_count)))),
+        None => Ok((old_start, old_start, None)),
+    }
+}
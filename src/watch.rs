@@ -0,0 +1,219 @@
+use crate::error::{AppError, Result, ErrorExt};
+use crate::eval::CompletionProvider;
+use crate::fim::{PostProcessOptions, postprocess};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::{debug, warn};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Comment openers a marker may follow, so markers in strings are left alone
+pub const COMMENT_TOKENS: &[&str] = &["//", "///", "//!", "#", "--", ";", "/*", "*", "<!--"];
+
+/// Always ignored, besides `.gitignore` and configured patterns
+const DEFAULT_IGNORES: &[&str] = &[".git/", "target/", "*.suggestion.patch"];
+
+/// A marker comment asking for code at its line
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerSite {
+    /// 0-based line index
+    pub line: usize,
+    pub indent: String,
+    /// Comment opener before the marker, e.g. `//`
+    pub comment: String,
+    /// Text after the marker, kept as a comment above the generated code
+    pub instruction: Option<String>,
+}
+
+/// Markers that were filled in a file's content
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerFill {
+    pub content: String,
+    pub filled: usize,
+    /// Markers left in place because the completion was empty
+    pub unfilled: usize,
+}
+
+/// Find marker comments, at most one per line
+pub fn find_markers(content: &str, markers: &[String]) -> Vec<MarkerSite> {
+    let mut sites = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let Some((position, marker)) = markers
+            .iter()
+            .filter_map(|m| line.find(m.as_str()).map(|p| (p, m)))
+            .min_by_key(|(p, _)| *p)
+        else {
+            continue;
+        };
+
+        let before = &line[..position];
+        let comment = before.trim();
+        if !COMMENT_TOKENS.contains(&comment) {
+            continue;
+        }
+        let instruction = line[position + marker.len()..]
+            .trim()
+            .trim_end_matches("-->")
+            .trim_end_matches("*/")
+            .trim();
+        sites.push(MarkerSite {
+            line: index,
+            indent: before[..before.len() - before.trim_start().len()].to_string(),
+            comment: comment.to_string(),
+            instruction: (!instruction.is_empty()).then(|| instruction.to_string()),
+        });
+    }
+    sites
+}
+
+/// FIM prefix and suffix for a marker. The marker line is dropped; its
+/// instruction, if any, stays as a plain comment at the end of the prefix.
+pub fn marker_fim_parts(content: &str, site: &MarkerSite) -> (String, String) {
+    let lines: Vec<&str> = content.lines().collect();
+    let split = site.line.min(lines.len());
+
+    let mut prefix = lines[..split].join("\n");
+    if !prefix.is_empty() {
+        prefix.push('\n');
+    }
+    if let Some(instruction) = &site.instruction {
+        prefix.push_str(&format!("{}{} {}\n", site.indent, site.comment, instruction));
+    }
+    prefix.push_str(&site.indent);
+
+    let mut suffix = String::new();
+    for line in lines.iter().skip(split + 1) {
+        suffix.push('\n');
+        suffix.push_str(line);
+    }
+    if content.ends_with('\n') {
+        suffix.push('\n');
+    }
+    (prefix, suffix)
+}
+
+/// Fill every marker in `content` with a FIM completion, working bottom-up
+/// so earlier marker lines stay where they were found
+pub async fn complete_markers(
+    content: &str,
+    markers: &[String],
+    provider: &dyn CompletionProvider,
+    max_tokens: u32,
+    seed: u64,
+    options: &PostProcessOptions,
+) -> Result<MarkerFill> {
+    let mut fill = MarkerFill { content: content.to_string(), filled: 0, unfilled: 0 };
+    for site in find_markers(content, markers).iter().rev() {
+        let (prefix, suffix) = marker_fim_parts(&fill.content, site);
+        let completion = provider.complete(&prefix, &suffix, max_tokens, seed).await?;
+        let text = postprocess(&completion.text, &prefix, &suffix, options);
+        let text = text.trim_end_matches('\n');
+        if text.trim().is_empty() {
+            warn!("Empty completion for the marker on line {}", site.line + 1);
+            fill.unfilled += 1;
+            continue;
+        }
+        fill.content = format!("{}{}{}", prefix, text, suffix);
+        fill.filled += 1;
+    }
+    Ok(fill)
+}
+
+/// Where the suggestion for a file is written in sidecar mode
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{}.suggestion.patch", name))
+}
+
+/// Ignore rules for a watched directory: its `.gitignore`, the built-in
+/// ignores and extra gitignore-style patterns
+pub fn ignore_rules(root: &Path, patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    let gitignore = root.join(".gitignore");
+    if gitignore.is_file() {
+        if let Some(e) = builder.add(&gitignore) {
+            warn!("Failed to read {}: {}", gitignore.display(), e);
+        }
+    }
+    for pattern in DEFAULT_IGNORES.iter().copied().chain(patterns.iter().map(String::as_str)) {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| AppError::cli_usage(format!("Invalid ignore pattern '{}': {}", pattern, e)))?;
+    }
+    builder.build().map_err(|e| AppError::cli_usage(format!("Invalid ignore patterns: {}", e)))
+}
+
+fn is_ignored(rules: &Gitignore, root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .map(|relative| rules.matched_path_or_any_parents(relative, false).is_ignore())
+        .unwrap_or(true)
+}
+
+/// Watches a directory for saved files, batching changes until no file
+/// has changed for the debounce period
+pub struct FileWatcher {
+    root: PathBuf,
+    debounce: Duration,
+    events: mpsc::UnboundedReceiver<PathBuf>,
+    processed: HashMap<PathBuf, u64>,
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(root: &Path, ignore_patterns: &[String], debounce: Duration) -> Result<Self> {
+        let root = root.canonicalize().with_context(|| format!("Failed to resolve {}", root.display()))?;
+        let rules = ignore_rules(&root, ignore_patterns)?;
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let watched_root = root.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Watch error: {}", e);
+                    return;
+                },
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            for path in event.paths {
+                if !is_ignored(&rules, &watched_root, &path) {
+                    let _ = sender.send(path);
+                }
+            }
+        })
+        .map_err(|e| AppError::unknown(format!("Failed to start watching: {}", e)))?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| AppError::unknown(format!("Failed to watch {}: {}", root.display(), e)))?;
+
+        Ok(Self { root, debounce, events, processed: HashMap::new(), _watcher: watcher })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Wait for changed files. Returns `None` if the watcher stopped.
+    pub async fn next_batch(&mut self) -> Option<Vec<PathBuf>> {
+        let mut batch = BTreeSet::from([self.events.recv().await?]);
+        while let Ok(Some(path)) = tokio::time::timeout(self.debounce, self.events.recv()).await {
+            batch.insert(path);
+        }
+        debug!("{} changed files", batch.len());
+        Some(batch.into_iter().filter(|p| p.is_file()).collect())
+    }
+
+    /// Record the content of a file, returning false if it was already
+    /// processed with this content, e.g. after our own write
+    pub fn mark_processed(&mut self, path: &Path, content: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        self.processed.insert(path.to_path_buf(), hasher.finish()) != Some(hasher.finish())
+    }
+}
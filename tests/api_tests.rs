@@ -1,16 +1,16 @@
-use codestral_test_rs::{Config, api::{MistralClient, CodestralClient}};
-use mockito;
+use codestral_test_rs::Config;
 use serde_json::json;
 use std::env;
 use test_log::test;
 
 // Helper function to create a mock Config with a server URL
 fn create_mock_config(server_url: &str) -> Config {
-    let mut config = Config::default();
-    
     // Override API URLs to use the mockito server
-    config.code_api_url = format!("{}/v1/fim/completions", server_url);
-    config.chat_api_url = format!("{}/v1/chat/completions", server_url);
+    let config = Config {
+        code_api_url: format!("{}/v1/fim/completions", server_url),
+        chat_api_url: format!("{}/v1/chat/completions", server_url),
+        ..Config::default()
+    };
     
     // Set mock API key environment variables
     env::set_var("CODESTRAL_API_KEY", "mock-code-api-key");
@@ -80,3 +80,435 @@ fn test_extract_response_fields() {
     assert_eq!(api_response.completion_tokens, 8);
     assert_eq!(api_response.total_tokens, 18);
 }
+
+#[test]
+fn test_extract_response_fields_keeps_all_choices() {
+    use codestral_test_rs::api::extract_response_fields;
+    
+    let completion_response = json!({
+        "id": "test-fim-id",
+        "choices": [
+            { "index": 0, "text": "a + b", "finish_reason": "stop" },
+            { "index": 1, "text": "b + a", "finish_reason": "stop" },
+            { "index": 2, "message": { "role": "assistant", "content": "a.add(b)" }, "finish_reason": "length" }
+        ]
+    });
+    
+    let api_response = extract_response_fields(&completion_response);
+    assert_eq!(api_response.content, Some("a + b".to_string()));
+    assert_eq!(api_response.choices, vec!["a + b", "b + a", "a.add(b)"]);
+    assert_eq!(api_response.finish_reason, "stop");
+}
+
+fn models_list_response() -> serde_json::Value {
+    json!({
+        "object": "list",
+        "data": [
+            {
+                "id": "mistral-large-latest",
+                "object": "model",
+                "capabilities": {
+                    "completion_chat": true,
+                    "completion_fim": false,
+                    "function_calling": true
+                },
+                "max_context_length": 131072,
+                "deprecation": null
+            },
+            {
+                "id": "codestral-latest",
+                "object": "model",
+                "capabilities": {
+                    "completion_chat": true,
+                    "completion_fim": true,
+                    "function_calling": true
+                },
+                "max_context_length": 262144,
+                "deprecation": "2026-01-01T00:00:00Z"
+            }
+        ]
+    })
+}
+
+#[test]
+fn test_parse_models_response() {
+    use codestral_test_rs::api::parse_models_response;
+    
+    let models = parse_models_response(&models_list_response()).expect("Failed to parse models");
+    
+    // Models are sorted by id
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].id, "codestral-latest");
+    assert!(models[0].capabilities.completion_fim);
+    assert_eq!(models[0].max_context_length, Some(262144));
+    assert_eq!(models[0].deprecation.as_deref(), Some("2026-01-01T00:00:00Z"));
+    assert_eq!(models[1].id, "mistral-large-latest");
+    assert!(!models[1].capabilities.completion_fim);
+    assert_eq!(models[1].deprecation, None);
+    
+    assert!(parse_models_response(&json!({"object": "list"})).is_err());
+}
+
+#[test(tokio::test)]
+async fn test_models_client_caches_list() {
+    use codestral_test_rs::api::{ModelsClient, ModelCache};
+    
+    let mut server = mockito::Server::new_async().await;
+    let mock = server.mock("GET", "/v1/models")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(models_list_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    
+    let cache_path = env::temp_dir().join("codestral-test-rs-models-cache.json");
+    let _ = std::fs::remove_file(&cache_path);
+    
+    let config = Config {
+        models_api_url: format!("{}/v1/models", server.url()),
+        models_cache_path: cache_path.to_string_lossy().to_string(),
+        ..create_mock_config(&server.url())
+    };
+    
+    let client = ModelsClient::new(config).expect("Failed to create client");
+    let models = client.list_models(false).await.expect("Failed to list models");
+    assert_eq!(models.len(), 2);
+    
+    // Second call is served from the cache without hitting the server
+    let cached = client.list_models(false).await.expect("Failed to list cached models");
+    assert_eq!(cached.len(), 2);
+    mock.assert_async().await;
+    
+    let cache = ModelCache::load(&cache_path).expect("Failed to load cache").expect("Cache missing");
+    assert_eq!(cache.models[0].id, "codestral-latest");
+    
+    std::fs::remove_file(&cache_path).expect("Failed to remove cache file");
+}
+
+#[test]
+fn test_parse_embeddings_response_orders_by_index() {
+    use codestral_test_rs::api::parse_embeddings_response;
+    
+    let response = json!({
+        "data": [
+            { "object": "embedding", "index": 1, "embedding": [0.5, 0.25] },
+            { "object": "embedding", "index": 0, "embedding": [1.0, -1.0] }
+        ]
+    });
+    
+    let embeddings = parse_embeddings_response(&response).expect("Failed to parse embeddings");
+    assert_eq!(embeddings, vec![vec![1.0, -1.0], vec![0.5, 0.25]]);
+    
+    assert!(parse_embeddings_response(&json!({"data": [{"index": 0}]})).is_err());
+}
+
+#[test(tokio::test)]
+async fn test_embeddings_client_batches_inputs() {
+    use codestral_test_rs::api::EmbeddingsClient;
+    
+    let mut server = mockito::Server::new_async().await;
+    let batch_body = |count: usize| json!({
+        "id": "embed-id",
+        "object": "list",
+        "model": "mistral-embed",
+        "data": (0..count).map(|i| json!({ "index": i, "embedding": [i as f64, 1.0] })).collect::<Vec<_>>(),
+        "usage": { "prompt_tokens": 4, "total_tokens": 4 }
+    }).to_string();
+    
+    let first = server.mock("POST", "/v1/embeddings")
+        .match_body(mockito::Matcher::PartialJson(json!({ "input": ["a", "b"] })))
+        .with_status(200)
+        .with_body(batch_body(2))
+        .create_async()
+        .await;
+    let second = server.mock("POST", "/v1/embeddings")
+        .match_body(mockito::Matcher::PartialJson(json!({ "input": ["c"] })))
+        .with_status(200)
+        .with_body(batch_body(1))
+        .create_async()
+        .await;
+    
+    let config = Config {
+        embed_api_url: format!("{}/v1/embeddings", server.url()),
+        embed_batch_size: 2,
+        ..create_mock_config(&server.url())
+    };
+    
+    let client = EmbeddingsClient::new(config).expect("Failed to create client");
+    let inputs = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let result = client.embed_with_usage(&inputs).await.expect("Failed to embed");
+    
+    first.assert_async().await;
+    second.assert_async().await;
+    assert_eq!(result.embeddings, vec![vec![0.0, 1.0], vec![1.0, 1.0], vec![0.0, 1.0]]);
+    assert_eq!(result.response.total_tokens, 8);
+    assert_eq!(result.response.model, "mistral-embed");
+}
+
+fn tool_call_response() -> serde_json::Value {
+    json!({
+        "id": "chat-1",
+        "model": "mistral-large-latest",
+        "object": "chat.completion",
+        "choices": [{
+            "index": 0,
+            "finish_reason": "tool_calls",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "id": "call_1", "function": { "name": "add", "arguments": "{\"a\": 2, \"b\": 3}" } }]
+            }
+        }]
+    })
+}
+
+fn add_registry() -> codestral_test_rs::tools::ToolRegistry {
+    let mut registry = codestral_test_rs::tools::ToolRegistry::new();
+    registry.register("add", "Add two numbers", json!({ "type": "object" }), |args| {
+        Ok((args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0)).to_string())
+    });
+    registry
+}
+
+#[test(tokio::test)]
+async fn test_chat_with_tools_loop() {
+    use codestral_test_rs::{ChatMessage, api::MistralClient};
+    
+    let mut server = mockito::Server::new_async().await;
+    
+    // First request offers the tools and gets a tool call back
+    let first = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::Regex(r#""tools":\[.*"name":"add""#.to_string()))
+        .with_status(200)
+        .with_body(tool_call_response().to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    
+    // Second request carries the tool result and gets the final answer
+    let second = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::Regex(r#""tool_call_id":"call_1""#.to_string()))
+        .with_status(200)
+        .with_body(json!({
+            "id": "chat-2",
+            "model": "mistral-large-latest",
+            "object": "chat.completion",
+            "choices": [{ "index": 0, "finish_reason": "stop", "message": { "role": "assistant", "content": "The sum is 5." } }]
+        }).to_string())
+        .create_async()
+        .await;
+    
+    let client = MistralClient::new(create_mock_config(&server.url())).expect("Failed to create client");
+    let outcome = client
+        .chat_with_tools(vec![ChatMessage::user("What is 2 + 3?")], &add_registry(), None, 4)
+        .await
+        .expect("Tool loop failed");
+    
+    second.assert_async().await;
+    first.assert_async().await;
+    assert_eq!(outcome.invocations.len(), 1);
+    assert_eq!(outcome.invocations[0].result, "5");
+    assert_eq!(outcome.messages.last().unwrap().content, "The sum is 5.");
+    assert_eq!(outcome.messages[2].role, "tool");
+}
+
+#[test(tokio::test)]
+async fn test_chat_with_tools_iteration_guard() {
+    use codestral_test_rs::{ChatMessage, api::MistralClient};
+    
+    let mut server = mockito::Server::new_async().await;
+    let mock = server.mock("POST", "/v1/chat/completions")
+        .with_status(200)
+        .with_body(tool_call_response().to_string())
+        .expect(3)
+        .create_async()
+        .await;
+    
+    let client = MistralClient::new(create_mock_config(&server.url())).expect("Failed to create client");
+    let result = client
+        .chat_with_tools(vec![ChatMessage::user("Loop forever")], &add_registry(), None, 3)
+        .await;
+    
+    mock.assert_async().await;
+    assert!(result.is_err());
+}
+
+#[test(tokio::test)]
+async fn test_chat_with_tools_failure_keeps_invocations() {
+    use codestral_test_rs::{ChatMessage, api::MistralClient};
+    
+    let mut server = mockito::Server::new_async().await;
+    let _mock = server.mock("POST", "/v1/chat/completions")
+        .with_status(200)
+        .with_body(tool_call_response().to_string())
+        .create_async()
+        .await;
+    
+    let client = MistralClient::new(create_mock_config(&server.url())).expect("Failed to create client");
+    let failure = client
+        .chat_with_tools(vec![ChatMessage::user("Loop forever")], &add_registry(), None, 2)
+        .await
+        .err()
+        .expect("Tool loop should fail");
+    
+    // Tool calls made before the failure are returned with the error
+    assert_eq!(failure.invocations.len(), 2);
+    assert!(failure.error.to_string().contains("tool-calling iterations"));
+}
+
+fn chat_content_response(content: &str) -> String {
+    json!({
+        "id": "chat-json",
+        "model": "mistral-large-latest",
+        "object": "chat.completion",
+        "choices": [{ "index": 0, "finish_reason": "stop", "message": { "role": "assistant", "content": content } }]
+    }).to_string()
+}
+
+#[test(tokio::test)]
+async fn test_chat_structured_reprompts_on_schema_errors() {
+    use codestral_test_rs::{ChatMessage, api::MistralClient};
+    
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+    }
+    
+    let mut server = mockito::Server::new_async().await;
+    let invalid = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(json!({ "response_format": { "type": "json_object" } })))
+        .with_status(200)
+        .with_body(chat_content_response(r#"{"name": "Ada"}"#))
+        .expect(1)
+        .create_async()
+        .await;
+    let corrected = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::Regex("Fix these problems".to_string()))
+        .with_status(200)
+        .with_body(chat_content_response(r#"{"name": "Ada", "age": 36}"#))
+        .create_async()
+        .await;
+    
+    let schema = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" }, "age": { "type": "integer" } },
+        "required": ["name", "age"]
+    });
+    
+    let client = MistralClient::new(create_mock_config(&server.url())).expect("Failed to create client");
+    let person: Person = client
+        .chat_structured(vec![ChatMessage::user("Who wrote the first program?")], Some(&schema), None, 2)
+        .await
+        .expect("Structured chat failed");
+    
+    invalid.assert_async().await;
+    corrected.assert_async().await;
+    assert_eq!(person, Person { name: "Ada".to_string(), age: 36 });
+}
+
+#[test(tokio::test)]
+async fn test_code_completion_sends_sampling_params() {
+    use codestral_test_rs::{SamplingParams, api::CodestralClient};
+    
+    let mut server = mockito::Server::new_async().await;
+    let mock = server.mock("POST", "/v1/fim/completions")
+        .match_body(mockito::Matcher::Json(json!({
+            "model": "codestral-latest",
+            "prompt": "fn add(a: i32, b: i32) -> i32 {",
+            "suffix": "}",
+            "max_tokens": 16,
+            "temperature": 0.2,
+            "top_p": 0.95,
+            "random_seed": 7,
+            "n": 2,
+            "stop": ["\n}"]
+        })))
+        .with_status(200)
+        .with_body(json!({ "id": "fim", "choices": [{ "text": "a + b" }, { "text": "b + a" }] }).to_string())
+        .create_async()
+        .await;
+    
+    let mut config = create_mock_config(&server.url());
+    config.top_p = Some(0.95);
+    config.random_seed = Some(1);
+    
+    let overrides = SamplingParams { random_seed: Some(7), n: Some(2), stop: Some(vec!["\n}".to_string()]), ..Default::default() };
+    let client = CodestralClient::new(config.clone())
+        .expect("Failed to create client")
+        .with_sampling(config.sampling_params().merge(&overrides));
+    let response = client.code_completion("fn add(a: i32, b: i32) -> i32 {", "}", 16).await.expect("Completion failed");
+    
+    mock.assert_async().await;
+    assert_eq!(codestral_test_rs::api::extract_choices(&response), vec!["a + b", "b + a"]);
+}
+
+#[test(tokio::test)]
+async fn test_code_completion_with_seeds_sends_parallel_requests() {
+    use codestral_test_rs::api::{CodestralClient, extract_choices};
+    
+    let mut server = mockito::Server::new_async().await;
+    let mut mocks = Vec::new();
+    for seed in [10, 11, 12] {
+        mocks.push(server.mock("POST", "/v1/fim/completions")
+            .match_body(mockito::Matcher::PartialJson(json!({ "random_seed": seed })))
+            .with_status(200)
+            .with_body(json!({ "id": format!("fim-{}", seed), "choices": [{ "text": format!("seed {}", seed) }] }).to_string())
+            .expect(1)
+            .create_async()
+            .await);
+    }
+    
+    let client = CodestralClient::new(create_mock_config(&server.url())).expect("Failed to create client");
+    let responses = client.code_completion_with_seeds("let x = ", ";", 8, &[10, 11, 12]).await.expect("Completions failed");
+    
+    for mock in &mocks {
+        mock.assert_async().await;
+    }
+    let texts: Vec<String> = responses.iter().flat_map(extract_choices).collect();
+    assert_eq!(texts, vec!["seed 10", "seed 11", "seed 12"]);
+}
+
+#[test(tokio::test)]
+async fn test_chat_sends_system_prompt() {
+    use codestral_test_rs::{ChatMessage, api::MistralClient};
+    
+    let mut server = mockito::Server::new_async().await;
+    let plain = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "messages": [
+                { "role": "system", "content": "You are a reviewer." },
+                { "role": "user", "content": "Hello" }
+            ]
+        })))
+        .with_status(200)
+        .with_body(chat_content_response("Hi"))
+        .expect(1)
+        .create_async()
+        .await;
+    // An existing system message, e.g. with context snippets, follows the system prompt
+    let merged = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(json!({
+            "messages": [
+                { "role": "system", "content": "You are a reviewer.\n\nContext" },
+                { "role": "user", "content": "Hello" }
+            ]
+        })))
+        .with_status(200)
+        .with_body(chat_content_response("Hi"))
+        .expect(1)
+        .create_async()
+        .await;
+    
+    let config = Config { system_prompt: Some("You are a reviewer.".to_string()), ..create_mock_config(&server.url()) };
+    let client = MistralClient::new(config).expect("Failed to create client");
+    client.chat("Hello", None).await.expect("Chat failed");
+    client
+        .chat_with_messages(vec![ChatMessage::system("Context"), ChatMessage::user("Hello")], None)
+        .await
+        .expect("Chat failed");
+    
+    plain.assert_async().await;
+    merged.assert_async().await;
+}
@@ -0,0 +1,101 @@
+use codestral_test_rs::commit_msg::{
+    DEFAULT_STYLE_GUIDE, HOOK_MARKER, clean_message, deleted_files, format_summaries, install_hook, message_prompt, write_message_file,
+};
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::process::Command;
+use test_log::test;
+
+fn git_repo() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let status = Command::new("git").args(["init", "-q"]).current_dir(dir.path()).status().unwrap();
+    assert!(status.success());
+    dir
+}
+
+#[test]
+fn test_clean_message() {
+    assert_eq!(clean_message("  feat: add login\n"), "feat: add login");
+    assert_eq!(clean_message("\"fix: handle empty input\""), "fix: handle empty input");
+    assert_eq!(
+        clean_message("```text\nfeat(cli): add review\n\nSplits diffs per file.\n```"),
+        "feat(cli): add review\n\nSplits diffs per file."
+    );
+    assert_eq!(clean_message("```\nchore: bump deps\n```"), "chore: bump deps");
+}
+
+#[test]
+fn test_prompts_include_style_guide_and_summaries() {
+    let summaries = vec![
+        ("src/lib.rs".to_string(), "Adds a parser. ".to_string()),
+        ("README.md".to_string(), "Documents it.".to_string()),
+    ];
+    let changes = format_summaries(&summaries);
+    assert_eq!(changes, "Summary of the changes by file:\n- src/lib.rs: Adds a parser.\n- README.md: Documents it.\n");
+    
+    let prompt = message_prompt(DEFAULT_STYLE_GUIDE, &changes);
+    assert!(prompt.contains("Conventional Commits"));
+    assert!(prompt.ends_with(&changes));
+}
+
+#[test]
+fn test_deleted_files() {
+    let diff = "--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n--- a/kept.rs\n+++ b/kept.rs\n@@ -1 +1 @@\n-a\n+b\n";
+    assert_eq!(deleted_files(diff), vec!["old.rs".to_string()]);
+    assert!(deleted_files("").is_empty());
+}
+
+#[test]
+fn test_write_message_file_keeps_git_comments() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("COMMIT_EDITMSG");
+    
+    fs::write(&path, "\n# Please enter the commit message for your changes.\n").unwrap();
+    write_message_file(&path, "feat: add hook").unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "feat: add hook\n\n# Please enter the commit message for your changes.\n"
+    );
+    
+    let empty = dir.path().join("empty");
+    write_message_file(&empty, "fix: typo").unwrap();
+    assert_eq!(fs::read_to_string(&empty).unwrap(), "fix: typo\n");
+}
+
+#[test]
+fn test_install_hook() {
+    let repo = git_repo();
+    let hook_path = repo.path().join(".git/hooks/prepare-commit-msg");
+    
+    // A stand-in for the binary that records how the hook called it
+    let executable = repo.path().join("fake bin");
+    fs::write(&executable, "#!/bin/sh\necho \"$1 $2 $3 $4\" > \"$5\"\n").unwrap();
+    Command::new("chmod").arg("+x").arg(&executable).status().unwrap();
+    let config_file = repo.path().join("it's config.toml");
+    fs::write(&config_file, "").unwrap();
+    
+    let hook = install_hook(repo.path(), &executable, &config_file, false).unwrap();
+    assert_eq!(hook.canonicalize().unwrap(), hook_path.canonicalize().unwrap());
+    assert!(fs::read_to_string(&hook).unwrap().contains(HOOK_MARKER));
+    
+    // The hook fills in plain commits but leaves `git commit -m` alone
+    let message = repo.path().join("MSG");
+    let status = Command::new(&hook).arg(&message).status().unwrap();
+    assert!(status.success());
+    // The configuration is passed by absolute path, quoted for the shell
+    let expected = format!("--config {} commit-msg --output\n", config_file.canonicalize().unwrap().display());
+    assert_eq!(fs::read_to_string(&message).unwrap(), expected);
+    
+    fs::remove_file(&message).unwrap();
+    Command::new(&hook).arg(&message).arg("message").status().unwrap();
+    assert!(!message.exists());
+    
+    // Reinstalling replaces our own hook, but other hooks need --force
+    install_hook(repo.path(), &executable, &config_file, false).unwrap();
+    fs::write(&hook_path, "#!/bin/sh\nexit 0\n").unwrap();
+    assert!(install_hook(repo.path(), &executable, &config_file, false).is_err());
+    install_hook(repo.path(), &executable, &config_file, true).unwrap();
+    
+    let not_a_repo = tempfile::tempdir().unwrap();
+    assert!(install_hook(not_a_repo.path(), &executable, &config_file, false).is_err());
+}
@@ -0,0 +1,107 @@
+use codestral_test_rs::compare::{ComparisonResult, compare_models, render_html, render_markdown, render_side_by_side};
+use codestral_test_rs::{AppError, ChatMessage, Config, api::MistralClient};
+use serde_json::json;
+use std::env;
+use test_log::test;
+
+fn result(model: &str, content: Option<&str>, error: Option<&str>) -> ComparisonResult {
+    ComparisonResult {
+        model: model.to_string(),
+        latency_ms: 12,
+        response: None,
+        content: content.map(|c| c.to_string()),
+        finish_reason: content.map(|_| "stop".to_string()),
+        completion_tokens: 3,
+        total_tokens: 9,
+        error: error.map(|e| e.to_string()),
+    }
+}
+
+#[test(tokio::test)]
+async fn test_compare_models_records_each_model() {
+    let mut server = mockito::Server::new_async().await;
+    let ok = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(json!({ "model": "model-a" })))
+        .with_status(200)
+        .with_body(json!({
+            "id": "a",
+            "model": "model-a",
+            "choices": [{ "message": { "role": "assistant", "content": "Hello from A" }, "finish_reason": "stop" }],
+            "usage": { "completion_tokens": 3, "total_tokens": 10 }
+        }).to_string())
+        .create_async()
+        .await;
+    let failing = server.mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(json!({ "model": "model-b" })))
+        .with_status(500)
+        .with_body("overloaded")
+        .create_async()
+        .await;
+    
+    env::set_var("MISTRAL_API_KEY", "mock-chat-api-key");
+    let url = format!("{}/v1/chat/completions", server.url());
+    let models = vec!["model-a".to_string(), "model-b".to_string()];
+    
+    let results = compare_models(&models, |model| {
+        let config = Config { chat_model: model, chat_api_url: url.clone(), ..Config::default() };
+        async move {
+            let client = MistralClient::new(config)?;
+            client.chat_with_messages(vec![ChatMessage::user("Hi")], Some(16)).await
+        }
+    }).await;
+    
+    ok.assert_async().await;
+    failing.assert_async().await;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].model, "model-a");
+    assert_eq!(results[0].content.as_deref(), Some("Hello from A"));
+    assert_eq!(results[0].total_tokens, 10);
+    assert_eq!(results[0].finish_reason.as_deref(), Some("stop"));
+    assert!(results[0].response.is_some());
+    assert_eq!(results[1].model, "model-b");
+    assert!(results[1].error.as_deref().unwrap().contains("500"));
+    assert!(results[1].response.is_none());
+}
+
+#[test(tokio::test)]
+async fn test_compare_models_keeps_model_order() {
+    let models = vec!["slow".to_string(), "fast".to_string()];
+    let results = compare_models(&models, |model| async move {
+        if model == "slow" {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        Err::<serde_json::Value, _>(AppError::api(format!("{} failed", model)))
+    }).await;
+    
+    assert_eq!(results[0].model, "slow");
+    assert_eq!(results[0].error.as_deref(), Some("API error: slow failed"));
+    assert_eq!(results[1].model, "fast");
+}
+
+#[test]
+fn test_render_side_by_side() {
+    let results = vec![result("model-a", Some("line one\nline two"), None), result("model-b", None, Some("boom"))];
+    let text = render_side_by_side(&results, 49);
+    let lines: Vec<&str> = text.lines().collect();
+    
+    assert_eq!(lines[0], format!("{:<23} | model-b", "model-a"));
+    assert!(lines[1].starts_with("12 ms, 9 tokens, stop"));
+    assert!(lines[1].ends_with("12 ms, 9 tokens, -"));
+    assert_eq!(lines[3], format!("{:<23} | Error: boom", "line one"));
+    assert_eq!(lines[4], format!("{:<23} |", "line two"));
+}
+
+#[test]
+fn test_render_reports() {
+    let results = vec![result("model-a", Some("fn main() {}"), None), result("model-b", Some("<b>&"), None)];
+    
+    let markdown = render_markdown("Write main", "cmp-1", &results);
+    assert!(markdown.contains("Comparison id: `cmp-1`"));
+    assert!(markdown.contains("| model-a | 12 | 3 | 9 | stop |"));
+    assert!(markdown.contains("## model-b\n\n```\n<b>&\n```"));
+    
+    let html = render_html("Write <main>", "cmp-1", &results);
+    assert!(html.contains("<pre>Write &lt;main&gt;</pre>"));
+    assert!(html.contains("<pre>&lt;b&gt;&amp;</pre>"));
+    assert_eq!(html.matches("class=\"column\"").count(), 2);
+}
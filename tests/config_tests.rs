@@ -15,10 +15,11 @@ fn test_default_config() {
     assert_eq!(config.chat_api_url, "https://api.mistral.ai/v1/chat/completions");
     assert_eq!(config.code_api_key_env, "CODESTRAL_API_KEY");
     assert_eq!(config.chat_api_key_env, "MISTRAL_API_KEY");
-    assert_eq!(config.temperature_code, 0.0);
-    assert_eq!(config.temperature_chat, 0.7);
-    assert_eq!(config.log_file, "generations.log");
-    assert_eq!(config.output_file, "generations.txt");
+    assert_eq!(config.code_temperature, 0.2);
+    assert_eq!(config.chat_temperature, 0.7);
+    assert_eq!(config.max_tokens_code, 512);
+    assert_eq!(config.max_tokens_chat, 2048);
+    assert_eq!(config.log_directory, "logs");
 }
 
 #[test]
@@ -42,10 +43,11 @@ fn test_save_and_load_config() {
     assert_eq!(config.chat_api_url, loaded_config.chat_api_url);
     assert_eq!(config.code_api_key_env, loaded_config.code_api_key_env);
     assert_eq!(config.chat_api_key_env, loaded_config.chat_api_key_env);
-    assert_eq!(config.temperature_code, loaded_config.temperature_code);
-    assert_eq!(config.temperature_chat, loaded_config.temperature_chat);
-    assert_eq!(config.log_file, loaded_config.log_file);
-    assert_eq!(config.output_file, loaded_config.output_file);
+    assert_eq!(config.code_temperature, loaded_config.code_temperature);
+    assert_eq!(config.chat_temperature, loaded_config.chat_temperature);
+    assert_eq!(config.max_tokens_code, loaded_config.max_tokens_code);
+    assert_eq!(config.max_tokens_chat, loaded_config.max_tokens_chat);
+    assert_eq!(config.log_directory, loaded_config.log_directory);
     
     // Clean up
     fs::remove_file(test_file).expect("Failed to remove test file");
@@ -72,3 +74,95 @@ fn test_generate_default_config() {
     // Clean up
     fs::remove_file(test_file).expect("Failed to remove test file");
 }
+
+#[test]
+fn test_validate_models() {
+    use codestral_test_rs::models::{ModelInfo, ModelCapabilities};
+    
+    let models = vec![
+        ModelInfo {
+            id: "codestral-latest".to_string(),
+            capabilities: ModelCapabilities { completion_chat: true, completion_fim: true, function_calling: true },
+            max_context_length: Some(262144),
+            deprecation: None,
+        },
+        ModelInfo {
+            id: "mistral-large-latest".to_string(),
+            capabilities: ModelCapabilities { completion_chat: true, completion_fim: false, function_calling: true },
+            max_context_length: Some(131072),
+            deprecation: Some("2026-01-01T00:00:00Z".to_string()),
+        },
+    ];
+    
+    // Default models are known, but the chat model is deprecated
+    let config = Config::default();
+    let warnings = config.validate_models(&models);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("deprecated"));
+    
+    // Unknown model names and models lacking FIM support are reported
+    let config = Config {
+        code_model: "mistral-large-latest".to_string(),
+        chat_model: "no-such-model".to_string(),
+        ..Config::default()
+    };
+    let warnings = config.validate_models(&models);
+    assert!(warnings.iter().any(|w| w.contains("does not support FIM")));
+    assert!(warnings.iter().any(|w| w.contains("'no-such-model' is not a known model name")));
+    
+    // Persona models are checked as chat models
+    let mut config = Config::default();
+    config.personas.insert("reviewer".to_string(), codestral_test_rs::Persona {
+        model: Some("old-model".to_string()),
+        ..Default::default()
+    });
+    let warnings = config.validate_models(&models);
+    assert!(warnings.iter().any(|w| w.contains("personas.reviewer.model 'old-model' is not a known model name")));
+}
+
+#[test]
+fn test_with_persona() {
+    use codestral_test_rs::Persona;
+    
+    let mut config = Config { system_prompt: Some("Be helpful.".to_string()), ..Config::default() };
+    config.personas.insert("reviewer".to_string(), Persona {
+        description: Some("Strict code reviewer".to_string()),
+        system_prompt: Some("You review Rust code for correctness.".to_string()),
+        model: Some("codestral-latest".to_string()),
+        temperature: Some(0.1),
+    });
+    config.personas.insert("casual".to_string(), Persona { temperature: Some(1.0), ..Default::default() });
+    
+    let reviewer = config.with_persona("reviewer").expect("Persona should exist");
+    assert_eq!(reviewer.chat_model, "codestral-latest");
+    assert_eq!(reviewer.chat_temperature, 0.1);
+    assert_eq!(reviewer.system_prompt.as_deref(), Some("You review Rust code for correctness."));
+    
+    // Unset persona fields fall back to the configuration
+    let casual = config.with_persona("casual").expect("Persona should exist");
+    assert_eq!(casual.chat_model, config.chat_model);
+    assert_eq!(casual.system_prompt.as_deref(), Some("Be helpful."));
+    
+    let error = config.with_persona("missing").unwrap_err().to_string();
+    assert!(error.contains("casual, reviewer"), "{}", error);
+}
+
+#[test]
+fn test_personas_round_trip_through_config_file() {
+    use codestral_test_rs::Persona;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("config.toml");
+    
+    let mut config = Config { system_prompt: Some("Answer briefly.".to_string()), ..Config::default() };
+    config.personas.insert("explainer".to_string(), Persona {
+        system_prompt: Some("Explain code to a beginner.".to_string()),
+        temperature: Some(0.5),
+        ..Default::default()
+    });
+    config.save_to_file(&path).expect("Failed to save config");
+    
+    let loaded = Config::load_from_file(&path).expect("Failed to load config");
+    assert_eq!(loaded.system_prompt, config.system_prompt);
+    assert_eq!(loaded.personas, config.personas);
+}
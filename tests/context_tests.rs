@@ -0,0 +1,99 @@
+use codestral_test_rs::context::{
+    ContextSnippet, ContextSource, estimate_tokens, format_for_fim_prefix, gather_context,
+    lexical_rank, normalize_scores, select_within_budget, tokenize,
+};
+use std::fs;
+use test_log::test;
+use pretty_assertions::assert_eq;
+
+fn snippet(path: &str, text: &str) -> ContextSnippet {
+    ContextSnippet {
+        path: path.to_string(),
+        start_line: 1,
+        end_line: text.lines().count(),
+        text: text.to_string(),
+        score: 0.0,
+    }
+}
+
+#[test]
+fn test_tokenize_splits_identifiers() {
+    let terms = tokenize("fn parseTokens(load_config: Config)");
+    assert!(terms.contains(&"parsetokens".to_string()));
+    assert!(terms.contains(&"parse".to_string()));
+    assert!(terms.contains(&"tokens".to_string()));
+    assert!(terms.contains(&"load_config".to_string()));
+    assert!(terms.contains(&"load".to_string()));
+    assert!(terms.contains(&"config".to_string()));
+}
+
+#[test]
+fn test_lexical_rank_orders_by_relevance() {
+    let snippets = vec![
+        snippet("net.rs", "fn open_socket() {}"),
+        snippet("config.rs", "fn load_config() -> Config { parse config file }"),
+        snippet("util.rs", "fn config_path() {}"),
+    ];
+    
+    let ranked = lexical_rank("how is the config file loaded", snippets);
+    
+    // Snippets without any query term are dropped
+    assert_eq!(ranked.len(), 2);
+    assert_eq!(ranked[0].path, "config.rs");
+    assert!(ranked[0].score > ranked[1].score);
+}
+
+#[test]
+fn test_select_within_budget() {
+    let ranked = vec![
+        snippet("a.rs", &"a".repeat(40)),
+        snippet("b.rs", &"b".repeat(400)),
+        snippet("c.rs", &"c".repeat(40)),
+    ];
+    assert_eq!(estimate_tokens(&ranked[0].text), 10);
+    
+    // The oversized snippet is skipped but smaller ones still fit
+    let selected = select_within_budget(ranked, 25);
+    let paths: Vec<&str> = selected.iter().map(|s| s.path.as_str()).collect();
+    assert_eq!(paths, vec!["a.rs", "c.rs"]);
+}
+
+#[test]
+fn test_normalize_scores() {
+    let mut snippets = vec![snippet("a.rs", "a"), snippet("b.rs", "b")];
+    snippets[0].score = 0.8;
+    snippets[1].score = 0.2;
+    normalize_scores(&mut snippets);
+    assert_eq!((snippets[0].score, snippets[1].score), (1.0, 0.25));
+    
+    let mut unscored = vec![snippet("c.rs", "c")];
+    normalize_scores(&mut unscored);
+    assert_eq!(unscored[0].score, 0.0);
+}
+
+#[test]
+fn test_format_for_fim_prefix() {
+    let prefix = format_for_fim_prefix(&[snippet("src/lib.rs", "fn a() {}\nfn b() {}")], "//");
+    assert_eq!(prefix, "// src/lib.rs:1-2\n// fn a() {}\n// fn b() {}\n\n");
+}
+
+#[test(tokio::test)]
+async fn test_gather_context_lexically_from_dir() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    fs::write(dir.path().join("auth.rs"), "fn check_token(token: &str) -> bool {\n    token.len() > 10\n}\n").unwrap();
+    fs::write(dir.path().join("math.rs"), "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+    
+    let source = ContextSource {
+        dir: Some(dir.path()),
+        file_globs: &[],
+        token_budget: 1000,
+        chunk_lines: 60,
+        index_file_name: ".codestral-index.json",
+    };
+    
+    // Without an index, snippets are ranked lexically and no embedder is needed
+    let snippets = gather_context("validate the auth token", &source, None).await.expect("Failed to gather context");
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].path, "auth.rs");
+    assert_eq!(snippets[0].start_line, 1);
+}
@@ -0,0 +1,133 @@
+use codestral_test_rs::Config;
+use codestral_test_rs::api::CodestralClient;
+use codestral_test_rs::daemon::{self, Daemon, DaemonRequest, DaemonResponse, DaemonStatus};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use test_log::test;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+#[test]
+fn test_protocol_format() {
+    assert_eq!(serde_json::to_value(DaemonRequest::Status).unwrap(), json!({ "op": "status" }));
+    let post: DaemonRequest =
+        serde_json::from_str(r#"{"op": "post", "url": "http://x", "api_key": "k", "body": {"a": 1}}"#).unwrap();
+    assert_eq!(post, DaemonRequest::Post { url: "http://x".to_string(), api_key: "k".to_string(), body: json!({ "a": 1 }) });
+
+    assert_eq!(serde_json::to_value(DaemonResponse::success(json!(1))).unwrap(), json!({ "ok": true, "result": 1 }));
+    let failure = DaemonResponse::failure("boom");
+    assert_eq!(serde_json::to_value(&failure).unwrap(), json!({ "ok": false, "error": "boom" }));
+    assert!(failure.into_result().unwrap_err().to_string().contains("boom"));
+}
+
+#[test(tokio::test)]
+async fn test_connection_answers_line_requests() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/v1/fim/completions")
+        .match_header("authorization", "Bearer caller-key")
+        .with_body(r#"{"id": "cmpl-1", "choices": []}"#)
+        .create_async()
+        .await;
+    let daemon = Arc::new(Daemon::new(Config::default()));
+    let (client, server) = tokio::io::duplex(4096);
+    let handle = tokio::spawn({
+        let daemon = daemon.clone();
+        async move { daemon.serve_connection(server).await }
+    });
+
+    let (read, mut write) = tokio::io::split(client);
+    let mut lines = BufReader::new(read).lines();
+    let post = json!({
+        "op": "post",
+        "url": format!("{}/v1/fim/completions", upstream.url()),
+        "api_key": "caller-key",
+        "body": { "prompt": "fn " }
+    });
+    for request in [post.to_string(), "not json".to_string(), r#"{"op": "status"}"#.to_string(), r#"{"op": "stop"}"#.to_string()] {
+        write.write_all(format!("{}\n", request).as_bytes()).await.unwrap();
+    }
+
+    let reply = |line: Option<String>| serde_json::from_str::<Value>(&line.unwrap()).unwrap();
+    assert_eq!(reply(lines.next_line().await.unwrap()), json!({ "ok": true, "result": { "id": "cmpl-1", "choices": [] } }));
+    assert_eq!(reply(lines.next_line().await.unwrap())["ok"], json!(false));
+    let status: DaemonStatus = serde_json::from_value(reply(lines.next_line().await.unwrap())["result"].clone()).unwrap();
+    assert_eq!((status.requests, status.pid), (1, std::process::id()));
+    assert_eq!(reply(lines.next_line().await.unwrap()), json!({ "ok": true, "result": null }));
+
+    // The connection ends after a stop request
+    handle.await.unwrap().unwrap();
+    mock.assert_async().await;
+}
+
+#[cfg(unix)]
+#[test(tokio::test)]
+async fn test_clients_use_running_daemon() {
+    let mut upstream = mockito::Server::new_async().await;
+    let mock = upstream
+        .mock("POST", "/v1/fim/completions")
+        .with_body(r#"{"id": "cmpl-2", "choices": [{"index": 0, "text": "1"}]}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        code_api_url: format!("{}/v1/fim/completions", upstream.url()),
+        daemon_socket: dir.path().join("daemon.sock").display().to_string(),
+        ..Config::default()
+    };
+    let socket = config.get_daemon_socket_path();
+
+    // A socket left behind by a crashed daemon is replaced
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    let listener = daemon::bind(&socket).unwrap();
+    assert!(daemon::bind(&socket).is_err());
+    let server = tokio::spawn({
+        let socket = socket.clone();
+        let daemon = Arc::new(Daemon::new(config.clone()));
+        async move { daemon.run(listener, &socket).await }
+    });
+
+    env::set_var("CODESTRAL_API_KEY", "mock-code-api-key");
+    let client = CodestralClient::new(config.clone()).unwrap();
+    let response = client.code_completion("fn ", "", 8).await.unwrap();
+    assert_eq!(response["id"], json!("cmpl-2"));
+
+    let status = daemon::send(&socket, &DaemonRequest::Status).await.unwrap().into_result().unwrap();
+    assert_eq!(status["requests"], json!(1));
+
+    daemon::send(&socket, &DaemonRequest::Stop).await.unwrap();
+    server.await.unwrap().unwrap();
+    assert!(!socket.exists());
+    mock.assert_async().await;
+
+    // Without a daemon the client sends requests itself
+    let direct = upstream.mock("POST", "/v1/fim/completions").with_body(r#"{"id": "cmpl-3"}"#).create_async().await;
+    assert_eq!(client.code_completion("fn ", "", 8).await.unwrap()["id"], json!("cmpl-3"));
+    direct.assert_async().await;
+}
+
+#[cfg(unix)]
+#[test(tokio::test)]
+async fn test_unresponsive_daemon_is_not_retried_directly() {
+    let dir = TempDir::new().unwrap();
+    let socket = dir.path().join("daemon.sock");
+    // Accepts connections but never answers
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    let hold = tokio::spawn(async move {
+        let mut streams = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            streams.push(stream);
+        }
+    });
+
+    let body = json!({ "prompt": "fn " });
+    // The daemon has the request, so it is not sent a second time directly
+    let result = daemon::try_post(Some(&socket), Duration::from_millis(100), "http://x", "k", &body).await;
+    assert!(result.unwrap().unwrap_err().to_string().contains("did not answer"));
+    assert!(daemon::try_post(None, Duration::from_millis(100), "http://x", "k", &body).await.is_none());
+    hold.abort();
+}
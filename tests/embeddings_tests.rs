@@ -0,0 +1,47 @@
+use codestral_test_rs::embeddings::{read_binary, write_binary, write_jsonl};
+use test_log::test;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_binary_round_trip() {
+    let embeddings = vec![vec![0.1, 0.2, 0.3], vec![-1.0, 0.0, 1.0]];
+    
+    let mut buffer = Vec::new();
+    write_binary(&mut buffer, &embeddings).expect("Failed to write binary");
+    
+    // Header is magic, version, count and dimension followed by the values
+    assert_eq!(&buffer[0..4], b"CEMB");
+    assert_eq!(buffer.len(), 16 + 6 * 4);
+    
+    let decoded = read_binary(&mut buffer.as_slice()).expect("Failed to read binary");
+    assert_eq!(decoded, embeddings);
+}
+
+#[test]
+fn test_binary_rejects_mismatched_dimensions() {
+    let embeddings = vec![vec![0.1, 0.2], vec![0.3]];
+    let mut buffer = Vec::new();
+    assert!(write_binary(&mut buffer, &embeddings).is_err());
+    
+    assert!(read_binary(&mut b"NOPE".as_slice()).is_err());
+}
+
+#[test]
+fn test_jsonl_output() {
+    let inputs = vec!["first".to_string(), "second".to_string()];
+    let embeddings = vec![vec![1.0], vec![2.0]];
+    
+    let mut buffer = Vec::new();
+    write_jsonl(&mut buffer, &inputs, &embeddings).expect("Failed to write jsonl");
+    
+    let lines: Vec<serde_json::Value> = String::from_utf8(buffer)
+        .expect("Invalid UTF-8")
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("Invalid JSON line"))
+        .collect();
+    
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["index"], 1);
+    assert_eq!(lines[1]["input"], "second");
+    assert_eq!(lines[1]["embedding"][0], 2.0);
+}
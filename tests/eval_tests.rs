@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use codestral_test_rs::error::{AppError, Result};
+use codestral_test_rs::eval::{Completion, CompletionProvider, EvalTask, MockProvider, check_completion, load_tasks, mock_provider_for, pass_at_k, run_eval};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+use test_log::test;
+
+fn task(id: &str, test_command: Option<&str>, expected_output: Option<&str>) -> EvalTask {
+    EvalTask {
+        id: id.to_string(),
+        prefix: format!("{} = ", id),
+        suffix: "\n".to_string(),
+        file: "answer.txt".to_string(),
+        files: BTreeMap::new(),
+        test_command: test_command.map(|c| c.to_string()),
+        expected_output: expected_output.map(|e| e.to_string()),
+        canonical_solution: Some("42".to_string()),
+    }
+}
+
+#[test]
+fn test_pass_at_k() {
+    assert_eq!(pass_at_k(1, 1, 1), 1.0);
+    assert_eq!(pass_at_k(1, 0, 1), 0.0);
+    assert!((pass_at_k(10, 3, 1) - 0.3).abs() < 1e-9);
+    // 1 - C(2, 2) / C(4, 2) = 1 - 1/6
+    assert!((pass_at_k(4, 2, 2) - 5.0 / 6.0).abs() < 1e-9);
+    assert_eq!(pass_at_k(5, 4, 2), 1.0);
+    assert_eq!(pass_at_k(0, 0, 1), 0.0);
+}
+
+#[test]
+fn test_load_tasks_applies_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tasks.jsonl");
+    fs::write(&path, "{\"id\": \"a\", \"prefix\": \"fn a() {\", \"suffix\": \"}\", \"expected_output\": \"1\"}\n\n").unwrap();
+    
+    let tasks = load_tasks(&path).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].file, "main.rs");
+    assert_eq!(tasks[0].test_command, None);
+    
+    fs::write(&path, "{\"id\": \"a\"}\n").unwrap();
+    assert!(load_tasks(&path).is_err());
+}
+
+#[test(tokio::test)]
+async fn test_check_completion_runs_command_in_task_dir() {
+    let timeout = Duration::from_secs(10);
+    let mut with_files = task("x", Some("cat answer.txt helper.txt"), Some("x = 42\nhelper"));
+    with_files.files.insert("helper.txt".to_string(), "helper\n".to_string());
+    
+    let (passed, output) = check_completion(&with_files, "42", timeout).await.unwrap();
+    assert!(passed, "output: {}", output);
+    
+    let (passed, _) = check_completion(&with_files, "41", timeout).await.unwrap();
+    assert!(!passed);
+    
+    let failing = task("x", Some("exit 1"), None);
+    assert!(!check_completion(&failing, "42", timeout).await.unwrap().0);
+    
+    let compared = task("x", None, Some("42"));
+    assert!(check_completion(&compared, " 42\n", timeout).await.unwrap().0);
+}
+
+#[test(tokio::test)]
+async fn test_check_completion_times_out() {
+    let slow = task("x", Some("sleep 5"), None);
+    let (passed, output) = check_completion(&slow, "42", Duration::from_millis(200)).await.unwrap();
+    assert!(!passed);
+    assert!(output.starts_with("Timed out"));
+}
+
+#[test(tokio::test)]
+async fn test_run_eval_with_mock_provider() {
+    let tasks = vec![
+        task("a", Some("grep -q 'a = 42' answer.txt"), None),
+        task("b", None, Some("7")),
+    ];
+    
+    let report = run_eval(&mock_provider_for(&tasks), &tasks, 2, 16, Duration::from_secs(10)).await.unwrap();
+    assert_eq!(report.model, "mock");
+    assert_eq!(report.tasks, 2);
+    assert_eq!(report.samples_per_task, 2);
+    assert_eq!(report.results[0].passed, 2);
+    assert_eq!(report.results[1].passed, 0);
+    assert_eq!(report.pass_at_1, 0.5);
+    assert_eq!(report.pass_at_k, 0.5);
+    assert_eq!(report.completion_tokens, 4);
+    
+    // Prefixes without an answer get an empty completion
+    let report = run_eval(&MockProvider::new(), &tasks[..1], 1, 16, Duration::from_secs(10)).await.unwrap();
+    assert_eq!(report.pass_at_1, 0.0);
+}
+
+struct FailingProvider;
+
+#[async_trait]
+impl CompletionProvider for FailingProvider {
+    fn name(&self) -> String {
+        "failing".to_string()
+    }
+
+    async fn complete(&self, _prefix: &str, _suffix: &str, _max_tokens: u32, _seed: u64) -> Result<Completion> {
+        Err(AppError::api("rate limited"))
+    }
+}
+
+#[test(tokio::test)]
+async fn test_run_eval_records_provider_errors() {
+    let tasks = vec![task("a", None, Some("7"))];
+    
+    let report = run_eval(&FailingProvider, &tasks, 2, 16, Duration::from_secs(10)).await.unwrap();
+    assert_eq!(report.results[0].passed, 0);
+    assert_eq!(report.results[0].samples.len(), 2);
+    assert!(report.results[0].samples[0].output.contains("rate limited"));
+}
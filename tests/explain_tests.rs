@@ -0,0 +1,67 @@
+use codestral_test_rs::explain::{Depth, Selection, default_template, parse_target};
+use codestral_test_rs::markdown::render_terminal;
+use pretty_assertions::assert_eq;
+use test_log::test;
+
+const CONTENT: &str = "line 1\nline 2\nline 3\nline 4\nline 5\nline 6\n";
+
+#[test]
+fn test_parse_target() {
+    assert_eq!(parse_target("src/lib.rs").unwrap(), ("src/lib.rs".to_string(), None));
+    assert_eq!(parse_target("src/lib.rs:10-20").unwrap(), ("src/lib.rs".to_string(), Some((10, 20))));
+    assert_eq!(parse_target("src/lib.rs:7").unwrap(), ("src/lib.rs".to_string(), Some((7, 7))));
+    assert_eq!(parse_target("C:notes.txt").unwrap(), ("C:notes.txt".to_string(), None));
+    assert!(parse_target("src/lib.rs:20-10").is_err());
+    assert!(parse_target("src/lib.rs:0-3").is_err());
+    assert!(parse_target("src/lib.rs:1-2-3").is_err());
+}
+
+#[test]
+fn test_selection_with_context() {
+    let selection = Selection::from_content("a.py", CONTENT, Some((3, 4)), 1).unwrap();
+    assert_eq!(selection.before, "line 2");
+    assert_eq!(selection.text, "line 3\nline 4");
+    assert_eq!(selection.after, "line 5");
+    
+    // Ranges past the end are clamped, whole files have no context
+    let selection = Selection::from_content("a.py", CONTENT, Some((5, 100)), 3).unwrap();
+    assert_eq!((selection.start, selection.end), (5, 6));
+    assert_eq!(selection.after, "");
+    let whole = Selection::from_content("a.py", CONTENT, None, 3).unwrap();
+    assert_eq!((whole.start, whole.end, whole.before.as_str()), (1, 6, ""));
+    
+    assert!(Selection::from_content("a.py", CONTENT, Some((9, 10)), 3).is_err());
+    assert!(Selection::from_content("a.py", "", None, 3).is_err());
+}
+
+#[test]
+fn test_default_template_renders_selection() {
+    let selection = Selection::from_content("src/app.py", CONTENT, Some((2, 2)), 1).unwrap();
+    let prompt = default_template().render(&selection.template_vars(Depth::Detailed), None).unwrap();
+    
+    assert!(prompt.contains("`src/app.py` (lines 2-2)"));
+    assert!(prompt.contains(Depth::Detailed.instruction()));
+    assert!(prompt.contains("Selected code:\n```python\nline 2\n```"));
+    assert!(prompt.contains("for context:\n```python\nline 1\n```"));
+}
+
+#[test]
+fn test_render_terminal_layout() {
+    let markdown = "# Overview\nThe `parse` function is **fast**.\n\n- first\n  * nested\n> note\n---\n```rust\nfn main() {}\n```";
+    let rendered = render_terminal(markdown, false);
+    assert_eq!(
+        rendered,
+        format!(
+            "Overview\nThe parse function is fast.\n\n  • first\n    • nested\n│ note\n{rule}\n── rust {tail}\n  fn main() {{}}\n{rule}\n",
+            rule = "─".repeat(40),
+            tail = "─".repeat(32)
+        )
+    );
+    
+    let styled = render_terminal("Use `x` and **y**", true);
+    assert!(styled.contains("\u{1b}["));
+    assert!(styled.contains('x') && styled.contains('y'));
+    
+    // Unmatched markers are kept as text
+    assert_eq!(render_terminal("2 * 3 and `open", false), "2 * 3 and `open\n");
+}
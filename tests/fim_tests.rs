@@ -0,0 +1,218 @@
+use codestral_test_rs::fim::{
+    BlockTruncation, Diagnostic, PostProcessOptions, bracket_errors, cargo_check_spliced, check_rust_syntax,
+    dedupe_candidates, feedback_prefix, find_crate_root, match_line_endings, parse_cargo_messages, postprocess,
+    rank_candidates, splice_at_cursor, strip_trailing_whitespace, suffix_overlap, syntax_valid, trim_suffix_overlap,
+    truncate_at_closing_brace, truncate_at_dedent,
+};
+use serde_json::json;
+use std::fs;
+use pretty_assertions::assert_eq;
+use test_log::test;
+
+#[test]
+fn test_dedupe_candidates_ignores_whitespace() {
+    let candidates = vec![
+        "a + b".to_string(),
+        "a +  b\n".to_string(),
+        "b + a".to_string(),
+    ];
+    assert_eq!(dedupe_candidates(candidates), vec!["a + b", "b + a"]);
+}
+
+#[test]
+fn test_bracket_errors() {
+    assert_eq!(bracket_errors("fn main() { let v = vec![1, 2]; }"), 0);
+    assert_eq!(bracket_errors("fn main() { (]"), 3);
+    // Brackets inside strings and comments are ignored
+    assert_eq!(bracket_errors("let s = \"(\\\"{\"; // [[\n"), 0);
+}
+
+#[test]
+fn test_suffix_overlap() {
+    assert_eq!(suffix_overlap("a + b\n}", "\n}\n"), 1);
+    assert_eq!(suffix_overlap("x.len()\n    }\n}", "    }\n}"), 3);
+    assert_eq!(suffix_overlap("a + b", "}"), 0);
+}
+
+#[test]
+fn test_syntax_valid() {
+    assert_eq!(syntax_valid("fn main() {}", "rust"), Some(true));
+    assert_eq!(syntax_valid("fn main( {}", "rs"), Some(false));
+    assert_eq!(syntax_valid("def f(): pass", "python"), None);
+}
+
+#[test]
+fn test_rank_candidates_prefers_fitting_completion() {
+    let prefix = "fn add(a: i32, b: i32) -> i32 {\n    ";
+    let suffix = "\n}\n";
+    let candidates = vec![
+        "a + b\n}".to_string(),
+        String::new(),
+        "(a + b".to_string(),
+        "a + b".to_string(),
+    ];
+    
+    let ranked = rank_candidates(&candidates, prefix, suffix, Some("rust"));
+    assert_eq!(ranked[0].text, "a + b");
+    assert_eq!(ranked[0].syntax_valid, Some(true));
+    assert_eq!(ranked[0].bracket_errors, 0);
+    
+    let overlapping = ranked.iter().find(|c| c.text == "a + b\n}").unwrap();
+    assert_eq!(overlapping.bracket_errors, 1);
+    assert_eq!(overlapping.suffix_overlap, 1);
+    assert_eq!(ranked.last().unwrap().text, "(a + b");
+}
+
+#[test]
+fn test_trim_suffix_overlap() {
+    assert_eq!(trim_suffix_overlap("a + b\n}\n", "\n}\n"), "a + b\n");
+    assert_eq!(trim_suffix_overlap("items.len());", ");\n"), "items.len()");
+    assert_eq!(trim_suffix_overlap("a + b", "}"), "a + b");
+    // Trailing newlines are kept when nothing overlaps, so lines don't merge
+    assert_eq!(trim_suffix_overlap("    let x = 1;\n", "    x\n}\n"), "    let x = 1;\n");
+    // Brackets the completion closes itself are not mistaken for the suffix's
+    assert_eq!(trim_suffix_overlap("{ 1 }", "\n}\n"), "{ 1 }");
+    assert_eq!(trim_suffix_overlap("    if x { y() }", "\n}"), "    if x { y() }");
+    assert_eq!(trim_suffix_overlap("if x {\n    y()\n}", "\n}\n"), "if x {\n    y()\n}");
+    // Whole repeated lines are still removed
+    assert_eq!(trim_suffix_overlap("let a = 1;\nreturn a;\n", "return a;\n}"), "let a = 1;\n");
+}
+
+#[test]
+fn test_truncate_at_closing_brace() {
+    let completion = "a + b\n}\n\nfn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n";
+    assert_eq!(truncate_at_closing_brace(completion), "a + b\n");
+    // Braces opened and closed inside the completion are kept
+    assert_eq!(truncate_at_closing_brace("if x { 1 } else { 2 }"), "if x { 1 } else { 2 }");
+    assert_eq!(truncate_at_closing_brace("format!(\"}}\")\n    }"), "format!(\"}}\")\n");
+}
+
+#[test]
+fn test_truncate_at_dedent() {
+    let prefix = "def total(items):\n    ";
+    let completion = "result = 0\n    for item in items:\n        result += item\n    return result\n\ndef other():\n    pass\n";
+    assert_eq!(
+        truncate_at_dedent(completion, prefix),
+        "result = 0\n    for item in items:\n        result += item\n    return result\n\n"
+    );
+}
+
+#[test]
+fn test_strip_trailing_whitespace() {
+    assert_eq!(strip_trailing_whitespace("let x = 1;   \n\tlet y = 2;\t\n"), "let x = 1;\n\tlet y = 2;\n");
+    assert_eq!(strip_trailing_whitespace("a \r\nb"), "a\r\nb");
+}
+
+#[test]
+fn test_match_line_endings() {
+    assert_eq!(match_line_endings("a\nb\r\nc", "fn f() {\r\n", "}\r\n"), "a\r\nb\r\nc");
+    assert_eq!(match_line_endings("a\r\nb", "fn f() {\n", "}\n"), "a\nb");
+}
+
+#[test]
+fn test_postprocess_pipeline() {
+    let options = PostProcessOptions {
+        trim_suffix_overlap: true,
+        block_truncation: BlockTruncation::Braces,
+        strip_trailing_whitespace: true,
+        normalize_line_endings: true,
+    };
+    let prefix = "fn add(a: i32, b: i32) -> i32 {\r\n    ";
+    let suffix = "\r\n}\r\n";
+    
+    assert_eq!(postprocess("a + b  \n}\n\nfn main() {}\n", prefix, suffix, &options), "a + b\r\n");
+    
+    let disabled = PostProcessOptions {
+        trim_suffix_overlap: false,
+        block_truncation: BlockTruncation::Off,
+        strip_trailing_whitespace: false,
+        normalize_line_endings: false,
+    };
+    assert_eq!(postprocess("a + b  \n}", prefix, suffix, &disabled), "a + b  \n}");
+}
+
+#[test]
+fn test_check_rust_syntax_reports_location() {
+    assert!(check_rust_syntax("fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").is_empty());
+    
+    let diagnostics = check_rust_syntax("fn add(a: i32, b: i32) -> i32 {\n    a + + ;\n}\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, "syntax");
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert!(diagnostics[0].is_error());
+}
+
+#[test]
+fn test_parse_cargo_messages_filters_by_file() {
+    let output = [
+        json!({ "reason": "compiler-artifact", "target": { "name": "demo" } }),
+        json!({ "reason": "compiler-message", "message": {
+            "level": "error",
+            "message": "mismatched types",
+            "spans": [{ "file_name": "src/lib.rs", "line_start": 3, "column_start": 5 }]
+        }}),
+        json!({ "reason": "compiler-message", "message": {
+            "level": "warning",
+            "message": "unused variable: `x`",
+            "spans": [{ "file_name": "src/other.rs", "line_start": 1, "column_start": 9 }]
+        }}),
+    ]
+    .iter()
+    .map(|v| v.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+    
+    let diagnostics = parse_cargo_messages(&output, "src/lib.rs");
+    assert_eq!(diagnostics, vec![Diagnostic {
+        level: "error".to_string(),
+        message: "mismatched types".to_string(),
+        line: Some(3),
+        column: Some(5),
+    }]);
+}
+
+#[test]
+fn test_splice_at_cursor() {
+    let file = "use std::fmt;\n\nfn f() -> i32 {\n    1\n}\n";
+    assert_eq!(
+        splice_at_cursor(file, "fn f() -> i32 {\n", "    let x = 0;\n", "    1\n}").unwrap(),
+        "use std::fmt;\n\nfn f() -> i32 {\n    let x = 0;\n    1\n}\n"
+    );
+    // Shells drop the trailing newline of `$(head ...)` prompts
+    assert_eq!(
+        splice_at_cursor(file, "use std::fmt;", " // x", "fn f()").unwrap(),
+        "use std::fmt; // x\n\nfn f() -> i32 {\n    1\n}\n"
+    );
+    assert_eq!(splice_at_cursor(file, "fn g()", "", ""), None);
+}
+
+#[test(tokio::test)]
+async fn test_cargo_check_spliced_leaves_file_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"demo\"\nedition = \"2021\"\n").unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    let file = dir.path().join("src/lib.rs");
+    fs::write(&file, "pub fn a() {}\n").unwrap();
+    assert_eq!(find_crate_root(&file), Some(fs::canonicalize(dir.path()).unwrap()));
+    
+    let diagnostics = cargo_check_spliced(&file, "pub fn a() -> i32 { \"x\" }\n", std::time::Duration::from_secs(120))
+        .await
+        .unwrap();
+    assert!(diagnostics.iter().any(|d| d.is_error() && d.line == Some(1)), "{:?}", diagnostics);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "pub fn a() {}\n");
+}
+
+#[test]
+fn test_feedback_prefix_lists_errors() {
+    let diagnostics = vec![Diagnostic {
+        level: "syntax".to_string(),
+        message: "expected expression".to_string(),
+        line: Some(2),
+        column: Some(9),
+    }];
+    
+    let prefix = feedback_prefix("fn f() -> i32 {\n    ", "+ 1", &diagnostics, "//");
+    assert!(prefix.contains("//     + 1\n"));
+    assert!(prefix.contains("// - line 2: expected expression\n"));
+    assert!(prefix.ends_with("\n\nfn f() -> i32 {\n    "));
+}
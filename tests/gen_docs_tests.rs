@@ -0,0 +1,106 @@
+use codestral_test_rs::gen_docs::{
+    UndocumentedItem, collect_rust_files, doc_fim_parts, doc_lines, find_undocumented, insert_docs, unified_patch,
+};
+use pretty_assertions::assert_eq;
+use std::fs;
+use test_log::test;
+
+const SOURCE: &str = "\
+/// Already documented
+pub fn documented() {}
+
+#[derive(Debug)]
+pub struct Config {
+    pub name: String,
+}
+
+fn private() {}
+
+pub(crate) fn internal() {}
+
+impl Config {
+    pub fn new() -> Self {
+        Config { name: String::new() }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+pub mod nested {
+    pub const LIMIT: usize = 3;
+}
+";
+
+fn names(items: &[UndocumentedItem]) -> Vec<(&str, &str, usize)> {
+    items.iter().map(|i| (i.kind, i.name.as_str(), i.line)).collect()
+}
+
+#[test]
+fn test_find_undocumented_public_items() {
+    let items = find_undocumented(SOURCE).unwrap();
+    assert_eq!(names(&items), vec![
+        ("struct", "Config", 4),
+        ("method", "new", 14),
+        ("mod", "nested", 25),
+        ("const", "LIMIT", 26),
+    ]);
+    assert_eq!(items[1].indent, "    ");
+    assert!(find_undocumented("pub fn broken(").is_err());
+}
+
+#[test]
+fn test_doc_fim_parts_open_a_comment_above_the_item() {
+    let items = find_undocumented(SOURCE).unwrap();
+    let (prefix, suffix) = doc_fim_parts(SOURCE, &items[1]);
+    assert!(prefix.ends_with("impl Config {\n    /// "));
+    assert!(suffix.starts_with("\n    pub fn new() -> Self {"));
+}
+
+#[test]
+fn test_doc_lines() {
+    assert_eq!(
+        doc_lines("Create an empty config\n    ///\n    /// The name is blank.\n    pub fn new() -> Self {", "    "),
+        vec!["    /// Create an empty config", "    ///", "    /// The name is blank."]
+    );
+    assert_eq!(doc_lines("Settings\n///\n", ""), vec!["/// Settings"]);
+    assert!(doc_lines("\npub struct Config", "").is_empty());
+}
+
+#[test]
+fn test_insert_docs_and_patch() {
+    let items = find_undocumented(SOURCE).unwrap();
+    
+    // Bottom-up insertion keeps earlier line numbers valid
+    let mut updated = SOURCE.to_string();
+    updated = insert_docs(&updated, &items[1], &["    /// Create an empty config".to_string()]);
+    updated = insert_docs(&updated, &items[0], &["/// Application settings".to_string()]);
+    
+    assert!(updated.contains("/// Application settings\n#[derive(Debug)]\npub struct Config {"));
+    assert!(updated.contains("impl Config {\n    /// Create an empty config\n    pub fn new()"));
+    assert!(updated.ends_with("}\n"));
+    assert_eq!(names(&find_undocumented(&updated).unwrap())[..2], [("mod", "nested", 27), ("const", "LIMIT", 28)]);
+    
+    let patch = unified_patch("./src/config.rs", SOURCE, &updated);
+    assert!(patch.starts_with("--- a/src/config.rs\n+++ b/src/config.rs\n"));
+    assert!(patch.contains("+/// Application settings\n"));
+    assert_eq!(patch.lines().filter(|l| l.starts_with('+') && !l.starts_with("+++")).count(), 2);
+}
+
+#[test]
+fn test_collect_rust_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "pub fn a() {}\n").unwrap();
+    fs::write(dir.path().join("README.md"), "# Readme\n").unwrap();
+    
+    let files = collect_rust_files(dir.path(), ".codestral-index.json").unwrap();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].0.ends_with("src/lib.rs"));
+    
+    let single = collect_rust_files(&dir.path().join("src/lib.rs"), ".codestral-index.json").unwrap();
+    assert_eq!(single[0].1, "pub fn a() {}\n");
+}
@@ -0,0 +1,130 @@
+use codestral_test_rs::gen_tests::{
+    TestTarget, TestOutcome, append_module, crate_name, extract_code, find_function, module_path, parse_test_results,
+    test_prompt, tests_name, wrap_test_module,
+};
+use codestral_test_rs::fim::check_rust_syntax;
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::Path;
+use test_log::test;
+
+const SOURCE: &str = r#"use std::fmt;
+
+/// A 2D point
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub enum Unused {
+    A,
+}
+
+/// Manhattan distance between two points
+pub fn distance(a: &Point, b: &Point) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+pub struct Parser;
+
+impl Parser {
+    pub fn parse(&self, input: &str) -> Option<i32> {
+        input.trim().parse().ok()
+    }
+}
+
+pub struct Lexer;
+
+impl Lexer {
+    pub fn parse(&self) {}
+}
+"#;
+
+#[test]
+fn test_find_free_function_with_related_types() {
+    let function = find_function(SOURCE, "distance").unwrap();
+    assert_eq!(function.self_type, None);
+    assert_eq!((function.start_line, function.end_line), (13, 16));
+    assert!(function.source.starts_with("/// Manhattan distance"));
+    assert!(function.source.ends_with("}"));
+    assert_eq!(function.related_types.len(), 1);
+    assert!(function.related_types[0].starts_with("/// A 2D point\npub struct Point {"));
+}
+
+#[test]
+fn test_find_method() {
+    let method = find_function(SOURCE, "Parser::parse").unwrap();
+    assert_eq!(method.self_type.as_deref(), Some("Parser"));
+    assert!(method.source.trim_start().starts_with("pub fn parse(&self, input: &str)"));
+    assert_eq!(method.related_types, vec!["pub struct Parser;"]);
+    
+    let error = find_function(SOURCE, "parse").unwrap_err().to_string();
+    assert!(error.contains("Parser::parse, Lexer::parse"), "{}", error);
+    assert!(find_function(SOURCE, "missing").is_err());
+    assert!(find_function("fn broken(", "broken").is_err());
+}
+
+#[test]
+fn test_names_and_paths() {
+    let method = find_function(SOURCE, "Parser::parse").unwrap();
+    assert_eq!(tests_name(&method), "parser_parse_generated_tests");
+    
+    let root = Path::new("/repo");
+    assert_eq!(module_path(Path::new("/repo/src/fim/ranking.rs"), root).as_deref(), Some("fim::ranking"));
+    assert_eq!(module_path(Path::new("/repo/src/fim/mod.rs"), root).as_deref(), Some("fim"));
+    assert_eq!(module_path(Path::new("/repo/src/lib.rs"), root), None);
+    assert_eq!(module_path(Path::new("/elsewhere/a.rs"), root), None);
+    
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\n").unwrap();
+    assert_eq!(crate_name(dir.path()).unwrap(), "my_crate");
+}
+
+#[test]
+fn test_prompt_mentions_target() {
+    let function = find_function(SOURCE, "distance").unwrap();
+    
+    let module = test_prompt(&function, &TestTarget::Module { module_name: "x".to_string() });
+    assert!(module.contains("use super::*;"));
+    assert!(module.contains("Related types:\n```rust\n/// A 2D point"));
+    
+    let integration = test_prompt(&function, &TestTarget::Integration {
+        crate_name: "my_crate".to_string(),
+        module_path: Some("geometry".to_string()),
+    });
+    assert!(integration.contains("imported from `my_crate::geometry`"));
+}
+
+#[test]
+fn test_generated_module_is_appended() {
+    let reply = "Here are the tests:\n```rust\nuse super::*;\n\n#[test]\nfn same_point() {\n    let p = Point { x: 1, y: 2 };\n    assert_eq!(distance(&p, &p), 0);\n}\n```\nDone.";
+    let code = extract_code(reply);
+    assert!(code.starts_with("use super::*;"));
+    assert!(code.ends_with("}"));
+    assert_eq!(extract_code("#[test]\nfn bare() {}\n"), "#[test]\nfn bare() {}");
+    
+    let module = wrap_test_module("distance_generated_tests", &code);
+    assert_eq!(module.matches("use super::*;").count(), 1);
+    assert!(module.starts_with("#[cfg(test)]\nmod distance_generated_tests {\n    use super::*;\n"));
+    
+    let content = append_module(SOURCE, &module);
+    assert!(content.starts_with(SOURCE.trim_end()));
+    assert!(check_rust_syntax(&content).is_empty());
+}
+
+#[test]
+fn test_parse_test_results() {
+    let output = "\
+running 3 tests
+test geometry::distance_generated_tests::same_point ... ok
+test geometry::distance_generated_tests::negative ... FAILED
+test geometry::distance_generated_tests::slow ... ignored
+
+failures:
+test result: FAILED. 1 passed; 1 failed; 1 ignored
+";
+    assert_eq!(parse_test_results(output), vec![
+        TestOutcome { name: "geometry::distance_generated_tests::same_point".to_string(), passed: true },
+        TestOutcome { name: "geometry::distance_generated_tests::negative".to_string(), passed: false },
+    ]);
+}
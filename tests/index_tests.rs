@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use codestral_test_rs::error::Result;
+use codestral_test_rs::index::{CodeIndex, Embedder, chunk_source, cosine_similarity, index_path};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use test_log::test;
+use pretty_assertions::assert_eq;
+
+const INDEX_FILE: &str = ".codestral-index.json";
+
+/// Deterministic bag-of-words embedder that counts how many inputs it embedded
+#[derive(Default)]
+struct FakeEmbedder {
+    inputs_embedded: AtomicUsize,
+}
+
+#[async_trait]
+impl Embedder for FakeEmbedder {
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.inputs_embedded.fetch_add(inputs.len(), Ordering::SeqCst);
+        Ok(inputs.iter().map(|input| {
+            let mut vector = vec![0.0; 64];
+            for word in input.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+                let mut hasher = DefaultHasher::new();
+                word.to_lowercase().hash(&mut hasher);
+                vector[(hasher.finish() % 64) as usize] += 1.0;
+            }
+            vector
+        }).collect())
+    }
+}
+
+#[test]
+fn test_chunk_source_splits_on_functions() {
+    let source = "use std::fmt;\n\n/// Adds numbers\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\npub fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n";
+    let chunks = chunk_source(source, 60);
+    
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].start_line, 1);
+    // Doc comments stay attached to the function they describe
+    assert_eq!(chunks[1].start_line, 3);
+    assert!(chunks[1].text.starts_with("/// Adds numbers"));
+    assert_eq!(chunks[2].start_line, 8);
+    assert_eq!(chunks[2].end_line, 10);
+}
+
+#[test]
+fn test_chunk_source_windows_long_segments() {
+    let source = (1..=25).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+    let chunks = chunk_source(&source, 10);
+    
+    let ranges: Vec<(usize, usize)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+    assert_eq!(ranges, vec![(1, 10), (11, 20), (21, 25)]);
+}
+
+#[test]
+fn test_cosine_similarity() {
+    assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+    assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+    assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+}
+
+#[test(tokio::test)]
+async fn test_build_update_and_search() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let root = dir.path();
+    
+    fs::write(root.join("parser.rs"), "fn parse_tokens(input: &str) -> Vec<Token> {\n    tokenize input\n}\n").unwrap();
+    fs::write(root.join("network.rs"), "fn open_socket(addr: &str) -> Socket {\n    connect socket addr\n}\n").unwrap();
+    fs::write(root.join("ignored.rs"), "fn parse_tokens_again() {}\n").unwrap();
+    fs::write(root.join(".gitignore"), "ignored.rs\n").unwrap();
+    
+    let embedder = FakeEmbedder::default();
+    let (index, stats) = CodeIndex::build(root, &embedder, 60, INDEX_FILE).await.expect("Failed to build index");
+    
+    // .gitignore'd and hidden files are not indexed
+    assert_eq!(stats.files_embedded, 2);
+    assert!(index.files.contains_key("parser.rs"));
+    assert!(!index.files.contains_key("ignored.rs"));
+    
+    let hits = index.search(&embedder, "parse tokens input", 2).await.expect("Search failed");
+    assert_eq!(hits[0].path, "parser.rs");
+    assert_eq!(hits[0].start_line, 1);
+    assert!(hits[0].score > hits[1].score);
+    
+    // Saving and reloading preserves the index
+    let path = index_path(root, INDEX_FILE);
+    index.save(&path).expect("Failed to save index");
+    let mut reloaded = CodeIndex::load(&path).expect("Failed to load index");
+    assert_eq!(reloaded.files.len(), 2);
+    
+    // Only the changed file is re-embedded on update
+    fs::write(root.join("network.rs"), "fn close_socket(sock: Socket) {}\n").unwrap();
+    fs::remove_file(root.join("parser.rs")).unwrap();
+    let before = embedder.inputs_embedded.load(Ordering::SeqCst);
+    let stats = reloaded.update(root, &embedder, INDEX_FILE).await.expect("Failed to update index");
+    
+    assert_eq!(stats.files_embedded, 1);
+    assert_eq!(stats.files_removed, 1);
+    assert_eq!(embedder.inputs_embedded.load(Ordering::SeqCst) - before, 1);
+    assert!(reloaded.files["network.rs"].chunks[0].text.contains("close_socket"));
+}
@@ -0,0 +1,60 @@
+use codestral_test_rs::input::{Attachment, LazyInput, language_for, read_message_file, with_attachments};
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::io::Cursor;
+use test_log::test;
+
+#[test]
+fn test_lazy_input_reads_once() {
+    let mut input = LazyInput::new(Cursor::new("diff --git a/x b/x\n"));
+    assert_eq!(input.resolve("plain message").unwrap(), "plain message");
+    assert_eq!(input.resolve("-").unwrap(), "diff --git a/x b/x\n");
+    
+    // Later reads see the same content
+    assert_eq!(input.read().unwrap(), "diff --git a/x b/x\n");
+}
+
+#[test]
+fn test_read_message_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("message.txt");
+    fs::write(&path, "Explain this").unwrap();
+    
+    let mut input = LazyInput::new(Cursor::new("from stdin"));
+    assert_eq!(read_message_file(path.to_str().unwrap(), &mut input).unwrap(), "Explain this");
+    assert_eq!(read_message_file("-", &mut input).unwrap(), "from stdin");
+    assert!(read_message_file("/no/such/file", &mut input).is_err());
+}
+
+#[test]
+fn test_language_for() {
+    assert_eq!(language_for("src/main.rs"), "rust");
+    assert_eq!(language_for("app/Main.PY"), "python");
+    assert_eq!(language_for("Dockerfile"), "dockerfile");
+    assert_eq!(language_for("changes.patch"), "diff");
+    assert_eq!(language_for("notes"), "");
+}
+
+#[test]
+fn test_attachments_are_fenced_with_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let rust = dir.path().join("lib.rs");
+    fs::write(&rust, "pub fn answer() -> u32 {\n    42\n}\n").unwrap();
+    let markdown = dir.path().join("README.md");
+    fs::write(&markdown, "```bash\ncargo run\n```\n").unwrap();
+    
+    let rust = Attachment::load(rust.to_str().unwrap()).unwrap();
+    let markdown = Attachment::load(markdown.to_str().unwrap()).unwrap();
+    
+    let message = with_attachments("Review these files\n", &[rust.clone(), markdown.clone()]);
+    assert_eq!(
+        message,
+        format!(
+            "Review these files\n\n{}:\n```rust\npub fn answer() -> u32 {{\n    42\n}}\n```\n\n{}:\n````markdown\n```bash\ncargo run\n```\n````",
+            rust.path, markdown.path
+        )
+    );
+    
+    assert_eq!(with_attachments("Only text", &[]), "Only text");
+    assert!(Attachment::load(dir.path().to_str().unwrap()).is_err());
+}
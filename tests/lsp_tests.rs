@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use codestral_test_rs::Config;
+use codestral_test_rs::error::Result;
+use codestral_test_rs::eval::{Completion, CompletionProvider};
+use codestral_test_rs::lsp::{CompletionCache, LspOptions, fim_parts, offset_at, position_at, serve, word_before};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use test_log::test;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream, ReadHalf, WriteHalf};
+use tower_lsp::lsp_types::Position;
+
+const URI: &str = "file:///tmp/main.rs";
+const TEXT: &str = "fn main() {\n    let answer = \n}\n";
+
+/// Provider completing any request with a fixed text, counting calls
+struct CountingProvider {
+    completion: String,
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl CompletionProvider for CountingProvider {
+    fn name(&self) -> String {
+        "counting".to_string()
+    }
+
+    async fn complete(&self, _prefix: &str, _suffix: &str, _max_tokens: u32, _seed: u64) -> Result<Completion> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(Completion { text: self.completion.clone(), completion_tokens: 2, total_tokens: 10 })
+    }
+}
+
+/// Minimal LSP client speaking Content-Length framed JSON-RPC
+struct TestClient {
+    reader: BufReader<ReadHalf<DuplexStream>>,
+    writer: WriteHalf<DuplexStream>,
+}
+
+impl TestClient {
+    async fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.writer.write_all(frame.as_bytes()).await.unwrap();
+    }
+
+    async fn request(&mut self, id: u64, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await;
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params })).await;
+    }
+
+    async fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).await.unwrap();
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Wait for the response to a request, skipping notifications
+    async fn response(&mut self, id: u64) -> Value {
+        loop {
+            let message = self.receive().await;
+            if message["id"] == json!(id) && message.get("method").is_none() {
+                return message;
+            }
+        }
+    }
+
+    async fn completion_at(&mut self, id: u64, method: &str, line: u32, character: u32) {
+        let params = json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } });
+        self.request(id, method, params).await;
+    }
+
+    async fn change(&mut self, version: i32, text: &str) {
+        let params = json!({ "textDocument": { "uri": URI, "version": version }, "contentChanges": [{ "text": text }] });
+        self.notify("textDocument/didChange", params).await;
+    }
+}
+
+fn options(debounce_ms: u64) -> LspOptions {
+    LspOptions::from_config(&Config { lsp_debounce_ms: debounce_ms, ..Config::default() })
+}
+
+/// Start a server and initialize it with `TEXT` open
+async fn start(provider: Arc<CountingProvider>, options: LspOptions) -> TestClient {
+    let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+    let (server_read, server_write) = tokio::io::split(server_end);
+    tokio::spawn(serve(server_read, server_write, provider, options));
+
+    let (read, writer) = tokio::io::split(client_end);
+    let mut client = TestClient { reader: BufReader::new(read), writer };
+
+    client.request(1, "initialize", json!({ "capabilities": {} })).await;
+    let response = client.response(1).await;
+    assert_eq!(response["result"]["capabilities"]["experimental"]["inlineCompletionProvider"], json!({}));
+    client.notify("initialized", json!({})).await;
+    client.notify("textDocument/didOpen", json!({
+        "textDocument": { "uri": URI, "languageId": "rust", "version": 1, "text": TEXT }
+    })).await;
+    client
+}
+
+fn provider(completion: &str) -> Arc<CountingProvider> {
+    Arc::new(CountingProvider { completion: completion.to_string(), calls: AtomicUsize::new(0) })
+}
+
+#[test]
+fn test_offset_and_position() {
+    let text = "aé😀b\nsecond";
+    // 😀 is two UTF-16 code units
+    assert_eq!(offset_at(text, Position::new(0, 2)), "aé".len());
+    assert_eq!(offset_at(text, Position::new(0, 4)), "aé😀".len());
+    assert_eq!(offset_at(text, Position::new(0, 99)), "aé😀b".len());
+    assert_eq!(offset_at(text, Position::new(1, 3)), "aé😀b\nsec".len());
+    assert_eq!(offset_at(text, Position::new(5, 0)), text.len());
+    assert_eq!(position_at(text, "aé😀b\nsec".len()), Position::new(1, 3));
+    assert_eq!(position_at(text, "aé😀".len()), Position::new(0, 4));
+}
+
+#[test]
+fn test_fim_parts_cut_at_lines() {
+    let text = "line one\nline two\nline three\nline four\n";
+    let offset = text.find("three").unwrap();
+    let (prefix, suffix) = fim_parts(text, offset, 100, 100);
+    assert_eq!(prefix, "line one\nline two\nline ");
+    assert_eq!(suffix, "three\nline four\n");
+
+    // Truncated parts keep whole lines
+    let (prefix, suffix) = fim_parts(text, offset, 12, 12);
+    assert_eq!(prefix, "line ");
+    assert_eq!(suffix, "three\n");
+}
+
+#[test]
+fn test_word_before() {
+    assert_eq!(word_before("let foo_ba", 10), "foo_ba");
+    assert_eq!(word_before("x.", 2), "");
+}
+
+#[test]
+fn test_completion_cache() {
+    let mut cache = CompletionCache::new(2);
+    cache.insert("let x = ", "\n", "compute(1);");
+    assert_eq!(cache.get("let x = ", "\n"), Some("compute(1);".to_string()));
+    // Typing the start of the completion reuses the rest of it
+    assert_eq!(cache.get("let x = comp", "\n"), Some("ute(1);".to_string()));
+    assert_eq!(cache.get("let x = other", "\n"), None);
+    assert_eq!(cache.get("let x = ", "\n}"), None);
+    assert_eq!(cache.get("let x = compute(1);", "\n"), None);
+
+    cache.insert("a", "", "1");
+    cache.insert("b", "", "2");
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get("let x = ", "\n"), None);
+}
+
+#[test(tokio::test)]
+async fn test_completion_request() {
+    let provider = provider("42;");
+    let mut client = start(provider.clone(), options(0)).await;
+
+    client.completion_at(2, "textDocument/completion", 1, 17).await;
+    let response = client.response(2).await;
+    let item = &response["result"][0];
+    assert_eq!(item["label"], json!("42;"));
+    assert_eq!(item["detail"], json!("counting"));
+    assert_eq!(item["textEdit"]["newText"], json!("42;"));
+    assert_eq!(item["textEdit"]["range"]["start"], json!({ "line": 1, "character": 17 }));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+}
+
+#[test(tokio::test)]
+async fn test_inline_completion_request() {
+    let provider = provider("42;");
+    let mut client = start(provider.clone(), options(0)).await;
+
+    client.completion_at(2, "textDocument/inlineCompletion", 1, 17).await;
+    let response = client.response(2).await;
+    assert_eq!(response["result"], json!({
+        "items": [{
+            "insertText": "42;",
+            "range": { "start": { "line": 1, "character": 17 }, "end": { "line": 1, "character": 17 } }
+        }]
+    }));
+}
+
+#[test(tokio::test)]
+async fn test_newer_request_supersedes_pending_one() {
+    let provider = provider("42;");
+    let mut client = start(provider.clone(), options(200)).await;
+
+    client.completion_at(2, "textDocument/inlineCompletion", 1, 17).await;
+    client.completion_at(3, "textDocument/inlineCompletion", 1, 17).await;
+    let first = client.response(2).await;
+    let second = client.response(3).await;
+    assert_eq!(first["result"], Value::Null);
+    assert_eq!(second["result"]["items"][0]["insertText"], json!("42;"));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+}
+
+#[test(tokio::test)]
+async fn test_edit_cancels_pending_request() {
+    let provider = provider("42;");
+    let mut client = start(provider.clone(), options(200)).await;
+
+    client.completion_at(2, "textDocument/completion", 1, 17).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    client.change(2, "fn main() {\n    let answer = 4\n}\n").await;
+    let response = client.response(2).await;
+    assert_eq!(response["result"], Value::Null);
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 0);
+}
+
+#[test(tokio::test)]
+async fn test_typing_ahead_uses_cache() {
+    let provider = provider("42;");
+    let mut client = start(provider.clone(), options(0)).await;
+
+    client.completion_at(2, "textDocument/inlineCompletion", 1, 17).await;
+    client.response(2).await;
+
+    client.change(2, "fn main() {\n    let answer = 4\n}\n").await;
+    client.completion_at(3, "textDocument/inlineCompletion", 1, 18).await;
+    let response = client.response(3).await;
+    assert_eq!(response["result"]["items"][0]["insertText"], json!("2;"));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+}
@@ -1,6 +1,5 @@
-use codestral_test_rs::models::{CompletionRequest, ChatMessage, ChatCompletionRequest, ApiResponse};
+use codestral_test_rs::models::{CompletionRequest, ChatMessage, ChatCompletionRequest, ApiResponse, SamplingParams};
 use test_log::test;
-use serde_json;
 
 #[test]
 fn test_completion_request_serialization() {
@@ -10,6 +9,7 @@ fn test_completion_request_serialization() {
         suffix: "}".to_string(),
         max_tokens: 100,
         temperature: 0.0,
+        ..Default::default()
     };
     
     let serialized = serde_json::to_string(&request).expect("Failed to serialize");
@@ -28,6 +28,7 @@ fn test_chat_message_serialization() {
     let message = ChatMessage {
         role: "user".to_string(),
         content: "Hello, world!".to_string(),
+        ..Default::default()
     };
     
     let serialized = serde_json::to_string(&message).expect("Failed to serialize");
@@ -44,6 +45,7 @@ fn test_chat_completion_request_serialization() {
         ChatMessage {
             role: "user".to_string(),
             content: "Hello, how are you?".to_string(),
+            ..Default::default()
         }
     ];
     
@@ -52,6 +54,7 @@ fn test_chat_completion_request_serialization() {
         messages,
         max_tokens: Some(100),
         temperature: 0.7,
+        ..Default::default()
     };
     
     let serialized = serde_json::to_string(&request).expect("Failed to serialize");
@@ -74,6 +77,7 @@ fn test_api_response_creation() {
         created: 1234567890,
         finish_reason: "stop".to_string(),
         content: Some("Hello, world!".to_string()),
+        choices: vec!["Hello, world!".to_string()],
         completion_tokens: 3,
         total_tokens: 10,
     };
@@ -87,3 +91,100 @@ fn test_api_response_creation() {
     assert_eq!(response.completion_tokens, 3);
     assert_eq!(response.total_tokens, 10);
 }
+
+#[test]
+fn test_tool_call_message_deserialization() {
+    // Assistant messages with tool calls may have null content and object arguments
+    let message: ChatMessage = serde_json::from_value(serde_json::json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [
+            { "id": "call_1", "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" } },
+            { "id": "call_2", "type": "function", "function": { "name": "get_time", "arguments": { "zone": "UTC" } } }
+        ]
+    })).expect("Failed to deserialize");
+    
+    assert_eq!(message.content, "");
+    let calls = message.tool_calls.expect("Missing tool calls");
+    assert_eq!(calls[0].call_type, "function");
+    assert_eq!(calls[0].function.arguments, "{\"city\":\"Paris\"}");
+    assert_eq!(calls[1].function.arguments, "{\"zone\":\"UTC\"}");
+}
+
+#[test]
+fn test_tool_fields_omitted_when_unset() {
+    let request = ChatCompletionRequest {
+        model: "mistral-large-latest".to_string(),
+        messages: vec![ChatMessage::user("Hi")],
+        ..Default::default()
+    };
+    
+    let json = serde_json::to_value(&request).expect("Failed to serialize");
+    assert!(json.get("tools").is_none());
+    assert!(json.get("tool_choice").is_none());
+    assert!(json["messages"][0].get("tool_calls").is_none());
+    
+    let tool_message = serde_json::to_value(ChatMessage::tool("call_1", "get_weather", "sunny")).expect("Failed to serialize");
+    assert_eq!(tool_message["role"], "tool");
+    assert_eq!(tool_message["tool_call_id"], "call_1");
+    assert_eq!(tool_message["name"], "get_weather");
+}
+
+#[test]
+fn test_sampling_params_omitted_when_unset() {
+    let request = CompletionRequest {
+        model: "codestral-latest".to_string(),
+        prompt: "fn main() {".to_string(),
+        suffix: "}".to_string(),
+        max_tokens: 10,
+        temperature: 0.2,
+        ..Default::default()
+    };
+    
+    let json = serde_json::to_value(&request).expect("Failed to serialize");
+    let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+    assert_eq!(keys, vec!["max_tokens", "model", "prompt", "suffix", "temperature"]);
+}
+
+#[test]
+fn test_sampling_params_flattened_into_chat_request() {
+    let request = ChatCompletionRequest {
+        model: "mistral-large-latest".to_string(),
+        messages: vec![ChatMessage::user("Hi")],
+        temperature: 0.7,
+        sampling: SamplingParams {
+            top_p: Some(0.9),
+            stop: Some(vec!["\n\n".to_string()]),
+            random_seed: Some(42),
+            min_tokens: Some(5),
+            presence_penalty: Some(0.5),
+            frequency_penalty: Some(-0.5),
+            n: Some(3),
+            safe_prompt: Some(true),
+        },
+        ..Default::default()
+    };
+    
+    let json = serde_json::to_value(&request).expect("Failed to serialize");
+    assert_eq!(json["top_p"], 0.9);
+    assert_eq!(json["stop"][0], "\n\n");
+    assert_eq!(json["random_seed"], 42);
+    assert_eq!(json["min_tokens"], 5);
+    assert_eq!(json["presence_penalty"], 0.5);
+    assert_eq!(json["frequency_penalty"], -0.5);
+    assert_eq!(json["n"], 3);
+    assert_eq!(json["safe_prompt"], true);
+    assert!(json.get("sampling").is_none());
+}
+
+#[test]
+fn test_sampling_params_merge_prefers_overrides() {
+    let defaults = SamplingParams { top_p: Some(0.9), random_seed: Some(1), ..Default::default() };
+    let overrides = SamplingParams { random_seed: Some(7), n: Some(2), ..Default::default() };
+    
+    let merged = defaults.merge(&overrides);
+    assert_eq!(merged.top_p, Some(0.9));
+    assert_eq!(merged.random_seed, Some(7));
+    assert_eq!(merged.n, Some(2));
+    assert_eq!(merged.stop, None);
+}
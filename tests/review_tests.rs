@@ -0,0 +1,126 @@
+use codestral_test_rs::review::{
+    DiffSource, Finding, ReviewResponse, Severity, findings_schema, git_diff, render_sarif, render_text,
+    review_prompt, sort_findings, split_diff,
+};
+use codestral_test_rs::structured::schema_errors;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::fs;
+use std::process::Command;
+use test_log::test;
+
+const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,4 @@ fn main() {
+ let a = 1;
+-let b = 2;
++let b = 3;
++let c = a + b;
+ println!();
+diff --git a/old.txt b/old.txt
+deleted file mode 100644
+--- a/old.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+fn finding(file: &str, line: usize, severity: Severity) -> Finding {
+    Finding { file: file.to_string(), line, severity, message: format!("issue at {}", line) }
+}
+
+#[test]
+fn test_split_diff_numbers_new_lines_and_skips_deleted_files() {
+    let chunks = split_diff(DIFF, 1000).unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].path, "src/lib.rs");
+    assert_eq!(chunks[0].language, "rust");
+    assert_eq!(
+        chunks[0].diff,
+        "...\n   10   let a = 1;\n      - let b = 2;\n   11 + let b = 3;\n   12 + let c = a + b;\n   13   println!();\n"
+    );
+    
+    let prompt = review_prompt(&chunks[0]);
+    assert!(prompt.contains("File: src/lib.rs\n```rust\n"));
+    
+    assert!(split_diff("  \n", 1000).unwrap().is_empty());
+}
+
+#[test]
+fn test_split_diff_respects_token_budget() {
+    let mut diff = String::from("--- a/big.py\n+++ b/big.py\n@@ -1,0 +1,40 @@\n");
+    for i in 0..40 {
+        diff.push_str(&format!("+value_{:02} = compute({})\n", i, i));
+    }
+    
+    let chunks = split_diff(&diff, 100).unwrap();
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|c| c.path == "big.py" && c.diff.len() / 4 <= 100));
+    
+    // Line numbers continue across pieces of the same hunk
+    let last = chunks.last().unwrap();
+    assert!(last.diff.trim_end().ends_with("40 + value_39 = compute(39)"), "{}", last.diff);
+}
+
+#[test]
+fn test_split_diff_without_text_changes() {
+    let binary = "diff --git a/logo.png b/logo.png\nindex 1111111..2222222 100644\nBinary files a/logo.png and b/logo.png differ\n";
+    assert!(split_diff(binary, 1000).unwrap().is_empty());
+}
+
+#[test]
+fn test_findings_schema_validates_responses() {
+    let schema = findings_schema();
+    let valid = json!({ "findings": [{ "file": "a.rs", "line": 3, "severity": "warning", "message": "unused" }] });
+    assert!(schema_errors(&schema, &valid).unwrap().is_empty());
+    
+    let parsed: ReviewResponse = serde_json::from_value(valid).unwrap();
+    assert_eq!(parsed.findings[0].severity, Severity::Warning);
+    
+    let invalid = json!({ "findings": [{ "file": "a.rs", "line": 0, "severity": "fatal", "message": "x" }] });
+    assert_eq!(schema_errors(&schema, &invalid).unwrap().len(), 2);
+}
+
+#[test]
+fn test_render_text_and_sarif() {
+    let mut findings = vec![
+        finding("src/b.rs", 4, Severity::Info),
+        finding("src/a.rs", 9, Severity::Warning),
+        finding("src/a.rs", 2, Severity::Error),
+    ];
+    sort_findings(&mut findings);
+    
+    assert_eq!(
+        render_text(&findings),
+        "src/a.rs:2: error: issue at 2\nsrc/a.rs:9: warning: issue at 9\nsrc/b.rs:4: info: issue at 4\n"
+    );
+    assert_eq!(render_text(&[]), "No findings.\n");
+    
+    let sarif = render_sarif(&findings);
+    assert_eq!(sarif["version"], "2.1.0");
+    let results = sarif["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[2]["level"], "note");
+    assert_eq!(results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "src/a.rs");
+    assert_eq!(results[0]["locations"][0]["physicalLocation"]["region"]["startLine"], 2);
+}
+
+#[test]
+fn test_git_diff_reads_staged_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git").args(args).current_dir(dir.path()).output().unwrap().status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+    git(&["init", "-q"]);
+    fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+    git(&["add", "main.rs"]);
+    
+    let staged = git_diff(dir.path(), &DiffSource::Staged).unwrap();
+    assert!(staged.contains("+++ b/main.rs"));
+    assert!(git_diff(dir.path(), &DiffSource::WorkingTree).unwrap().is_empty());
+    assert!(git_diff(dir.path(), &DiffSource::Range("nope..HEAD".to_string())).is_err());
+}
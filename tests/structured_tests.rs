@@ -0,0 +1,45 @@
+use codestral_test_rs::structured::{check_schema, correction_prompt, parse_json_content, schema_errors};
+use serde_json::json;
+use test_log::test;
+use pretty_assertions::assert_eq;
+
+fn person_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "age": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["name", "age"]
+    })
+}
+
+#[test]
+fn test_parse_json_content_strips_fences() {
+    assert_eq!(parse_json_content("{\"a\": 1}").unwrap(), json!({"a": 1}));
+    assert_eq!(parse_json_content("```json\n{\"a\": 1}\n```").unwrap(), json!({"a": 1}));
+    assert_eq!(parse_json_content("  ```\n[1, 2]\n```  ").unwrap(), json!([1, 2]));
+    assert!(parse_json_content("not json").is_err());
+}
+
+#[test]
+fn test_schema_errors() {
+    let schema = person_schema();
+    
+    assert!(schema_errors(&schema, &json!({"name": "Ada", "age": 36})).unwrap().is_empty());
+    
+    let errors = schema_errors(&schema, &json!({"name": "Ada", "age": -1})).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("/age:"));
+    
+    let errors = schema_errors(&schema, &json!({"age": "old"})).unwrap();
+    assert_eq!(errors.len(), 2);
+    
+    assert!(check_schema(&json!({"type": "no-such-type"})).is_err());
+}
+
+#[test]
+fn test_correction_prompt_lists_errors() {
+    let prompt = correction_prompt(&["/age: -1 is less than the minimum of 0".to_string()]);
+    assert!(prompt.contains("- /age: -1 is less than the minimum of 0\n"));
+}
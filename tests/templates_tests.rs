@@ -0,0 +1,131 @@
+use codestral_test_rs::Config;
+use codestral_test_rs::templates::{
+    Placeholder, Template, TemplateDef, TemplateLibrary, TemplateSource, create_template, parse_vars, placeholders,
+};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::fs;
+use test_log::test;
+
+fn template(text: &str) -> Template {
+    Template {
+        name: "test".to_string(),
+        def: TemplateDef { text: text.to_string(), ..Default::default() },
+        source: TemplateSource::Config,
+    }
+}
+
+#[test]
+fn test_placeholders() {
+    assert_eq!(
+        placeholders("Review {{ lang }} code {{style|concise}} {{file:src/lib.rs}} {{stdin}}"),
+        vec![
+            Placeholder::Variable { name: "lang".to_string(), default: None },
+            Placeholder::Variable { name: "style".to_string(), default: Some("concise".to_string()) },
+            Placeholder::File("src/lib.rs".to_string()),
+            Placeholder::Stdin,
+        ]
+    );
+}
+
+#[test]
+fn test_parse_vars() {
+    let vars = parse_vars(&["lang=rust".to_string(), "query=a=b".to_string()]).unwrap();
+    assert_eq!(vars["lang"], "rust");
+    assert_eq!(vars["query"], "a=b");
+    assert!(parse_vars(&["novalue".to_string()]).is_err());
+    assert!(parse_vars(&["=x".to_string()]).is_err());
+}
+
+#[test]
+fn test_render_with_defaults_and_stdin() {
+    let mut t = template("Review this {{lang}} code ({{tone|briefly}}, {{focus}}):\n{{stdin}}");
+    t.def.defaults.insert("focus".to_string(), "safety".to_string());
+    
+    let vars = parse_vars(&["lang=rust".to_string()]).unwrap();
+    assert_eq!(t.render(&vars, Some("fn main() {}")).unwrap(), "Review this rust code (briefly, safety):\nfn main() {}");
+    assert!(t.uses_stdin());
+    assert!(t.render(&vars, None).is_err());
+    
+    // Explicit values take precedence over defaults
+    let vars = parse_vars(&["lang=go".to_string(), "focus=style".to_string(), "tone=harshly".to_string()]).unwrap();
+    assert_eq!(t.render(&vars, Some("")).unwrap(), "Review this go code (harshly, style):\n");
+}
+
+#[test]
+fn test_render_reports_all_missing_variables() {
+    let t = template("{{a}} {{b}} {{a}} {{c|x}}");
+    assert_eq!(t.missing_variables(&BTreeMap::new()), vec!["a", "b"]);
+    assert_eq!(t.variables(), vec!["a", "b", "c"]);
+    
+    let error = t.render(&BTreeMap::new(), None).unwrap_err().to_string();
+    assert!(error.contains("a, b"), "{}", error);
+}
+
+#[test]
+fn test_render_includes_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snippet.rs");
+    fs::write(&path, "fn included() {}").unwrap();
+    
+    let t = template(&format!("Code:\n{{{{file:{}}}}}", path.display()));
+    assert_eq!(t.render(&BTreeMap::new(), None).unwrap(), "Code:\nfn included() {}");
+    
+    let missing = template("{{file:/no/such/file}}");
+    assert!(missing.render(&BTreeMap::new(), None).is_err());
+}
+
+#[test]
+fn test_library_loads_config_and_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("explain.txt"), "Explain {{input}}").unwrap();
+    fs::write(dir.path().join("review.toml"), "description = \"From file\"\ntext = \"Review {{input}}\"\n").unwrap();
+    
+    let mut config = Config { templates_dir: dir.path().display().to_string(), ..Config::default() };
+    config.templates.insert("review".to_string(), TemplateDef { text: "inline".to_string(), ..Default::default() });
+    config.templates.insert("inline".to_string(), TemplateDef { text: "Only inline".to_string(), ..Default::default() });
+    
+    let library = TemplateLibrary::load(&config).unwrap();
+    let names: Vec<&str> = library.list().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["explain", "inline", "review"]);
+    
+    // Files override inline templates of the same name
+    let review = library.get("review").unwrap();
+    assert_eq!(review.def.description.as_deref(), Some("From file"));
+    assert_eq!(review.source, TemplateSource::File(dir.path().join("review.toml")));
+    assert_eq!(library.get("inline").unwrap().source, TemplateSource::Config);
+    assert!(library.get("missing").is_err());
+}
+
+#[test]
+fn test_create_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let templates = dir.path().join("templates");
+    
+    let path = create_template(&templates, "review", Some("Code review")).unwrap();
+    assert_eq!(path, templates.join("review.toml"));
+    
+    let config = Config { templates_dir: templates.display().to_string(), ..Config::default() };
+    let library = TemplateLibrary::load(&config).unwrap();
+    assert_eq!(library.get("review").unwrap().def.description.as_deref(), Some("Code review"));
+    
+    assert!(create_template(&templates, "review", None).is_err());
+    assert!(create_template(&templates, "../escape", None).is_err());
+}
+
+#[test]
+fn test_inline_templates_round_trip_through_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    
+    let mut config = Config::default();
+    config.templates.insert("review".to_string(), TemplateDef {
+        description: Some("Review".to_string()),
+        text: "Review {{input}}".to_string(),
+        defaults: BTreeMap::from([("lang".to_string(), "rust".to_string())]),
+    });
+    config.save_to_file(&path).unwrap();
+    
+    let loaded = Config::load_from_file(&path).unwrap();
+    assert_eq!(loaded.templates, config.templates);
+}
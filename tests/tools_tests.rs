@@ -0,0 +1,233 @@
+use codestral_test_rs::error::AppError;
+use codestral_test_rs::models::{FunctionCall, ToolCall};
+use codestral_test_rs::tools::ToolRegistry;
+use serde_json::json;
+use test_log::test;
+use pretty_assertions::assert_eq;
+
+fn tool_call(name: &str, arguments: &str) -> ToolCall {
+    ToolCall {
+        id: "call_1".to_string(),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        },
+    }
+}
+
+fn registry() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    registry
+        .register(
+            "add",
+            "Add two numbers",
+            json!({
+                "type": "object",
+                "properties": { "a": { "type": "number" }, "b": { "type": "number" } },
+                "required": ["a", "b"]
+            }),
+            |args| {
+                let a = args["a"].as_f64().ok_or_else(|| AppError::cli_usage("a must be a number"))?;
+                let b = args["b"].as_f64().ok_or_else(|| AppError::cli_usage("b must be a number"))?;
+                Ok((a + b).to_string())
+            },
+        )
+        .register("ping", "Reply with pong", json!({ "type": "object", "properties": {} }), |_| Ok("pong".to_string()));
+    registry
+}
+
+#[test]
+fn test_registry_definitions() {
+    let registry = registry();
+    let definitions = registry.definitions();
+    
+    assert_eq!(registry.len(), 2);
+    assert_eq!(definitions[0].tool_type, "function");
+    assert_eq!(definitions[0].function.name, "add");
+    assert_eq!(definitions[0].function.parameters["required"], json!(["a", "b"]));
+}
+
+#[test]
+fn test_registry_calls_closures() {
+    let registry = registry();
+    
+    assert_eq!(registry.call(&tool_call("add", r#"{"a": 2, "b": 3}"#)).unwrap(), "5");
+    // Empty arguments are treated as an empty object
+    assert_eq!(registry.call(&tool_call("ping", "")).unwrap(), "pong");
+    assert!(registry.call(&tool_call("missing", "{}")).is_err());
+    assert!(registry.call(&tool_call("add", "not json")).is_err());
+}
+
+#[test]
+fn test_invoke_captures_errors() {
+    let registry = registry();
+    
+    let ok = registry.invoke(&tool_call("add", r#"{"a": 1, "b": 1}"#));
+    assert!(!ok.is_error);
+    assert_eq!(ok.arguments, json!({"a": 1, "b": 1}));
+    
+    let failed = registry.invoke(&tool_call("add", r#"{"a": "x", "b": 1}"#));
+    assert!(failed.is_error);
+    assert!(failed.result.starts_with("Error:"));
+}
+
+fn workspace(dir: &std::path::Path, confirm: codestral_test_rs::tools::ConfirmFn) -> std::sync::Arc<codestral_test_rs::tools::Workspace> {
+    use codestral_test_rs::tools::Workspace;
+    let allowlist = vec!["echo *".to_string(), "cargo check".to_string()];
+    std::sync::Arc::new(Workspace::new(dir, allowlist, std::time::Duration::from_secs(10), confirm).expect("Failed to open workspace"))
+}
+
+#[test]
+fn test_workspace_confines_paths() {
+    use codestral_test_rs::tools::auto_approve;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+    let ws = workspace(dir.path(), auto_approve());
+    
+    assert!(ws.resolve("a.txt").is_ok());
+    assert!(ws.resolve("sub/../a.txt").is_ok());
+    assert!(ws.resolve("../outside.txt").is_err());
+    assert!(ws.resolve("/etc/passwd").is_err());
+    
+    assert_eq!(ws.read_file("a.txt", Some(2), Some(2)).unwrap(), "    2 two");
+    assert!(ws.read_file("../a.txt", None, None).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspace_rejects_symlink_escapes() {
+    use codestral_test_rs::tools::auto_approve;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let outside = tempfile::tempdir().expect("Failed to create temp dir");
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+    std::os::unix::fs::symlink(outside.path().join("missing.txt"), dir.path().join("dangling")).unwrap();
+    let ws = workspace(dir.path(), auto_approve());
+    
+    assert!(ws.resolve("link/pwned.txt").is_err());
+    assert!(ws.resolve("link/new/dir/pwned.txt").is_err());
+    assert!(ws.resolve("dangling").is_err());
+    assert!(ws.resolve("new/dir/file.txt").is_ok());
+    
+    let patch = "--- /dev/null\n+++ b/link/pwned.txt\n@@ -0,0 +1 @@\n+pwned\n";
+    assert!(ws.apply_patch(patch).is_err());
+    assert!(!outside.path().join("pwned.txt").exists());
+}
+
+#[test]
+fn test_workspace_tools_through_registry() {
+    use codestral_test_rs::tools::auto_approve;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), "fn alpha() {}\nfn beta() {}\n").unwrap();
+    
+    let mut registry = ToolRegistry::new();
+    workspace(dir.path(), auto_approve()).register_tools(&mut registry);
+    assert_eq!(registry.len(), 5);
+    
+    assert_eq!(registry.call(&tool_call("list_dir", r#"{"path": "."}"#)).unwrap(), "src/");
+    assert_eq!(registry.call(&tool_call("grep", r#"{"pattern": "fn b"}"#)).unwrap(), "src/lib.rs:2: fn beta() {}");
+    
+    let patch = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn alpha() {}\n-fn beta() {}\n+fn gamma() {}\n";
+    let result = registry.call(&tool_call("apply_patch", &json!({ "patch": patch }).to_string())).unwrap();
+    assert_eq!(result, "patched src/lib.rs");
+    assert_eq!(std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(), "fn alpha() {}\nfn gamma() {}\n");
+    
+    let output = registry.call(&tool_call("run_command", r#"{"command": "echo hello"}"#)).unwrap();
+    assert!(output.contains("exit status: 0"));
+    assert!(output.contains("hello"));
+    assert!(registry.call(&tool_call("run_command", r#"{"command": "rm -rf ."}"#)).is_err());
+}
+
+#[test]
+fn test_apply_patch_renames_and_refuses_to_overwrite() {
+    use codestral_test_rs::tools::auto_approve;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(dir.path().join("old.txt"), "a\nb\n").unwrap();
+    let ws = workspace(dir.path(), auto_approve());
+    
+    let rename = "diff --git a/old.txt b/new.txt\nsimilarity index 50%\nrename from old.txt\nrename to new.txt\n--- a/old.txt\n+++ b/new.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n";
+    assert_eq!(ws.apply_patch(rename).unwrap(), "patched new.txt\ndeleted old.txt");
+    assert_eq!(std::fs::read_to_string(dir.path().join("new.txt")).unwrap(), "a\nc\n");
+    assert!(!dir.path().join("old.txt").exists());
+    
+    // A patch creating a file never replaces one that exists
+    let create = "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+replaced\n";
+    assert!(ws.apply_patch(create).unwrap_err().to_string().contains("already exists"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("new.txt")).unwrap(), "a\nc\n");
+}
+
+#[test]
+fn test_command_allowlist_rejects_extra_arguments() {
+    use codestral_test_rs::tools::auto_approve;
+    
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let ws = workspace(dir.path(), auto_approve());
+    
+    // Exact entries accept no extra arguments at all
+    let rejected = ws.run_command("cargo check --config build.rustc-wrapper=/tmp/x").unwrap_err();
+    assert!(rejected.to_string().contains("not allowed"));
+    assert!(ws.run_command("cargo check --manifest-path /elsewhere/Cargo.toml").is_err());
+    assert!(ws.run_command("cargo check extra").is_err());
+    
+    // Entries ending in `*` accept extra arguments, but no flags
+    assert!(ws.run_command("echo one two").unwrap().contains("one two"));
+    assert!(ws.run_command("echo -e hi").is_err());
+    assert!(ws.run_command("echoes").is_err());
+}
+
+#[test]
+fn test_declined_tool_calls_do_not_run() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    std::fs::write(dir.path().join("keep.txt"), "keep\n").unwrap();
+    
+    let mut registry = ToolRegistry::new();
+    workspace(dir.path(), std::sync::Arc::new(|_: &str, _: &serde_json::Value| false)).register_tools(&mut registry);
+    
+    let patch = "--- a/keep.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-keep\n";
+    let invocation = registry.invoke(&tool_call("apply_patch", &json!({ "patch": patch }).to_string()));
+    assert!(invocation.is_error);
+    assert!(invocation.result.contains("declined"));
+    assert!(dir.path().join("keep.txt").exists());
+}
+
+#[test]
+fn test_parse_and_apply_unified_diff() {
+    use codestral_test_rs::tools::patch::{apply_hunks, parse_unified_diff};
+    
+    let diff = "diff --git a/x.txt b/x.txt\n--- a/x.txt\n+++ b/x.txt\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n@@ -8,2 +8,3 @@\n h\n i\n+j\n--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+first\n+second\n";
+    let patches = parse_unified_diff(diff).expect("Failed to parse diff");
+    
+    assert_eq!(patches.len(), 2);
+    assert_eq!(patches[0].path(), "x.txt");
+    assert_eq!(patches[0].hunks.len(), 2);
+    assert_eq!(patches[1].old_path, None);
+    
+    // Hunks still apply when line numbers are off by a few lines
+    let original = "x\na\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let patched = apply_hunks(original, &patches[0].hunks).expect("Failed to apply hunks");
+    assert_eq!(patched, "x\na\nb\nC\nd\ne\nf\ng\nh\ni\nj\n");
+    
+    assert_eq!(apply_hunks("", &patches[1].hunks).unwrap(), "first\nsecond\n");
+    assert!(apply_hunks("unrelated\n", &patches[0].hunks).is_err());
+}
+
+#[test]
+fn test_parse_unified_diff_removing_dash_lines() {
+    use codestral_test_rs::tools::patch::{HunkLine, parse_unified_diff};
+    
+    // Removing a SQL comment gives a hunk line starting with `--- `
+    let diff = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,2 +1,1 @@\n--- old comment\n SELECT 1;\n--- a/next.sql\n+++ b/next.sql\n@@ -1 +1 @@\n-a\n+b\n";
+    let patches = parse_unified_diff(diff).expect("Failed to parse diff");
+    
+    assert_eq!(patches.len(), 2);
+    assert_eq!(patches[0].hunks[0].lines, vec![
+        HunkLine::Remove("-- old comment".to_string()),
+        HunkLine::Context("SELECT 1;".to_string()),
+    ]);
+    assert_eq!(patches[1].path(), "next.sql");
+}
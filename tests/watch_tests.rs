@@ -0,0 +1,105 @@
+use codestral_test_rs::Config;
+use codestral_test_rs::eval::MockProvider;
+use codestral_test_rs::fim::PostProcessOptions;
+use codestral_test_rs::watch::{FileWatcher, MarkerSite, complete_markers, find_markers, ignore_rules, marker_fim_parts, sidecar_path};
+use pretty_assertions::assert_eq;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use test_log::test;
+
+const CODE: &str = "\
+fn add(a: i32, b: i32) -> i32 {
+    // @complete
+}
+
+fn label() -> &'static str {
+    \"// @complete\"
+}
+
+# TODO(ai): parse the header
+";
+
+fn markers() -> Vec<String> {
+    Config::default().watch_markers
+}
+
+#[test]
+fn test_find_markers_in_comments_only() {
+    let sites = find_markers(CODE, &markers());
+    assert_eq!(sites, vec![
+        MarkerSite { line: 1, indent: "    ".to_string(), comment: "//".to_string(), instruction: None },
+        MarkerSite { line: 8, indent: String::new(), comment: "#".to_string(), instruction: Some("parse the header".to_string()) },
+    ]);
+
+    let block = find_markers("  /* @complete sort the list */\n", &markers());
+    assert_eq!(block[0].instruction.as_deref(), Some("sort the list"));
+}
+
+#[test]
+fn test_marker_fim_parts() {
+    let sites = find_markers(CODE, &markers());
+
+    let (prefix, suffix) = marker_fim_parts(CODE, &sites[0]);
+    assert_eq!(prefix, "fn add(a: i32, b: i32) -> i32 {\n    ");
+    assert!(suffix.starts_with("\n}\n\nfn label()"));
+    assert!(suffix.ends_with("parse the header\n"));
+
+    // The instruction stays as a plain comment above the generated code
+    let (prefix, suffix) = marker_fim_parts(CODE, &sites[1]);
+    assert!(prefix.ends_with("}\n\n# parse the header\n"));
+    assert_eq!(suffix, "\n");
+}
+
+#[test(tokio::test)]
+async fn test_complete_markers() {
+    let sites = find_markers(CODE, &markers());
+    let (first_prefix, _) = marker_fim_parts(CODE, &sites[0]);
+    let provider = MockProvider::new().with_answer(&first_prefix, "a + b\n");
+    let options = PostProcessOptions::from_config(&Config::default());
+
+    let fill = complete_markers(CODE, &markers(), &provider, 64, 0, &options).await.unwrap();
+    assert_eq!((fill.filled, fill.unfilled), (1, 1));
+    assert!(fill.content.starts_with("fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"));
+    // The unanswered marker is left in place
+    assert!(fill.content.ends_with("# TODO(ai): parse the header\n"));
+}
+
+#[test]
+fn test_sidecar_path() {
+    assert_eq!(sidecar_path(Path::new("src/lib.rs")), Path::new("src/lib.rs.suggestion.patch"));
+}
+
+#[test]
+fn test_ignore_rules() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+    let rules = ignore_rules(dir.path(), &["generated/".to_string()]).unwrap();
+
+    let ignored = |path: &str| rules.matched_path_or_any_parents(path, false).is_ignore();
+    assert!(ignored("debug.log"));
+    assert!(ignored("generated/api.rs"));
+    assert!(ignored("target/debug/build.rs"));
+    assert!(ignored("src/lib.rs.suggestion.patch"));
+    assert!(!ignored("src/lib.rs"));
+}
+
+#[test(tokio::test)]
+async fn test_watcher_batches_saved_files() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("skip")).unwrap();
+    let mut watcher = FileWatcher::new(dir.path(), &["skip/".to_string()], Duration::from_millis(200)).unwrap();
+
+    fs::write(dir.path().join("skip/ignored.rs"), "// @complete\n").unwrap();
+    fs::write(dir.path().join("main.rs"), "// @complete\n").unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}\n// @complete\n").unwrap();
+
+    let batch = tokio::time::timeout(Duration::from_secs(10), watcher.next_batch()).await.unwrap().unwrap();
+    assert_eq!(batch, vec![watcher.root().join("main.rs")]);
+
+    let path = watcher.root().join("main.rs");
+    assert!(watcher.mark_processed(&path, "fn main() {}\n"));
+    assert!(!watcher.mark_processed(&path, "fn main() {}\n"));
+    assert!(watcher.mark_processed(&path, "fn main() { run() }\n"));
+}